@@ -1,9 +1,10 @@
 use super::processor_config::ProcessorConfig;
-use crate::steps::processor::ContractProcessor;
+use crate::steps::{backfill_processor::BackfillProcessor, processor::ContractProcessor};
 use anyhow::Result;
 use aptos_indexer_processor_sdk::aptos_indexer_transaction_stream::TransactionStreamConfig;
 use aptos_indexer_processor_sdk_server_framework::RunnableConfig;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 pub const QUERY_DEFAULT_RETRIES: u32 = 5;
 pub const QUERY_DEFAULT_RETRY_DELAY_MS: u64 = 500;
@@ -12,19 +13,43 @@ pub const QUERY_DEFAULT_RETRY_DELAY_MS: u64 = 500;
 #[serde(deny_unknown_fields)]
 pub struct IndexerProcessorConfig {
     pub processor_config: ProcessorConfig,
-    pub transaction_stream_config: TransactionStreamConfig,
+    pub transaction_stream_config: TransactionSourceConfig,
     pub db_config: DbConfig,
     pub contract_config: ContractConfig,
+    // Last version to process, inclusive. The processor exits once it is stored, for
+    // backfills over a fixed range. Runs forever when unset.
+    #[serde(default)]
+    pub ending_version: Option<u64>,
+    // Splits [starting_version, ending_version] across parallel pipelines instead of
+    // running the live processor
+    #[serde(default)]
+    pub backfill_config: Option<BackfillConfig>,
+    // Writes every batch of the transaction stream to disk, for replay from files
+    #[serde(default)]
+    pub record_config: Option<RecordConfig>,
+    #[serde(default)]
+    pub pricing_config: PricingConfig,
+    #[serde(default)]
+    pub alert_config: AlertConfig,
+    #[serde(default)]
+    pub mev_config: MevConfig,
+    #[serde(default)]
+    pub wash_trading_config: WashTradingConfig,
+    #[serde(default)]
+    pub health_config: HealthConfig,
+    #[serde(default)]
+    pub shutdown_config: ShutdownConfig,
 }
 
 #[async_trait::async_trait]
 impl RunnableConfig for IndexerProcessorConfig {
     async fn run(&self) -> Result<()> {
-        match self.processor_config {
-            ProcessorConfig::ContractProcessor => {
-                let events_processor = ContractProcessor::new(self.clone()).await?;
-                events_processor.run_processor().await
-            }
+        // Every processor runs the same pipeline over the domains of its ProcessorConfig
+        let events_processor = ContractProcessor::new(self.clone()).await?;
+        if self.backfill_config.is_some() {
+            BackfillProcessor::new(events_processor).run().await
+        } else {
+            events_processor.run_processor().await
         }
     }
 
@@ -40,6 +65,55 @@ impl RunnableConfig for IndexerProcessorConfig {
     }
 }
 
+/// Where transactions come from: the gRPC transaction stream, or local files when
+/// `source_path` is set.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TransactionSourceConfig {
+    File(FileTransactionSourceConfig),
+    Grpc(TransactionStreamConfig),
+}
+
+impl TransactionSourceConfig {
+    pub fn starting_version(&self) -> Option<u64> {
+        match self {
+            Self::File(config) => config.starting_version,
+            Self::Grpc(config) => config.starting_version,
+        }
+    }
+
+    pub fn request_ending_version(&self) -> Option<u64> {
+        match self {
+            Self::File(config) => config.request_ending_version,
+            Self::Grpc(config) => config.request_ending_version,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileTransactionSourceConfig {
+    // A JSON or protobuf file of transactions, optionally gzipped, or a directory of them
+    // replayed in file name order
+    pub source_path: PathBuf,
+    #[serde(default)]
+    pub starting_version: Option<u64>,
+    #[serde(default)]
+    pub request_ending_version: Option<u64>,
+    // Transactions per batch handed to the extractor
+    #[serde(default = "FileTransactionSourceConfig::default_batch_size")]
+    pub batch_size: usize,
+    // Chain the files were recorded on, checked against ledger_infos when set
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+}
+
+impl FileTransactionSourceConfig {
+    pub const fn default_batch_size() -> usize {
+        1_000
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct DbConfig {
@@ -47,12 +121,84 @@ pub struct DbConfig {
     // Size of the pool for writes/reads to the DB. Limits maximum number of queries in flight
     #[serde(default = "DbConfig::default_db_pool_size")]
     pub db_pool_size: u32,
+    #[serde(default)]
+    pub batch_commit_mode: BatchCommitMode,
+    // Times a storer query is run again after an error known to have been rolled back, a
+    // serialization failure, a deadlock or a pool timeout. Closed or reset connections are
+    // not retried, since the commit may have gone through and counter upserts would be
+    // applied twice. They fail the batch, which is replayed from the saved version on restart
+    #[serde(default = "DbConfig::default_query_retries")]
+    pub query_retries: u32,
+    // Delay before the first retry, doubled on every further attempt up to
+    // query_max_retry_delay_ms and jittered
+    #[serde(default = "DbConfig::default_query_retry_delay_ms")]
+    pub query_retry_delay_ms: u64,
+    #[serde(default = "DbConfig::default_query_max_retry_delay_ms")]
+    pub query_max_retry_delay_ms: u64,
 }
 
 impl DbConfig {
     pub const fn default_db_pool_size() -> u32 {
         50
     }
+
+    pub const fn default_query_retries() -> u32 {
+        QUERY_DEFAULT_RETRIES
+    }
+
+    pub const fn default_query_retry_delay_ms() -> u64 {
+        QUERY_DEFAULT_RETRY_DELAY_MS
+    }
+
+    pub const fn default_query_max_retry_delay_ms() -> u64 {
+        10_000
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchCommitMode {
+    // Every table is written in its own transactions, in chunks over several connections. A
+    // failed batch can be left partly written until it is replayed
+    #[default]
+    Chunked,
+    // The whole batch commits in a single transaction on one connection
+    Atomic,
+    // Atomic, and the processor status moves to the end of the batch in the same
+    // transaction, so a committed batch is never replayed
+    AtomicWithCursor,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackfillConfig {
+    // Number of pipelines, each indexing its own share of the range
+    #[serde(default = "BackfillConfig::default_workers")]
+    pub workers: u64,
+}
+
+impl BackfillConfig {
+    pub const fn default_workers() -> u64 {
+        4
+    }
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            workers: Self::default_workers(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordConfig {
+    pub output_dir: PathBuf,
+    // Only transactions sent by, emitting events of or changing the state of these addresses
+    // are recorded. Every transaction is recorded when empty.
+    #[serde(default)]
+    pub addresses: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -60,3 +206,369 @@ impl DbConfig {
 pub struct ContractConfig {
     pub contract_address: String,
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StablecoinConfig {
+    pub address: String,
+    pub decimals: u8,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PricingConfig {
+    // Tokens pinned to 1 USD, every other price is routed from these through indexed pools
+    #[serde(default = "PricingConfig::default_stablecoins")]
+    pub stablecoins: Vec<StablecoinConfig>,
+    // Maximum number of pools between a stablecoin and a priced token
+    #[serde(default = "PricingConfig::default_max_hops")]
+    pub max_hops: usize,
+    // Pools whose priced side holds less than this are ignored when routing
+    #[serde(default = "PricingConfig::default_min_liquidity_usd")]
+    pub min_liquidity_usd: f64,
+    // Chain seconds between two routings over all pools, 0 routes on every batch
+    #[serde(default = "PricingConfig::default_refresh_interval_secs")]
+    pub refresh_interval_secs: i64,
+}
+
+impl PricingConfig {
+    pub fn default_stablecoins() -> Vec<StablecoinConfig> {
+        vec![
+            // USDC (native fungible asset)
+            StablecoinConfig {
+                address: "0xbae207659db88bea0cbead6da0ed00aac12edcdda169e591cd41c94180b46f3b"
+                    .to_string(),
+                decimals: 6,
+            },
+            // USDt (native fungible asset)
+            StablecoinConfig {
+                address: "0x357b0b74bc833e95a115ad22604854d6b0fca151cecd94111770e5d6ffc9dc2b"
+                    .to_string(),
+                decimals: 6,
+            },
+        ]
+    }
+
+    pub const fn default_max_hops() -> usize {
+        3
+    }
+
+    pub const fn default_min_liquidity_usd() -> f64 {
+        1_000.0
+    }
+
+    pub const fn default_refresh_interval_secs() -> i64 {
+        60
+    }
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            stablecoins: Self::default_stablecoins(),
+            max_hops: Self::default_max_hops(),
+            min_liquidity_usd: Self::default_min_liquidity_usd(),
+            refresh_interval_secs: Self::default_refresh_interval_secs(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    // A swap is flagged once it crosses either the USD or the percent-of-TVL threshold
+    #[serde(default = "AlertConfig::default_large_swap_usd")]
+    pub large_swap_usd: f64,
+    #[serde(default = "AlertConfig::default_large_swap_tvl_percent")]
+    pub large_swap_tvl_percent: f64,
+    // Flagged swaps crossing either of these are raised as critical instead of warning
+    #[serde(default = "AlertConfig::default_critical_swap_usd")]
+    pub critical_swap_usd: f64,
+    #[serde(default = "AlertConfig::default_critical_swap_tvl_percent")]
+    pub critical_swap_tvl_percent: f64,
+    // User defined rules, evaluated against the pools touched by each batch
+    #[serde(default)]
+    pub rules: Vec<AlertRuleConfig>,
+    // Where new alerts are pushed besides the alerts table
+    #[serde(default)]
+    pub sinks: Vec<AlertSinkConfig>,
+}
+
+impl AlertConfig {
+    pub const fn default_large_swap_usd() -> f64 {
+        100_000.0
+    }
+
+    pub const fn default_large_swap_tvl_percent() -> f64 {
+        2.0
+    }
+
+    pub const fn default_critical_swap_usd() -> f64 {
+        1_000_000.0
+    }
+
+    pub const fn default_critical_swap_tvl_percent() -> f64 {
+        10.0
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            large_swap_usd: Self::default_large_swap_usd(),
+            large_swap_tvl_percent: Self::default_large_swap_tvl_percent(),
+            critical_swap_usd: Self::default_critical_swap_usd(),
+            critical_swap_tvl_percent: Self::default_critical_swap_tvl_percent(),
+            rules: vec![],
+            sinks: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRuleConfig {
+    // Unique name of the rule, part of the dedup key of every alert it raises
+    pub name: String,
+    #[serde(default = "AlertRuleConfig::default_severity")]
+    pub severity: String,
+    // Minimum time between two alerts of this rule for the same pool
+    #[serde(default = "AlertRuleConfig::default_cooldown_secs")]
+    pub cooldown_secs: i64,
+    // Restricts the rule to these pools, empty means every pool
+    #[serde(default)]
+    pub pools: Vec<String>,
+    pub condition: AlertCondition,
+}
+
+impl AlertRuleConfig {
+    pub fn default_severity() -> String {
+        "warning".to_string()
+    }
+
+    pub const fn default_cooldown_secs() -> i64 {
+        3_600
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    // USD volume of the current hour above `multiplier` times the trailing hourly average
+    VolumeSpike { multiplier: f64, trailing_hours: i64 },
+    // Active liquidity down at least `percent` from its peak over the last `window_minutes`
+    LiquidityDrop { percent: f64, window_minutes: i64 },
+    AprAbove { apr: f64 },
+    // Absolute pool price change over `window` of at least `percent`
+    PriceMove { percent: f64, window: PriceChangeWindow },
+    // A pool was created with `token` on either side
+    NewPool { token: String },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum PriceChangeWindow {
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "24h")]
+    OneDay,
+    #[serde(rename = "7d")]
+    SevenDays,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, tag = "type", rename_all = "snake_case")]
+pub enum AlertSinkConfig {
+    // Logs every new alert through tracing
+    Log,
+    // POSTs signed JSON payloads through a durable outbox
+    Webhook(WebhookSinkConfig),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSinkConfig {
+    // Unique endpoint name, keys the outbox and the delivery status
+    pub name: String,
+    pub url: String,
+    // Shared secret for the HMAC-SHA256 signature header
+    pub secret: String,
+    // Alert types to deliver, empty means every alert
+    #[serde(default)]
+    pub alert_types: Vec<String>,
+    // Indexed events to deliver besides alerts
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+    #[serde(default = "WebhookSinkConfig::default_max_attempts")]
+    pub max_attempts: i32,
+    // Delay before the first retry, doubled on every further attempt up to max_backoff_ms
+    #[serde(default = "WebhookSinkConfig::default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "WebhookSinkConfig::default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "WebhookSinkConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+    // How often the outbox is polled for due deliveries
+    #[serde(default = "WebhookSinkConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+impl WebhookSinkConfig {
+    pub const fn default_max_attempts() -> i32 {
+        10
+    }
+
+    pub const fn default_initial_backoff_ms() -> u64 {
+        1_000
+    }
+
+    pub const fn default_max_backoff_ms() -> u64 {
+        600_000
+    }
+
+    pub const fn default_timeout_ms() -> u64 {
+        10_000
+    }
+
+    pub const fn default_poll_interval_ms() -> u64 {
+        1_000
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    PoolCreated,
+    Swap,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MevConfig {
+    // Maximum versions between the front-run and the back-run of a sandwich
+    #[serde(default = "MevConfig::default_sandwich_max_version_gap")]
+    pub sandwich_max_version_gap: i64,
+}
+
+impl MevConfig {
+    pub const fn default_sandwich_max_version_gap() -> i64 {
+        5
+    }
+}
+
+impl Default for MevConfig {
+    fn default() -> Self {
+        Self {
+            sandwich_max_version_gap: Self::default_sandwich_max_version_gap(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WashTradingConfig {
+    // Maximum seconds between the two legs of a round trip
+    #[serde(default = "WashTradingConfig::default_round_trip_window_secs")]
+    pub round_trip_window_secs: i64,
+    // How far the amounts of the two legs may differ for the net position change to count as zero
+    #[serde(default = "WashTradingConfig::default_amount_tolerance_percent")]
+    pub amount_tolerance_percent: f64,
+    // Scores cover the activity of this many seconds before the batch
+    #[serde(default = "WashTradingConfig::default_score_window_secs")]
+    pub score_window_secs: i64,
+    // Share of an address' volume in round trips, in percent, above which it is flagged
+    #[serde(default = "WashTradingConfig::default_flag_score")]
+    pub flag_score: f64,
+    #[serde(default = "WashTradingConfig::default_min_round_trips")]
+    pub min_round_trips: i64,
+    // Removes the round trips of flagged addresses from trader_stats and the pool volume windows
+    #[serde(default)]
+    pub exclude_flagged_volume: bool,
+}
+
+impl WashTradingConfig {
+    pub const fn default_round_trip_window_secs() -> i64 {
+        3_600
+    }
+
+    pub const fn default_amount_tolerance_percent() -> f64 {
+        2.0
+    }
+
+    pub const fn default_score_window_secs() -> i64 {
+        86_400
+    }
+
+    pub const fn default_flag_score() -> f64 {
+        50.0
+    }
+
+    pub const fn default_min_round_trips() -> i64 {
+        2
+    }
+}
+
+impl Default for WashTradingConfig {
+    fn default() -> Self {
+        Self {
+            round_trip_window_secs: Self::default_round_trip_window_secs(),
+            amount_tolerance_percent: Self::default_amount_tolerance_percent(),
+            score_window_secs: Self::default_score_window_secs(),
+            flag_score: Self::default_flag_score(),
+            min_round_trips: Self::default_min_round_trips(),
+            exclude_flagged_volume: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    // Seconds the last processed transaction may lag behind the wall clock for /readyz to pass
+    #[serde(default = "HealthConfig::default_max_lag_secs")]
+    pub max_lag_secs: i64,
+    // Size of the health server's own DB pool, kept apart from the processor's
+    #[serde(default = "HealthConfig::default_db_pool_size")]
+    pub db_pool_size: u32,
+}
+
+impl HealthConfig {
+    pub const fn default_max_lag_secs() -> i64 {
+        300
+    }
+
+    pub const fn default_db_pool_size() -> u32 {
+        2
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            max_lag_secs: Self::default_max_lag_secs(),
+            db_pool_size: Self::default_db_pool_size(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShutdownConfig {
+    // Seconds between SIGTERM and the exit, to drain the batches in flight and save
+    // processor_status. Keep it below the grace period of the platform, 10s on Cloud Run.
+    #[serde(default = "ShutdownConfig::default_deadline_secs")]
+    pub deadline_secs: u64,
+}
+
+impl ShutdownConfig {
+    pub const fn default_deadline_secs() -> u64 {
+        8
+    }
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            deadline_secs: Self::default_deadline_secs(),
+        }
+    }
+}
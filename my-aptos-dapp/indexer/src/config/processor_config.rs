@@ -32,7 +32,12 @@ use serde::{Deserialize, Serialize};
     strum(serialize_all = "snake_case")
 )]
 pub enum ProcessorConfig {
+    // Indexes every domain with a single cursor
     ContractProcessor,
+    HyperionProcessor,
+    MessageBoardProcessor,
+    TradeRadarProcessor,
+    UpgradeHistoryProcessor,
 }
 
 impl ProcessorConfig {
@@ -41,6 +46,36 @@ impl ProcessorConfig {
     pub fn name(&self) -> &'static str {
         self.into()
     }
+
+    /// The domains this processor extracts and stores. Each processor saves its progress to
+    /// its own `processor_status` row, so domains can be run, backfilled or reset separately.
+    pub fn domains(&self) -> &'static [ProcessorDomain] {
+        match self {
+            ProcessorConfig::ContractProcessor => &[
+                ProcessorDomain::Hyperion,
+                ProcessorDomain::MessageBoard,
+                ProcessorDomain::TradeRadar,
+                ProcessorDomain::UpgradeHistory,
+            ],
+            ProcessorConfig::HyperionProcessor => &[ProcessorDomain::Hyperion],
+            ProcessorConfig::MessageBoardProcessor => &[ProcessorDomain::MessageBoard],
+            ProcessorConfig::TradeRadarProcessor => &[ProcessorDomain::TradeRadar],
+            ProcessorConfig::UpgradeHistoryProcessor => &[ProcessorDomain::UpgradeHistory],
+        }
+    }
+}
+
+/// A group of contract events or changes, and the tables derived from them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProcessorDomain {
+    // pool_v3 events: pools, swaps and everything computed from swaps
+    Hyperion,
+    // custom_indexer_ex_message_board events: messages and user_stats
+    MessageBoard,
+    // trade_radar events: trades and trader_stats
+    TradeRadar,
+    // Package publishes: module_upgrade_history and package_upgrade_history
+    UpgradeHistory,
 }
 #[derive(Debug)]
 // To ensure that the variants of ProcessorConfig and Processor line up, in the testing
@@ -57,6 +92,10 @@ impl ProcessorConfig {
 )]
 pub enum Processor {
     ContractProcessor,
+    HyperionProcessor,
+    MessageBoardProcessor,
+    TradeRadarProcessor,
+    UpgradeHistoryProcessor,
 }
 
 #[cfg(test)]
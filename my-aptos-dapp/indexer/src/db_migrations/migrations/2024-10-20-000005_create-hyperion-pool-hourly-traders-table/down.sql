@@ -0,0 +1 @@
+DROP TABLE IF EXISTS hyperion_pool_hourly_traders;
@@ -0,0 +1,10 @@
+-- One row per distinct swapper per pool per hour. Inserts are idempotent, so
+-- re-processing a version range never inflates the distinct counts.
+CREATE TABLE IF NOT EXISTS hyperion_pool_hourly_traders (
+    pool_address VARCHAR(300) NOT NULL,
+    bucket_start BIGINT NOT NULL,
+    trader_addr VARCHAR(300) NOT NULL,
+    PRIMARY KEY (pool_address, bucket_start, trader_addr)
+);
+
+CREATE INDEX IF NOT EXISTS idx_hyperion_pool_hourly_traders_bucket ON hyperion_pool_hourly_traders (bucket_start);
@@ -0,0 +1,14 @@
+-- Latest on-chain USD price per token, routed through indexed Hyperion pools
+-- from the configured stablecoins. Prices are per smallest on-chain unit.
+CREATE TABLE IF NOT EXISTS token_prices (
+    token_address VARCHAR(300) PRIMARY KEY,
+    price_usd VARCHAR(100) NOT NULL,
+    liquidity_usd VARCHAR(100) NOT NULL,
+    anchor_token VARCHAR(300) NOT NULL,
+    -- comma separated pool addresses, from the anchor stablecoin to this token
+    path TEXT NOT NULL,
+    hops INTEGER NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_prices_timestamp ON token_prices (timestamp DESC);
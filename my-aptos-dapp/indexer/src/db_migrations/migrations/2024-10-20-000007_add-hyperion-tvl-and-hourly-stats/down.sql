@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS hyperion_pool_hourly_stats;
+
+ALTER TABLE IF EXISTS hyperion_pool_stats
+DROP COLUMN IF EXISTS reserve0,
+DROP COLUMN IF EXISTS reserve1;
+
+ALTER TABLE IF EXISTS hyperion_swaps
+DROP COLUMN IF EXISTS amount_usd;
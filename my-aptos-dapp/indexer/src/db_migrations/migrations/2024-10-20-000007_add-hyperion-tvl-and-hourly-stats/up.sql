@@ -0,0 +1,21 @@
+-- USD value of each swap, priced with token_prices at the time the swap was indexed
+ALTER TABLE IF EXISTS hyperion_swaps
+ADD COLUMN IF NOT EXISTS amount_usd VARCHAR(100) NOT NULL DEFAULT '0';
+
+-- Virtual reserves of the active liquidity, in smallest on-chain units
+ALTER TABLE IF EXISTS hyperion_pool_stats
+ADD COLUMN IF NOT EXISTS reserve0 VARCHAR(100) NOT NULL DEFAULT '0',
+ADD COLUMN IF NOT EXISTS reserve1 VARCHAR(100) NOT NULL DEFAULT '0';
+
+-- Hourly volume and fees per pool. Each bucket is recomputed from hyperion_swaps
+-- whenever a swap lands in it, so re-processing never double counts.
+CREATE TABLE IF NOT EXISTS hyperion_pool_hourly_stats (
+    pool_address VARCHAR(300) NOT NULL,
+    bucket_start BIGINT NOT NULL,
+    volume_usd VARCHAR(100) NOT NULL,
+    fees_usd VARCHAR(100) NOT NULL,
+    swap_count BIGINT NOT NULL,
+    PRIMARY KEY (pool_address, bucket_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_hyperion_pool_hourly_stats_bucket ON hyperion_pool_hourly_stats (bucket_start);
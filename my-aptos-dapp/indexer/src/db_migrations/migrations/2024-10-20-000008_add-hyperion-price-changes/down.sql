@@ -0,0 +1,6 @@
+ALTER TABLE IF EXISTS hyperion_pool_stats
+DROP COLUMN IF EXISTS price_change_1h,
+DROP COLUMN IF EXISTS price_change_7d;
+
+ALTER TABLE IF EXISTS hyperion_pool_hourly_stats
+DROP COLUMN IF EXISTS close_price;
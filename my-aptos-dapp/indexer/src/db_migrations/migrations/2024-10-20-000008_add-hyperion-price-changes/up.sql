@@ -0,0 +1,7 @@
+-- Pool price (token0 in token1) at the end of each hour, the reference for price changes
+ALTER TABLE IF EXISTS hyperion_pool_hourly_stats
+ADD COLUMN IF NOT EXISTS close_price VARCHAR(100) NOT NULL DEFAULT '0';
+
+ALTER TABLE IF EXISTS hyperion_pool_stats
+ADD COLUMN IF NOT EXISTS price_change_1h VARCHAR(50) NOT NULL DEFAULT '0',
+ADD COLUMN IF NOT EXISTS price_change_7d VARCHAR(50) NOT NULL DEFAULT '0';
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS token_stats;
+
+ALTER TABLE IF EXISTS hyperion_pool_hourly_stats
+DROP COLUMN IF EXISTS volume0,
+DROP COLUMN IF EXISTS volume1;
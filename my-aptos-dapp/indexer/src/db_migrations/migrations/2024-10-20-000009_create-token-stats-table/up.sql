@@ -0,0 +1,25 @@
+-- Raw token amounts swapped on each side of the pool, so volume can be aggregated per token
+ALTER TABLE IF EXISTS hyperion_pool_hourly_stats
+ADD COLUMN IF NOT EXISTS volume0 VARCHAR(100) NOT NULL DEFAULT '0',
+ADD COLUMN IF NOT EXISTS volume1 VARCHAR(100) NOT NULL DEFAULT '0';
+
+-- Per-token aggregates across every Hyperion pool containing the token.
+-- liquidity and volume_24h/volume_7d are in smallest on-chain units of the token.
+CREATE TABLE IF NOT EXISTS token_stats (
+    token_address VARCHAR(300) PRIMARY KEY,
+    liquidity VARCHAR(100) NOT NULL DEFAULT '0',
+    liquidity_usd VARCHAR(100) NOT NULL DEFAULT '0',
+    volume_24h VARCHAR(100) NOT NULL DEFAULT '0',
+    volume_7d VARCHAR(100) NOT NULL DEFAULT '0',
+    volume_usd_24h VARCHAR(100) NOT NULL DEFAULT '0',
+    volume_usd_7d VARCHAR(100) NOT NULL DEFAULT '0',
+    swap_count_24h BIGINT NOT NULL DEFAULT 0,
+    swap_count_7d BIGINT NOT NULL DEFAULT 0,
+    pool_count INTEGER NOT NULL DEFAULT 0,
+    -- pool holding the most USD liquidity of this token
+    best_pool_address VARCHAR(300) NOT NULL DEFAULT '',
+    last_update_timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_stats_liquidity ON token_stats (CAST(liquidity_usd AS NUMERIC) DESC);
+CREATE INDEX IF NOT EXISTS idx_token_stats_volume ON token_stats (CAST(volume_usd_24h AS NUMERIC) DESC);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_hyperion_swaps_price_impact;
+
+ALTER TABLE IF EXISTS hyperion_swaps
+DROP COLUMN IF EXISTS zero_for_one,
+DROP COLUMN IF EXISTS sqrt_price_before,
+DROP COLUMN IF EXISTS execution_price,
+DROP COLUMN IF EXISTS price_impact_bps;
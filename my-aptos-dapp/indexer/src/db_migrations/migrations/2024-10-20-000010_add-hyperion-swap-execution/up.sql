@@ -0,0 +1,9 @@
+-- Direction and execution of each swap relative to the pool state right before it.
+-- Prices are token0 in token1, in smallest on-chain units.
+ALTER TABLE IF EXISTS hyperion_swaps
+ADD COLUMN IF NOT EXISTS zero_for_one BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN IF NOT EXISTS sqrt_price_before VARCHAR(100) NOT NULL DEFAULT '0',
+ADD COLUMN IF NOT EXISTS execution_price VARCHAR(100) NOT NULL DEFAULT '0',
+ADD COLUMN IF NOT EXISTS price_impact_bps VARCHAR(50) NOT NULL DEFAULT '0';
+
+CREATE INDEX IF NOT EXISTS idx_hyperion_swaps_price_impact ON hyperion_swaps (CAST(price_impact_bps AS NUMERIC) DESC);
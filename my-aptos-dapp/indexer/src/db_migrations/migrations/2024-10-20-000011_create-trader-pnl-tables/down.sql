@@ -0,0 +1,3 @@
+DROP VIEW IF EXISTS trader_token_positions_pnl;
+DROP TABLE IF EXISTS trader_pnl_daily;
+DROP TABLE IF EXISTS trader_token_positions;
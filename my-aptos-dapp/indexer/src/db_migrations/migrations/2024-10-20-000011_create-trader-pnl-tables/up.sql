@@ -0,0 +1,44 @@
+-- Average cost position of each Hyperion swapper in each token, rebuilt from hyperion_swaps.
+-- quantity is in smallest on-chain units, all *_usd columns are in USD.
+CREATE TABLE IF NOT EXISTS trader_token_positions (
+    trader_addr VARCHAR(300) NOT NULL,
+    token_address VARCHAR(300) NOT NULL,
+    quantity VARCHAR(100) NOT NULL,
+    cost_basis_usd VARCHAR(100) NOT NULL,
+    realized_pnl_usd VARCHAR(100) NOT NULL,
+    -- last swap applied to the position, older swaps are skipped when re-processing
+    last_tx_version BIGINT NOT NULL,
+    last_event_idx BIGINT NOT NULL,
+    last_update_timestamp BIGINT NOT NULL,
+    PRIMARY KEY (trader_addr, token_address)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trader_token_positions_token ON trader_token_positions (token_address);
+
+-- Realized PnL and traded volume per trader and UTC day of chain time
+CREATE TABLE IF NOT EXISTS trader_pnl_daily (
+    trader_addr VARCHAR(300) NOT NULL,
+    day_start BIGINT NOT NULL,
+    realized_pnl_usd VARCHAR(100) NOT NULL,
+    volume_usd VARCHAR(100) NOT NULL,
+    swap_count BIGINT NOT NULL,
+    PRIMARY KEY (trader_addr, day_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trader_pnl_daily_day ON trader_pnl_daily (day_start);
+
+-- Positions marked to the latest indexed token prices. market_value_usd and
+-- unrealized_pnl_usd are NULL for tokens without a routed price.
+CREATE OR REPLACE VIEW trader_token_positions_pnl AS
+SELECT
+    p.trader_addr,
+    p.token_address,
+    p.quantity,
+    p.cost_basis_usd,
+    p.realized_pnl_usd,
+    CAST(p.quantity AS NUMERIC) * CAST(tp.price_usd AS NUMERIC) AS market_value_usd,
+    CAST(p.quantity AS NUMERIC) * CAST(tp.price_usd AS NUMERIC)
+        - CAST(p.cost_basis_usd AS NUMERIC) AS unrealized_pnl_usd,
+    p.last_update_timestamp
+FROM trader_token_positions p
+LEFT JOIN token_prices tp ON tp.token_address = p.token_address;
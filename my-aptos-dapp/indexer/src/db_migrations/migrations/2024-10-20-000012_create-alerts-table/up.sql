@@ -0,0 +1,20 @@
+-- Alerts raised by the indexer. alert_id doubles as the dedup key, so re-processing a
+-- range never raises the same alert twice.
+CREATE TABLE IF NOT EXISTS alerts (
+    alert_id VARCHAR(400) PRIMARY KEY,
+    alert_type VARCHAR(50) NOT NULL,
+    -- warning or critical
+    severity VARCHAR(20) NOT NULL,
+    pool_address VARCHAR(300) NOT NULL,
+    -- trader behind the alert, empty for pool level alerts
+    address VARCHAR(300) NOT NULL,
+    tx_version BIGINT NOT NULL,
+    -- metric that triggered the alert, e.g. the swap value in USD
+    value VARCHAR(100) NOT NULL,
+    message TEXT NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_alerts_timestamp ON alerts (timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_alerts_pool ON alerts (pool_address, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_alerts_type ON alerts (alert_type, timestamp DESC);
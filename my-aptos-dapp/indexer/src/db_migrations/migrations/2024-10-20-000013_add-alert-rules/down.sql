@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS hyperion_pool_liquidity_snapshots;
+
+DROP INDEX IF EXISTS idx_alerts_rule;
+
+ALTER TABLE IF EXISTS alerts
+DROP COLUMN IF EXISTS rule_name;
@@ -0,0 +1,15 @@
+-- Rule that raised the alert, empty for built-in detectors. Backs the per rule cooldowns.
+ALTER TABLE IF EXISTS alerts
+ADD COLUMN IF NOT EXISTS rule_name VARCHAR(100) NOT NULL DEFAULT '';
+
+CREATE INDEX IF NOT EXISTS idx_alerts_rule ON alerts (rule_name, pool_address, timestamp DESC);
+
+-- Active liquidity of each pool at the end of every batch that touched it,
+-- the history behind liquidity drop rules
+CREATE TABLE IF NOT EXISTS hyperion_pool_liquidity_snapshots (
+    pool_address VARCHAR(300) NOT NULL,
+    timestamp BIGINT NOT NULL,
+    liquidity VARCHAR(100) NOT NULL,
+    tvl_usd VARCHAR(100) NOT NULL,
+    PRIMARY KEY (pool_address, timestamp)
+);
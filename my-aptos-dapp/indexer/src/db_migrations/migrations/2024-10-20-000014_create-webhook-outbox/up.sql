@@ -0,0 +1,32 @@
+-- Durable queue of webhook deliveries. Rows are enqueued in the same pass that stores the
+-- alert or event, and delivered by a background dispatcher per endpoint. Times are wall
+-- clock unix seconds, not chain time.
+CREATE TABLE IF NOT EXISTS webhook_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    endpoint VARCHAR(100) NOT NULL,
+    -- alert_id or event id, a payload is only enqueued once per endpoint
+    dedup_key VARCHAR(400) NOT NULL,
+    payload TEXT NOT NULL,
+    -- pending, delivered or failed
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at BIGINT NOT NULL,
+    last_error TEXT,
+    created_at BIGINT NOT NULL,
+    delivered_at BIGINT,
+    UNIQUE (endpoint, dedup_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_outbox_due ON webhook_outbox (endpoint, status, next_attempt_at);
+
+-- Delivery health of each configured endpoint
+CREATE TABLE IF NOT EXISTS webhook_endpoint_status (
+    endpoint VARCHAR(100) PRIMARY KEY,
+    url TEXT NOT NULL,
+    delivered_count BIGINT NOT NULL DEFAULT 0,
+    failed_count BIGINT NOT NULL DEFAULT 0,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_success_at BIGINT,
+    last_failure_at BIGINT,
+    last_error TEXT
+);
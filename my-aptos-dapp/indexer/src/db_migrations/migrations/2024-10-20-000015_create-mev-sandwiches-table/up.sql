@@ -0,0 +1,25 @@
+-- Sandwiches detected on Hyperion swaps: the same attacker swapping in the victim's direction
+-- right before it and back right after it, in the same pool. One row per victim swap, so
+-- re-processing a range never records a sandwich twice.
+CREATE TABLE IF NOT EXISTS mev_sandwiches (
+    victim_swap_id VARCHAR(400) PRIMARY KEY,
+    pool_address VARCHAR(300) NOT NULL,
+    attacker VARCHAR(300) NOT NULL,
+    victim VARCHAR(300) NOT NULL,
+    front_run_swap_id VARCHAR(400) NOT NULL,
+    back_run_swap_id VARCHAR(400) NOT NULL,
+    front_run_version BIGINT NOT NULL,
+    victim_version BIGINT NOT NULL,
+    back_run_version BIGINT NOT NULL,
+    -- victim's output token, the loss is expressed in it (smallest on-chain units)
+    victim_token_out VARCHAR(300) NOT NULL,
+    estimated_victim_loss VARCHAR(100) NOT NULL,
+    estimated_victim_loss_usd VARCHAR(100) NOT NULL,
+    -- back-run output minus front-run input when both are the same token, else 0
+    attacker_profit VARCHAR(100) NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_mev_sandwiches_pool ON mev_sandwiches (pool_address, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_mev_sandwiches_attacker ON mev_sandwiches (attacker, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_mev_sandwiches_victim ON mev_sandwiches (victim, timestamp DESC);
@@ -0,0 +1,29 @@
+-- Multi-hop routes reconstructed from the Hyperion swaps of a transaction, chained by
+-- token continuity (each hop spends what the previous hop received).
+CREATE TABLE IF NOT EXISTS swap_routes (
+    -- {tx_version}-{event_idx of the first hop}
+    route_id VARCHAR(100) PRIMARY KEY,
+    tx_version BIGINT NOT NULL,
+    sender VARCHAR(300) NOT NULL,
+    token_in VARCHAR(300) NOT NULL,
+    token_out VARCHAR(300) NOT NULL,
+    -- amount_in of the first hop and amount_out of the last, smallest on-chain units
+    amount_in VARCHAR(100) NOT NULL,
+    amount_out VARCHAR(100) NOT NULL,
+    amount_usd VARCHAR(100) NOT NULL,
+    -- token_out per token_in over the whole route, smallest on-chain units
+    effective_price VARCHAR(100) NOT NULL,
+    -- comma separated, in hop order
+    pool_path TEXT NOT NULL,
+    token_path TEXT NOT NULL,
+    hop_count INTEGER NOT NULL,
+    -- cyclic route starting and ending in the same token
+    is_arbitrage BOOLEAN NOT NULL,
+    -- amount_out - amount_in of an arbitrage, 0 otherwise
+    arbitrage_profit VARCHAR(100) NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_swap_routes_tx_version ON swap_routes (tx_version);
+CREATE INDEX IF NOT EXISTS idx_swap_routes_sender ON swap_routes (sender, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_swap_routes_arbitrage ON swap_routes (timestamp DESC) WHERE is_arbitrage;
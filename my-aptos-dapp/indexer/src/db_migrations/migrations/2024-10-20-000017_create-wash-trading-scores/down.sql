@@ -0,0 +1,3 @@
+ALTER TABLE IF EXISTS trades DROP COLUMN IF EXISTS is_wash;
+ALTER TABLE IF EXISTS hyperion_swaps DROP COLUMN IF EXISTS is_wash;
+DROP TABLE IF EXISTS wash_trading_scores;
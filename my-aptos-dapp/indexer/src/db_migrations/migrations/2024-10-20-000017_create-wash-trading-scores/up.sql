@@ -0,0 +1,20 @@
+-- Wash trading scores per address and pool. Trades of the trading contract are scored per
+-- trader with an empty pool_address. Volumes are USD for pools and trade price units for trades.
+CREATE TABLE IF NOT EXISTS wash_trading_scores (
+    address VARCHAR(300) NOT NULL,
+    pool_address VARCHAR(300) NOT NULL,
+    -- share of the volume in round trips, in percent
+    score VARCHAR(100) NOT NULL,
+    round_trips BIGINT NOT NULL,
+    wash_volume VARCHAR(100) NOT NULL,
+    total_volume VARCHAR(100) NOT NULL,
+    flagged BOOLEAN NOT NULL,
+    last_update_timestamp BIGINT NOT NULL,
+    PRIMARY KEY (address, pool_address)
+);
+
+CREATE INDEX IF NOT EXISTS idx_wash_trading_scores_flagged ON wash_trading_scores (pool_address) WHERE flagged;
+
+-- Round trip legs of flagged addresses, excluded from volumes when configured
+ALTER TABLE IF EXISTS hyperion_swaps ADD COLUMN IF NOT EXISTS is_wash BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE IF EXISTS trades ADD COLUMN IF NOT EXISTS is_wash BOOLEAN NOT NULL DEFAULT FALSE;
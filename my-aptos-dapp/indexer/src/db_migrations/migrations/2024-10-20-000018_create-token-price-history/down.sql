@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_hyperion_swaps_pool_version;
+DROP TABLE IF EXISTS token_price_history;
@@ -0,0 +1,19 @@
+-- Every routing of token prices, so swaps are valued at the prices of their own time even
+-- when their range is indexed long after, e.g. by a backfill or a replay.
+CREATE TABLE IF NOT EXISTS token_price_history (
+    token_address VARCHAR(300) NOT NULL,
+    -- chain time of the batch the prices were routed for
+    timestamp BIGINT NOT NULL,
+    price_usd VARCHAR(100) NOT NULL,
+    liquidity_usd VARCHAR(100) NOT NULL,
+    anchor_token VARCHAR(300) NOT NULL,
+    path TEXT NOT NULL,
+    hops INTEGER NOT NULL,
+    PRIMARY KEY (token_address, timestamp)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_price_history_timestamp ON token_price_history (timestamp);
+
+-- Pool states as of a version, read when routing prices for a range behind the pool rows
+CREATE INDEX IF NOT EXISTS idx_hyperion_swaps_pool_version
+    ON hyperion_swaps (pool_address, tx_version DESC, event_idx DESC);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_trades_trader_creation;
+DROP INDEX IF EXISTS idx_hyperion_swaps_sender_pool_time;
+DROP INDEX IF EXISTS idx_hyperion_swaps_unmatched;
+ALTER TABLE IF EXISTS trades DROP COLUMN IF EXISTS round_trip_id;
+ALTER TABLE IF EXISTS hyperion_swaps DROP COLUMN IF EXISTS round_trip_id;
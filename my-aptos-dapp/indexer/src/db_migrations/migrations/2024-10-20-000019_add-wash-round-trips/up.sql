@@ -0,0 +1,10 @@
+-- Round trip a leg was matched in, the id of its first leg. Matches are kept so each batch
+-- only pairs its own legs with the unmatched legs of the round trip window.
+ALTER TABLE IF EXISTS hyperion_swaps ADD COLUMN IF NOT EXISTS round_trip_id VARCHAR(400);
+ALTER TABLE IF EXISTS trades ADD COLUMN IF NOT EXISTS round_trip_id VARCHAR(300);
+
+CREATE INDEX IF NOT EXISTS idx_hyperion_swaps_unmatched
+    ON hyperion_swaps (pool_address, timestamp) WHERE round_trip_id IS NULL;
+CREATE INDEX IF NOT EXISTS idx_hyperion_swaps_sender_pool_time
+    ON hyperion_swaps (sender, pool_address, timestamp);
+CREATE INDEX IF NOT EXISTS idx_trades_trader_creation ON trades (trader_addr, creation_timestamp);
@@ -0,0 +1,2 @@
+-- The cleared rows are rebuilt by re-indexing, there is nothing to restore.
+SELECT 1;
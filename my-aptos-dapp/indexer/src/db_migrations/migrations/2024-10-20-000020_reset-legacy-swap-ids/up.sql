@@ -0,0 +1,16 @@
+-- Hyperion rows indexed before on-chain pricing stored the event index and sequence number
+-- of each event where the transaction version and event index belong. The real version can't
+-- be recovered from them, so swap ids (`{pool}-{tx_version}-{event_idx}`) can't be rewritten.
+-- Everything keyed on or computed from them is cleared instead, and every processor cursor is
+-- reset so all domains are re-indexed together and counters such as trader_stats and
+-- user_stats aren't applied twice. On an empty database this does nothing.
+TRUNCATE hyperion_pools, hyperion_swaps, hyperion_pool_stats, hyperion_pool_hourly_stats,
+    hyperion_pool_hourly_traders, hyperion_pool_liquidity_snapshots, token_prices,
+    token_price_history, token_stats, trader_token_positions, trader_pnl_daily,
+    mev_sandwiches, swap_routes, wash_trading_scores, trades, trader_stats, messages,
+    user_stats, module_upgrade_history, package_upgrade_history;
+
+-- Large swap alert ids embed the swap id. Webhook deliveries of the deleted alerts are kept.
+DELETE FROM alerts WHERE alert_type = 'large_swap';
+
+DELETE FROM processor_status;
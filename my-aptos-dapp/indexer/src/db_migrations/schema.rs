@@ -38,6 +38,9 @@ diesel::table! {
         last_update_timestamp -> Int8,
         last_update_event_idx -> Int8,
         notes -> Text,
+        is_wash -> Bool,
+        #[max_length = 300]
+        round_trip_id -> Nullable<Varchar>,
     }
 }
 
@@ -160,6 +163,18 @@ diesel::table! {
         tx_version -> Int8,
         event_idx -> Int8,
         timestamp -> Int8,
+        #[max_length = 100]
+        amount_usd -> Varchar,
+        zero_for_one -> Bool,
+        #[max_length = 100]
+        sqrt_price_before -> Varchar,
+        #[max_length = 100]
+        execution_price -> Varchar,
+        #[max_length = 50]
+        price_impact_bps -> Varchar,
+        is_wash -> Bool,
+        #[max_length = 400]
+        round_trip_id -> Nullable<Varchar>,
     }
 }
 
@@ -188,6 +203,273 @@ diesel::table! {
         #[max_length = 50]
         price_change_24h -> Varchar,
         last_update_timestamp -> Int8,
+        #[max_length = 100]
+        reserve0 -> Varchar,
+        #[max_length = 100]
+        reserve1 -> Varchar,
+        #[max_length = 50]
+        price_change_1h -> Varchar,
+        #[max_length = 50]
+        price_change_7d -> Varchar,
+    }
+}
+
+diesel::table! {
+    hyperion_pool_hourly_stats (pool_address, bucket_start) {
+        #[max_length = 300]
+        pool_address -> Varchar,
+        bucket_start -> Int8,
+        #[max_length = 100]
+        volume_usd -> Varchar,
+        #[max_length = 100]
+        fees_usd -> Varchar,
+        swap_count -> Int8,
+        #[max_length = 100]
+        close_price -> Varchar,
+        #[max_length = 100]
+        volume0 -> Varchar,
+        #[max_length = 100]
+        volume1 -> Varchar,
+    }
+}
+
+diesel::table! {
+    hyperion_pool_hourly_traders (pool_address, bucket_start, trader_addr) {
+        #[max_length = 300]
+        pool_address -> Varchar,
+        bucket_start -> Int8,
+        #[max_length = 300]
+        trader_addr -> Varchar,
+    }
+}
+
+diesel::table! {
+    token_prices (token_address) {
+        #[max_length = 300]
+        token_address -> Varchar,
+        #[max_length = 100]
+        price_usd -> Varchar,
+        #[max_length = 100]
+        liquidity_usd -> Varchar,
+        #[max_length = 300]
+        anchor_token -> Varchar,
+        path -> Text,
+        hops -> Int4,
+        timestamp -> Int8,
+    }
+}
+
+diesel::table! {
+    token_stats (token_address) {
+        #[max_length = 300]
+        token_address -> Varchar,
+        #[max_length = 100]
+        liquidity -> Varchar,
+        #[max_length = 100]
+        liquidity_usd -> Varchar,
+        #[max_length = 100]
+        volume_24h -> Varchar,
+        #[max_length = 100]
+        volume_7d -> Varchar,
+        #[max_length = 100]
+        volume_usd_24h -> Varchar,
+        #[max_length = 100]
+        volume_usd_7d -> Varchar,
+        swap_count_24h -> Int8,
+        swap_count_7d -> Int8,
+        pool_count -> Int4,
+        #[max_length = 300]
+        best_pool_address -> Varchar,
+        last_update_timestamp -> Int8,
+    }
+}
+
+diesel::table! {
+    trader_token_positions (trader_addr, token_address) {
+        #[max_length = 300]
+        trader_addr -> Varchar,
+        #[max_length = 300]
+        token_address -> Varchar,
+        #[max_length = 100]
+        quantity -> Varchar,
+        #[max_length = 100]
+        cost_basis_usd -> Varchar,
+        #[max_length = 100]
+        realized_pnl_usd -> Varchar,
+        last_tx_version -> Int8,
+        last_event_idx -> Int8,
+        last_update_timestamp -> Int8,
+    }
+}
+
+diesel::table! {
+    trader_pnl_daily (trader_addr, day_start) {
+        #[max_length = 300]
+        trader_addr -> Varchar,
+        day_start -> Int8,
+        #[max_length = 100]
+        realized_pnl_usd -> Varchar,
+        #[max_length = 100]
+        volume_usd -> Varchar,
+        swap_count -> Int8,
+    }
+}
+
+diesel::table! {
+    alerts (alert_id) {
+        #[max_length = 400]
+        alert_id -> Varchar,
+        #[max_length = 50]
+        alert_type -> Varchar,
+        #[max_length = 20]
+        severity -> Varchar,
+        #[max_length = 300]
+        pool_address -> Varchar,
+        #[max_length = 300]
+        address -> Varchar,
+        tx_version -> Int8,
+        #[max_length = 100]
+        value -> Varchar,
+        message -> Text,
+        timestamp -> Int8,
+        #[max_length = 100]
+        rule_name -> Varchar,
+    }
+}
+
+diesel::table! {
+    hyperion_pool_liquidity_snapshots (pool_address, timestamp) {
+        #[max_length = 300]
+        pool_address -> Varchar,
+        timestamp -> Int8,
+        #[max_length = 100]
+        liquidity -> Varchar,
+        #[max_length = 100]
+        tvl_usd -> Varchar,
+    }
+}
+
+diesel::table! {
+    webhook_outbox (id) {
+        id -> Int8,
+        #[max_length = 100]
+        endpoint -> Varchar,
+        #[max_length = 400]
+        dedup_key -> Varchar,
+        payload -> Text,
+        #[max_length = 20]
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Int8,
+        last_error -> Nullable<Text>,
+        created_at -> Int8,
+        delivered_at -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    webhook_endpoint_status (endpoint) {
+        #[max_length = 100]
+        endpoint -> Varchar,
+        url -> Text,
+        delivered_count -> Int8,
+        failed_count -> Int8,
+        consecutive_failures -> Int4,
+        last_success_at -> Nullable<Int8>,
+        last_failure_at -> Nullable<Int8>,
+        last_error -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    mev_sandwiches (victim_swap_id) {
+        #[max_length = 400]
+        victim_swap_id -> Varchar,
+        #[max_length = 300]
+        pool_address -> Varchar,
+        #[max_length = 300]
+        attacker -> Varchar,
+        #[max_length = 300]
+        victim -> Varchar,
+        #[max_length = 400]
+        front_run_swap_id -> Varchar,
+        #[max_length = 400]
+        back_run_swap_id -> Varchar,
+        front_run_version -> Int8,
+        victim_version -> Int8,
+        back_run_version -> Int8,
+        #[max_length = 300]
+        victim_token_out -> Varchar,
+        #[max_length = 100]
+        estimated_victim_loss -> Varchar,
+        #[max_length = 100]
+        estimated_victim_loss_usd -> Varchar,
+        #[max_length = 100]
+        attacker_profit -> Varchar,
+        timestamp -> Int8,
+    }
+}
+
+diesel::table! {
+    swap_routes (route_id) {
+        #[max_length = 100]
+        route_id -> Varchar,
+        tx_version -> Int8,
+        #[max_length = 300]
+        sender -> Varchar,
+        #[max_length = 300]
+        token_in -> Varchar,
+        #[max_length = 300]
+        token_out -> Varchar,
+        #[max_length = 100]
+        amount_in -> Varchar,
+        #[max_length = 100]
+        amount_out -> Varchar,
+        #[max_length = 100]
+        amount_usd -> Varchar,
+        #[max_length = 100]
+        effective_price -> Varchar,
+        pool_path -> Text,
+        token_path -> Text,
+        hop_count -> Int4,
+        is_arbitrage -> Bool,
+        #[max_length = 100]
+        arbitrage_profit -> Varchar,
+        timestamp -> Int8,
+    }
+}
+
+diesel::table! {
+    wash_trading_scores (address, pool_address) {
+        #[max_length = 300]
+        address -> Varchar,
+        #[max_length = 300]
+        pool_address -> Varchar,
+        #[max_length = 100]
+        score -> Varchar,
+        round_trips -> Int8,
+        #[max_length = 100]
+        wash_volume -> Varchar,
+        #[max_length = 100]
+        total_volume -> Varchar,
+        flagged -> Bool,
+        last_update_timestamp -> Int8,
+    }
+}
+
+diesel::table! {
+    token_price_history (token_address, timestamp) {
+        #[max_length = 300]
+        token_address -> Varchar,
+        timestamp -> Int8,
+        #[max_length = 100]
+        price_usd -> Varchar,
+        #[max_length = 100]
+        liquidity_usd -> Varchar,
+        #[max_length = 300]
+        anchor_token -> Varchar,
+        path -> Text,
+        hops -> Int4,
     }
 }
 
@@ -203,4 +485,18 @@ diesel::allow_tables_to_appear_in_same_query!(
     hyperion_pools,
     hyperion_swaps,
     hyperion_pool_stats,
+    hyperion_pool_hourly_stats,
+    hyperion_pool_hourly_traders,
+    token_prices,
+    token_stats,
+    trader_token_positions,
+    trader_pnl_daily,
+    alerts,
+    hyperion_pool_liquidity_snapshots,
+    webhook_outbox,
+    webhook_endpoint_status,
+    mev_sandwiches,
+    swap_routes,
+    wash_trading_scores,
+    token_price_history,
 );
@@ -0,0 +1,29 @@
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::alerts;
+
+pub const ALERT_TYPE_LARGE_SWAP: &str = "large_swap";
+pub const ALERT_TYPE_RULE: &str = "rule";
+
+pub const SEVERITY_WARNING: &str = "warning";
+pub const SEVERITY_CRITICAL: &str = "critical";
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = alerts)]
+/// Database representation of an alert raised while indexing.
+/// `alert_id` is deterministic so the same alert is only stored once.
+pub struct Alert {
+    pub alert_id: String,
+    pub alert_type: String,
+    pub severity: String,
+    pub pool_address: String,
+    pub address: String,
+    pub tx_version: i64,
+    pub value: String,
+    pub message: String,
+    pub timestamp: i64,
+    /// Configured rule that raised the alert, empty for built-in detectors
+    pub rule_name: String,
+}
@@ -1,8 +1,19 @@
-use crate::db_migrations::schema::hyperion_pools;
+use crate::schema::hyperion_pools;
 use diesel::prelude::*;
+use field_count::FieldCount;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, Insertable, Queryable, Serialize)]
+/// Fixed point scale of `sqrt_price_x96` (2^96).
+const Q96: f64 = 79_228_162_514_264_337_593_543_950_336.0;
+
+/// Converts a `sqrt_price_x96` value into the spot price of one unit of token0 expressed in
+/// token1, both in smallest on-chain units.
+pub fn price_from_sqrt_price_x96(sqrt_price_x96: &str) -> f64 {
+    let sqrt_price = sqrt_price_x96.parse::<f64>().unwrap_or(0.0) / Q96;
+    sqrt_price * sqrt_price
+}
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
 #[diesel(table_name = hyperion_pools)]
 pub struct HyperionPool {
     pub pool_address: String,
@@ -76,4 +87,24 @@ impl HyperionPool {
         self.last_update_timestamp = event.timestamp.parse::<i64>().unwrap_or(0);
         self.last_update_version = tx_version;
     }
+
+    /// Square root of the pool price, i.e. `sqrt_price_x96 / 2^96`.
+    pub fn sqrt_price(&self) -> f64 {
+        self.sqrt_price_x96.parse::<f64>().unwrap_or(0.0) / Q96
+    }
+
+    /// Spot price of one unit of token0 expressed in token1, both in smallest on-chain units.
+    pub fn price(&self) -> f64 {
+        price_from_sqrt_price_x96(&self.sqrt_price_x96)
+    }
+
+    /// Virtual (token0, token1) reserves backing the active liquidity, in smallest on-chain units.
+    pub fn virtual_reserves(&self) -> (f64, f64) {
+        let liquidity = self.liquidity.parse::<f64>().unwrap_or(0.0);
+        let sqrt_price = self.sqrt_price();
+        if sqrt_price <= 0.0 {
+            return (0.0, 0.0);
+        }
+        (liquidity / sqrt_price, liquidity * sqrt_price)
+    }
 }
@@ -0,0 +1,22 @@
+use diesel::{AsChangeset, Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::hyperion_pool_hourly_stats;
+
+#[derive(AsChangeset, Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = hyperion_pool_hourly_stats)]
+/// Database representation of a pool's activity during one hour of chain time
+pub struct HyperionPoolHourlyStat {
+    pub pool_address: String,
+    pub bucket_start: i64,
+    pub volume_usd: String,
+    pub fees_usd: String,
+    pub swap_count: i64,
+    /// Pool price (token0 in token1) after the last swap of the hour
+    pub close_price: String,
+    /// Raw token0 amount swapped in or out during the hour
+    pub volume0: String,
+    /// Raw token1 amount swapped in or out during the hour
+    pub volume1: String,
+}
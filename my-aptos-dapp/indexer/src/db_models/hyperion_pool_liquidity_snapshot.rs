@@ -0,0 +1,15 @@
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::hyperion_pool_liquidity_snapshots;
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = hyperion_pool_liquidity_snapshots)]
+/// Database representation of a pool's liquidity at the end of a processed batch
+pub struct HyperionPoolLiquiditySnapshot {
+    pub pool_address: String,
+    pub timestamp: i64,
+    pub liquidity: String,
+    pub tvl_usd: String,
+}
@@ -1,7 +1,27 @@
-use crate::db_migrations::schema::hyperion_pool_stats;
+use crate::schema::hyperion_pool_stats;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
+pub const SECONDS_PER_HOUR: i64 = 3_600;
+pub const WINDOW_24H_SECS: i64 = 24 * SECONDS_PER_HOUR;
+pub const WINDOW_7D_SECS: i64 = 7 * WINDOW_24H_SECS;
+
+/// Start of the hour bucket (in chain seconds) that `timestamp` falls into.
+pub fn hour_bucket(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(SECONDS_PER_HOUR)
+}
+
+/// First hour bucket that belongs to the trailing `window_secs` window ending at `timestamp`.
+/// The bucket containing `timestamp` counts as the last bucket of the window.
+pub fn window_start_bucket(timestamp: i64, window_secs: i64) -> i64 {
+    hour_bucket(timestamp) - window_secs + SECONDS_PER_HOUR
+}
+
+/// Start of the last hour bucket that ended at least `window_secs` before `timestamp`.
+pub fn last_bucket_ended_before(timestamp: i64, window_secs: i64) -> i64 {
+    hour_bucket(timestamp - window_secs) - SECONDS_PER_HOUR
+}
+
 #[derive(Clone, Debug, Deserialize, Insertable, Queryable, Serialize, AsChangeset)]
 #[diesel(table_name = hyperion_pool_stats)]
 pub struct HyperionPoolStat {
@@ -19,6 +39,10 @@ pub struct HyperionPoolStat {
     pub last_price: String,
     pub price_change_24h: String,
     pub last_update_timestamp: i64,
+    pub reserve0: String,
+    pub reserve1: String,
+    pub price_change_1h: String,
+    pub price_change_7d: String,
 }
 
 impl HyperionPoolStat {
@@ -38,6 +62,10 @@ impl HyperionPoolStat {
             last_price: "0".to_string(),
             price_change_24h: "0".to_string(),
             last_update_timestamp: 0,
+            reserve0: "0".to_string(),
+            reserve1: "0".to_string(),
+            price_change_1h: "0".to_string(),
+            price_change_7d: "0".to_string(),
         }
     }
 
@@ -83,3 +111,30 @@ impl HyperionPoolStat {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_last_bucket_ended_before_across_an_hour_boundary() {
+        let ten = 10 * SECONDS_PER_HOUR;
+        // At 10:01 the 9:00 bucket closed a minute ago, the 1h reference is the 8:00 one
+        assert_eq!(
+            last_bucket_ended_before(ten + 60, SECONDS_PER_HOUR),
+            8 * SECONDS_PER_HOUR
+        );
+        assert_eq!(
+            last_bucket_ended_before(ten, SECONDS_PER_HOUR),
+            8 * SECONDS_PER_HOUR
+        );
+        assert_eq!(
+            last_bucket_ended_before(ten - 1, SECONDS_PER_HOUR),
+            7 * SECONDS_PER_HOUR
+        );
+        assert_eq!(
+            last_bucket_ended_before(ten + 60, WINDOW_24H_SECS),
+            -15 * SECONDS_PER_HOUR
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::{db_models::hyperion_pool_stat::hour_bucket, schema::hyperion_pool_hourly_traders};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = hyperion_pool_hourly_traders)]
+/// Database representation of a trader that swapped in a pool during a given hour
+pub struct HyperionPoolHourlyTrader {
+    pub pool_address: String,
+    pub bucket_start: i64,
+    pub trader_addr: String,
+}
+
+impl HyperionPoolHourlyTrader {
+    pub fn new(pool_address: String, trader_addr: String, timestamp: i64) -> Self {
+        Self {
+            pool_address,
+            bucket_start: hour_bucket(timestamp),
+            trader_addr,
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use crate::db_migrations::schema::hyperion_swaps;
+use crate::{
+    db_models::{hyperion_pool::price_from_sqrt_price_x96, token_price::usd_price_at},
+    schema::hyperion_swaps,
+};
+use ahash::AHashMap;
+use aptos_indexer_processor_sdk::utils::convert::standardize_address;
 use diesel::prelude::*;
+use field_count::FieldCount;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, Insertable, Queryable, Serialize)]
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
 #[diesel(table_name = hyperion_swaps)]
 pub struct HyperionSwap {
     pub swap_id: String,
@@ -19,6 +25,17 @@ pub struct HyperionSwap {
     pub tx_version: i64,
     pub event_idx: i64,
     pub timestamp: i64,
+    pub amount_usd: String,
+    pub zero_for_one: bool,
+    pub sqrt_price_before: String,
+    /// Realized price of token0 in token1, in smallest on-chain units
+    pub execution_price: String,
+    /// How far the swap moved the pool price, in basis points
+    pub price_impact_bps: String,
+    /// Round trip leg of an address flagged for wash trading
+    pub is_wash: bool,
+    /// swap_id of the first leg of the round trip the swap was matched in
+    pub round_trip_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -59,6 +76,54 @@ impl HyperionSwap {
             tx_version,
             event_idx,
             timestamp: event.timestamp.parse::<i64>().unwrap_or(0),
+            amount_usd: "0".to_string(),
+            zero_for_one: false,
+            sqrt_price_before: "0".to_string(),
+            execution_price: "0".to_string(),
+            price_impact_bps: "0".to_string(),
+            is_wash: false,
+            round_trip_id: None,
         }
     }
+
+    /// Fills in the direction, execution price and price impact of the swap. Needs the pool's
+    /// token0 and its `sqrt_price_x96` right before the swap; the impact stays at 0 when the
+    /// previous price is unknown.
+    pub fn set_execution(&mut self, token0_address: &str, sqrt_price_before: &str) {
+        self.zero_for_one =
+            standardize_address(&self.token_in) == standardize_address(token0_address);
+        let amount_in = self.amount_in.parse::<f64>().unwrap_or(0.0);
+        let amount_out = self.amount_out.parse::<f64>().unwrap_or(0.0);
+        let (amount0, amount1) = if self.zero_for_one {
+            (amount_in, amount_out)
+        } else {
+            (amount_out, amount_in)
+        };
+        if amount0 > 0.0 {
+            self.execution_price = (amount1 / amount0).to_string();
+        }
+
+        self.sqrt_price_before = sqrt_price_before.to_string();
+        let price_before = price_from_sqrt_price_x96(sqrt_price_before);
+        let price_after = price_from_sqrt_price_x96(&self.sqrt_price_x96_after);
+        if price_before > 0.0 {
+            let impact_bps = (price_after / price_before - 1.0).abs() * 10_000.0;
+            self.price_impact_bps = impact_bps.to_string();
+        }
+    }
+
+    /// USD value of the swap at its own time, taken from the input side when its price is
+    /// known and from the output side otherwise. Price histories are keyed by standardized
+    /// token address, as returned by `TokenPriceHistory::get_usd_price_history`.
+    pub fn value_usd(&self, price_histories: &AHashMap<String, Vec<(i64, f64)>>) -> f64 {
+        let side_value = |token: &str, amount: &str| {
+            price_histories
+                .get(&standardize_address(token))
+                .and_then(|history| usd_price_at(history, self.timestamp))
+                .map(|price| amount.parse::<f64>().unwrap_or(0.0) * price)
+        };
+        side_value(&self.token_in, &self.amount_in)
+            .or_else(|| side_value(&self.token_out, &self.amount_out))
+            .unwrap_or(0.0)
+    }
 }
@@ -0,0 +1,202 @@
+use ahash::{AHashMap, AHashSet};
+use aptos_indexer_processor_sdk::utils::convert::standardize_address;
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db_models::{hyperion_pool::price_from_sqrt_price_x96, hyperion_swap::HyperionSwap},
+    schema::mev_sandwiches,
+};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = mev_sandwiches)]
+/// Database representation of a sandwich around a victim swap.
+/// The loss is what the victim would have received on top of `amount_out` without the
+/// front-run, estimated from the pool price the front-run moved.
+pub struct MevSandwich {
+    pub victim_swap_id: String,
+    pub pool_address: String,
+    pub attacker: String,
+    pub victim: String,
+    pub front_run_swap_id: String,
+    pub back_run_swap_id: String,
+    pub front_run_version: i64,
+    pub victim_version: i64,
+    pub back_run_version: i64,
+    pub victim_token_out: String,
+    pub estimated_victim_loss: String,
+    pub estimated_victim_loss_usd: String,
+    pub attacker_profit: String,
+    pub timestamp: i64,
+}
+
+/// Share of the victim's output lost to the price move of the front-run. Selling token0
+/// pushes the price (token1 per token0) down and buying it pushes it up, so the victim
+/// trading in the front-run's direction always gets a worse price.
+fn loss_fraction(front_run: &HyperionSwap, victim: &HyperionSwap) -> f64 {
+    let front_run_price = price_from_sqrt_price_x96(&front_run.sqrt_price_before);
+    let victim_price = price_from_sqrt_price_x96(&victim.sqrt_price_before);
+    if front_run_price <= 0.0 || victim_price <= 0.0 {
+        return 0.0;
+    }
+    let fraction = if victim.zero_for_one {
+        front_run_price / victim_price - 1.0
+    } else {
+        victim_price / front_run_price - 1.0
+    };
+    fraction.max(0.0)
+}
+
+impl MevSandwich {
+    fn new(front_run: &HyperionSwap, victim: &HyperionSwap, back_run: &HyperionSwap) -> Self {
+        let fraction = loss_fraction(front_run, victim);
+        let parse = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+        let attacker_profit = if standardize_address(&back_run.token_out)
+            == standardize_address(&front_run.token_in)
+        {
+            parse(&back_run.amount_out) - parse(&front_run.amount_in)
+        } else {
+            0.0
+        };
+        Self {
+            victim_swap_id: victim.swap_id.clone(),
+            pool_address: victim.pool_address.clone(),
+            attacker: front_run.sender.clone(),
+            victim: victim.sender.clone(),
+            front_run_swap_id: front_run.swap_id.clone(),
+            back_run_swap_id: back_run.swap_id.clone(),
+            front_run_version: front_run.tx_version,
+            victim_version: victim.tx_version,
+            back_run_version: back_run.tx_version,
+            victim_token_out: victim.token_out.clone(),
+            estimated_victim_loss: (parse(&victim.amount_out) * fraction).to_string(),
+            estimated_victim_loss_usd: (parse(&victim.amount_usd) * fraction).to_string(),
+            attacker_profit: attacker_profit.to_string(),
+            timestamp: victim.timestamp,
+        }
+    }
+}
+
+/// Sandwiches among `swaps`, sorted by `(tx_version, event_idx)`. A sandwich is a front-run,
+/// one or more swaps of other senders in the same direction in later transactions, and a
+/// swap of the front-runner in the opposite direction in an even later transaction, all in
+/// the same pool and within `max_version_gap` versions of the front-run.
+pub fn detect_sandwiches(swaps: &[HyperionSwap], max_version_gap: i64) -> Vec<MevSandwich> {
+    let mut swaps_by_pool: AHashMap<&str, Vec<&HyperionSwap>> = AHashMap::new();
+    for swap in swaps {
+        swaps_by_pool
+            .entry(swap.pool_address.as_str())
+            .or_default()
+            .push(swap);
+    }
+
+    let mut sandwiches = vec![];
+    let mut sandwiched = AHashSet::new();
+    for pool_swaps in swaps_by_pool.values() {
+        for (i, front_run) in pool_swaps.iter().enumerate() {
+            let mut victims: Vec<&HyperionSwap> = vec![];
+            for swap in &pool_swaps[i + 1..] {
+                if swap.tx_version - front_run.tx_version > max_version_gap {
+                    break;
+                }
+                if swap.tx_version == front_run.tx_version {
+                    continue;
+                }
+                if swap.sender != front_run.sender {
+                    if swap.zero_for_one == front_run.zero_for_one {
+                        victims.push(swap);
+                    }
+                    continue;
+                }
+                if swap.zero_for_one == front_run.zero_for_one {
+                    continue;
+                }
+                // The attacker unwinds, victims of the same transaction do not count
+                for victim in victims
+                    .iter()
+                    .filter(|victim| victim.tx_version < swap.tx_version)
+                {
+                    if sandwiched.insert(victim.swap_id.as_str()) {
+                        sandwiches.push(MevSandwich::new(front_run, victim, swap));
+                    }
+                }
+                break;
+            }
+        }
+    }
+    sandwiches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const Q96: &str = "79228162514264337593543950336";
+
+    fn swap(
+        tx_version: i64,
+        sender: &str,
+        zero_for_one: bool,
+        sqrt_price_before: &str,
+    ) -> HyperionSwap {
+        let (token_in, token_out) = if zero_for_one {
+            ("0xa", "0xb")
+        } else {
+            ("0xb", "0xa")
+        };
+        HyperionSwap {
+            swap_id: format!("0xpool-{}-0", tx_version),
+            pool_address: "0xpool".to_string(),
+            sender: sender.to_string(),
+            recipient: sender.to_string(),
+            token_in: token_in.to_string(),
+            token_out: token_out.to_string(),
+            amount_in: "1000".to_string(),
+            amount_out: "1000".to_string(),
+            sqrt_price_x96_after: "0".to_string(),
+            liquidity_after: "0".to_string(),
+            tick_after: 0,
+            tx_version,
+            event_idx: 0,
+            timestamp: tx_version,
+            amount_usd: "100".to_string(),
+            zero_for_one,
+            sqrt_price_before: sqrt_price_before.to_string(),
+            execution_price: "0".to_string(),
+            price_impact_bps: "0".to_string(),
+            is_wash: false,
+            round_trip_id: None,
+        }
+    }
+
+    #[test]
+    fn test_detects_sandwich_and_victim_loss() {
+        // Front-run sells token0 and leaves the price at 0.9801, the victim then sells too
+        let swaps = vec![
+            swap(10, "0xattacker", true, Q96),
+            swap(11, "0xvictim", true, "78435880889121694217608510833"),
+            swap(12, "0xother", false, Q96),
+            swap(13, "0xattacker", false, Q96),
+        ];
+        let sandwiches = detect_sandwiches(&swaps, 5);
+        assert_eq!(sandwiches.len(), 1);
+        let sandwich = &sandwiches[0];
+        assert_eq!(sandwich.attacker, "0xattacker");
+        assert_eq!(sandwich.victim, "0xvictim");
+        assert_eq!(sandwich.back_run_version, 13);
+        let loss_usd = sandwich.estimated_victim_loss_usd.parse::<f64>().unwrap();
+        assert!((loss_usd - 100.0 * (1.0 / 0.9801 - 1.0)).abs() < 1e-6);
+        assert_eq!(sandwich.attacker_profit, "0");
+    }
+
+    #[test]
+    fn test_ignores_swaps_outside_version_gap() {
+        let swaps = vec![
+            swap(10, "0xattacker", true, Q96),
+            swap(11, "0xvictim", true, Q96),
+            swap(20, "0xattacker", false, Q96),
+        ];
+        assert!(detect_sandwiches(&swaps, 5).is_empty());
+    }
+}
@@ -9,3 +9,16 @@ pub mod trader_stat;
 pub mod hyperion_pool;
 pub mod hyperion_swap;
 pub mod hyperion_pool_stat;
+pub mod hyperion_pool_hourly_stat;
+pub mod hyperion_pool_trader;
+pub mod token_price;
+pub mod token_stat;
+pub mod trader_token_position;
+pub mod trader_pnl_daily;
+pub mod alert;
+pub mod hyperion_pool_liquidity_snapshot;
+pub mod webhook_outbox;
+pub mod webhook_endpoint_status;
+pub mod mev_sandwich;
+pub mod swap_route;
+pub mod wash_trading_score;
@@ -3,7 +3,7 @@ use diesel_async::RunQueryDsl;
 
 use crate::{schema::processor_status, utils::database_utils::DbPoolConnection};
 
-#[derive(AsChangeset, Debug, Insertable)]
+#[derive(AsChangeset, Clone, Debug, Insertable)]
 #[diesel(table_name = processor_status)]
 /// Only tracking the latest version successfully processed
 pub struct ProcessorStatus {
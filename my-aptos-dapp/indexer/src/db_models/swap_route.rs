@@ -0,0 +1,187 @@
+use aptos_indexer_processor_sdk::utils::convert::standardize_address;
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::{db_models::hyperion_swap::HyperionSwap, schema::swap_routes};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = swap_routes)]
+/// Database representation of a multi-hop route through Hyperion pools within one transaction.
+/// Tokens are standardized addresses, amounts are in smallest on-chain units.
+pub struct SwapRoute {
+    pub route_id: String,
+    pub tx_version: i64,
+    pub sender: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: String,
+    pub amount_out: String,
+    pub amount_usd: String,
+    pub effective_price: String,
+    pub pool_path: String,
+    pub token_path: String,
+    pub hop_count: i32,
+    pub is_arbitrage: bool,
+    pub arbitrage_profit: String,
+    pub timestamp: i64,
+}
+
+fn is_cyclic(hops: &[&HyperionSwap]) -> bool {
+    hops.len() >= 2
+        && standardize_address(&hops[0].token_in)
+            == standardize_address(&hops[hops.len() - 1].token_out)
+}
+
+impl SwapRoute {
+    fn from_hops(hops: &[&HyperionSwap]) -> Self {
+        let (first, last) = (hops[0], hops[hops.len() - 1]);
+        let parse = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+        let (amount_in, amount_out) = (parse(&first.amount_in), parse(&last.amount_out));
+        let is_arbitrage = is_cyclic(hops);
+        // Every hop carries roughly the same value, the first priced one stands for the route
+        let amount_usd = hops
+            .iter()
+            .map(|hop| parse(&hop.amount_usd))
+            .find(|amount_usd| *amount_usd > 0.0)
+            .unwrap_or(0.0);
+        let token_path = std::iter::once(standardize_address(&first.token_in))
+            .chain(hops.iter().map(|hop| standardize_address(&hop.token_out)))
+            .collect::<Vec<_>>();
+        Self {
+            route_id: format!("{}-{}", first.tx_version, first.event_idx),
+            tx_version: first.tx_version,
+            sender: first.sender.clone(),
+            token_in: token_path[0].clone(),
+            token_out: token_path[token_path.len() - 1].clone(),
+            amount_in: first.amount_in.clone(),
+            amount_out: last.amount_out.clone(),
+            amount_usd: amount_usd.to_string(),
+            effective_price: if amount_in > 0.0 {
+                (amount_out / amount_in).to_string()
+            } else {
+                "0".to_string()
+            },
+            pool_path: hops
+                .iter()
+                .map(|hop| hop.pool_address.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            token_path: token_path.join(","),
+            hop_count: hops.len() as i32,
+            is_arbitrage,
+            arbitrage_profit: if is_arbitrage {
+                (amount_out - amount_in).to_string()
+            } else {
+                "0".to_string()
+            },
+            timestamp: first.timestamp,
+        }
+    }
+}
+
+/// Chains the swaps of one transaction into routes. A hop extends the latest route that
+/// received the token it spends, a route that is back at its input token is complete.
+fn transaction_routes(tx_swaps: &[HyperionSwap]) -> Vec<SwapRoute> {
+    let mut chains: Vec<Vec<&HyperionSwap>> = vec![];
+    for swap in tx_swaps {
+        let token_in = standardize_address(&swap.token_in);
+        let open_chain = chains.iter_mut().rev().find(|chain| {
+            !is_cyclic(chain) && standardize_address(&chain[chain.len() - 1].token_out) == token_in
+        });
+        match open_chain {
+            Some(chain) => chain.push(swap),
+            None => chains.push(vec![swap]),
+        }
+    }
+    chains
+        .iter()
+        .filter(|chain| chain.len() >= 2)
+        .map(|chain| SwapRoute::from_hops(chain))
+        .collect()
+}
+
+/// Multi-hop routes among `swaps`, sorted by `(tx_version, event_idx)`. Single swaps are
+/// not routes and are left to `hyperion_swaps`.
+pub fn build_routes(swaps: &[HyperionSwap]) -> Vec<SwapRoute> {
+    let mut routes = vec![];
+    let mut start = 0;
+    while start < swaps.len() {
+        let end = swaps[start..]
+            .iter()
+            .position(|swap| swap.tx_version != swaps[start].tx_version)
+            .map_or(swaps.len(), |offset| start + offset);
+        routes.extend(transaction_routes(&swaps[start..end]));
+        start = end;
+    }
+    routes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn swap(
+        tx_version: i64,
+        event_idx: i64,
+        pool: &str,
+        token_in: &str,
+        token_out: &str,
+        amount_in: &str,
+        amount_out: &str,
+    ) -> HyperionSwap {
+        HyperionSwap {
+            swap_id: format!("{}-{}-{}", pool, tx_version, event_idx),
+            pool_address: pool.to_string(),
+            sender: "0xrouter".to_string(),
+            recipient: "0xrouter".to_string(),
+            token_in: token_in.to_string(),
+            token_out: token_out.to_string(),
+            amount_in: amount_in.to_string(),
+            amount_out: amount_out.to_string(),
+            sqrt_price_x96_after: "0".to_string(),
+            liquidity_after: "0".to_string(),
+            tick_after: 0,
+            tx_version,
+            event_idx,
+            timestamp: tx_version,
+            amount_usd: "0".to_string(),
+            zero_for_one: true,
+            sqrt_price_before: "0".to_string(),
+            execution_price: "0".to_string(),
+            price_impact_bps: "0".to_string(),
+            is_wash: false,
+            round_trip_id: None,
+        }
+    }
+
+    #[test]
+    fn test_builds_multi_hop_and_arbitrage_routes() {
+        let swaps = vec![
+            // 0xa -> 0xb -> 0xc
+            swap(1, 0, "0xp1", "0xa", "0xb", "100", "200"),
+            swap(1, 1, "0xp2", "0xb", "0xc", "200", "50"),
+            // Single swap, not a route
+            swap(2, 0, "0xp1", "0xa", "0xb", "10", "20"),
+            // 0xa -> 0xb -> 0xa
+            swap(3, 0, "0xp1", "0xa", "0xb", "100", "200"),
+            swap(3, 1, "0xp3", "0xb", "0xa", "200", "110"),
+        ];
+        let routes = build_routes(&swaps);
+        assert_eq!(routes.len(), 2);
+
+        let route = &routes[0];
+        assert_eq!(route.route_id, "1-0");
+        assert_eq!(route.pool_path, "0xp1,0xp2");
+        assert_eq!(route.token_out, standardize_address("0xc"));
+        assert_eq!(route.amount_in, "100");
+        assert_eq!(route.amount_out, "50");
+        assert_eq!(route.effective_price, "0.5");
+        assert!(!route.is_arbitrage);
+
+        let arbitrage = &routes[1];
+        assert_eq!(arbitrage.hop_count, 2);
+        assert!(arbitrage.is_arbitrage);
+        assert_eq!(arbitrage.arbitrage_profit, "10");
+    }
+}
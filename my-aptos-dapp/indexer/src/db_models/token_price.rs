@@ -0,0 +1,169 @@
+use ahash::AHashMap;
+use aptos_indexer_processor_sdk::utils::convert::standardize_address;
+use diesel::{
+    AsChangeset, ExpressionMethods, Insertable, OptionalExtension, QueryDsl, QueryResult, Queryable,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{token_price_history, token_prices};
+
+#[derive(AsChangeset, Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = token_prices)]
+/// Database representation of a token's USD price derived from indexed pools.
+/// `price_usd` is the value of one smallest on-chain unit of the token.
+pub struct TokenPrice {
+    pub token_address: String,
+    pub price_usd: String,
+    pub liquidity_usd: String,
+    pub anchor_token: String,
+    pub path: String,
+    pub hops: i32,
+    pub timestamp: i64,
+}
+
+impl TokenPrice {
+    /// Latest USD price per smallest unit for each of the given tokens that has one,
+    /// keyed by standardized token address.
+    pub async fn get_usd_prices(
+        conn: &mut AsyncPgConnection,
+        token_addresses: Vec<String>,
+    ) -> QueryResult<AHashMap<String, f64>> {
+        let token_addresses = token_addresses
+            .iter()
+            .map(|address| standardize_address(address))
+            .collect::<Vec<_>>();
+        let prices = token_prices::table
+            .filter(token_prices::token_address.eq_any(token_addresses))
+            .load::<Self>(conn)
+            .await?;
+        Ok(prices
+            .into_iter()
+            .map(|price| {
+                let price_usd = price.price_usd.parse::<f64>().unwrap_or(0.0);
+                (price.token_address, price_usd)
+            })
+            .collect())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = token_price_history)]
+/// A routed USD price as of the chain time of the batch it was routed for. Unlike
+/// `token_prices`, every routing is kept.
+pub struct TokenPriceHistory {
+    pub token_address: String,
+    pub timestamp: i64,
+    pub price_usd: String,
+    pub liquidity_usd: String,
+    pub anchor_token: String,
+    pub path: String,
+    pub hops: i32,
+}
+
+impl From<&TokenPrice> for TokenPriceHistory {
+    fn from(price: &TokenPrice) -> Self {
+        Self {
+            token_address: price.token_address.clone(),
+            timestamp: price.timestamp,
+            price_usd: price.price_usd.clone(),
+            liquidity_usd: price.liquidity_usd.clone(),
+            anchor_token: price.anchor_token.clone(),
+            path: price.path.clone(),
+            hops: price.hops,
+        }
+    }
+}
+
+impl TokenPriceHistory {
+    /// The `(timestamp, price_usd)` routings of each token that cover `[from, to]`: the
+    /// last one at or before `from`, or the first one after it when the token has none that
+    /// old, and every one up to `to`. Keyed by standardized token address, oldest first.
+    pub async fn get_usd_price_history(
+        conn: &mut AsyncPgConnection,
+        token_addresses: Vec<String>,
+        from: i64,
+        to: i64,
+    ) -> QueryResult<AHashMap<String, Vec<(i64, f64)>>> {
+        let mut token_addresses = token_addresses
+            .iter()
+            .map(|address| standardize_address(address))
+            .collect::<Vec<_>>();
+        token_addresses.sort();
+        token_addresses.dedup();
+
+        let mut histories = AHashMap::new();
+        for token_address in token_addresses {
+            let last_before = token_price_history::table
+                .filter(token_price_history::token_address.eq(&token_address))
+                .filter(token_price_history::timestamp.le(from))
+                .order(token_price_history::timestamp.desc())
+                .select((
+                    token_price_history::timestamp,
+                    token_price_history::price_usd,
+                ))
+                .first::<(i64, String)>(conn)
+                .await
+                .optional()?;
+            let first = match last_before {
+                Some(last_before) => Some(last_before),
+                None => token_price_history::table
+                    .filter(token_price_history::token_address.eq(&token_address))
+                    .order(token_price_history::timestamp.asc())
+                    .select((
+                        token_price_history::timestamp,
+                        token_price_history::price_usd,
+                    ))
+                    .first::<(i64, String)>(conn)
+                    .await
+                    .optional()?,
+            };
+            let Some(first) = first else {
+                continue;
+            };
+            let rest = token_price_history::table
+                .filter(token_price_history::token_address.eq(&token_address))
+                .filter(token_price_history::timestamp.gt(first.0))
+                .filter(token_price_history::timestamp.le(to))
+                .order(token_price_history::timestamp.asc())
+                .select((
+                    token_price_history::timestamp,
+                    token_price_history::price_usd,
+                ))
+                .load::<(i64, String)>(conn)
+                .await?;
+            let prices = std::iter::once(first)
+                .chain(rest)
+                .map(|(timestamp, price_usd)| (timestamp, price_usd.parse::<f64>().unwrap_or(0.0)))
+                .collect();
+            histories.insert(token_address, prices);
+        }
+        Ok(histories)
+    }
+}
+
+/// Price as of `timestamp` in a history returned by `get_usd_price_history`: the last one
+/// routed at or before it, or the first one routed after it.
+pub fn usd_price_at(history: &[(i64, f64)], timestamp: i64) -> Option<f64> {
+    let routed_after = history.partition_point(|(routed_at, _)| *routed_at <= timestamp);
+    history
+        .get(routed_after.saturating_sub(1))
+        .map(|(_, price_usd)| *price_usd)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_usd_price_at_takes_the_last_routing_before_the_swap() {
+        let history = [(100, 1.0), (160, 2.0), (220, 3.0)];
+        assert_eq!(usd_price_at(&history, 50), Some(1.0));
+        assert_eq!(usd_price_at(&history, 100), Some(1.0));
+        assert_eq!(usd_price_at(&history, 159), Some(1.0));
+        assert_eq!(usd_price_at(&history, 160), Some(2.0));
+        assert_eq!(usd_price_at(&history, 1_000), Some(3.0));
+        assert_eq!(usd_price_at(&[], 100), None);
+    }
+}
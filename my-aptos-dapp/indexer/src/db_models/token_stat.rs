@@ -0,0 +1,24 @@
+use diesel::{AsChangeset, Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::token_stats;
+
+#[derive(AsChangeset, Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = token_stats)]
+/// Database representation of a token's activity aggregated across every pool containing it.
+/// `liquidity` and `volume_24h`/`volume_7d` are in smallest on-chain units of the token.
+pub struct TokenStat {
+    pub token_address: String,
+    pub liquidity: String,
+    pub liquidity_usd: String,
+    pub volume_24h: String,
+    pub volume_7d: String,
+    pub volume_usd_24h: String,
+    pub volume_usd_7d: String,
+    pub swap_count_24h: i64,
+    pub swap_count_7d: i64,
+    pub pool_count: i32,
+    pub best_pool_address: String,
+    pub last_update_timestamp: i64,
+}
@@ -0,0 +1,21 @@
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::{db_models::hyperion_pool_stat::WINDOW_24H_SECS, schema::trader_pnl_daily};
+
+/// Start of the UTC day (in chain seconds) that `timestamp` falls into.
+pub fn day_bucket(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(WINDOW_24H_SECS)
+}
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = trader_pnl_daily)]
+/// Database representation of a trader's realized PnL and swap volume during one day
+pub struct TraderPnlDaily {
+    pub trader_addr: String,
+    pub day_start: i64,
+    pub realized_pnl_usd: String,
+    pub volume_usd: String,
+    pub swap_count: i64,
+}
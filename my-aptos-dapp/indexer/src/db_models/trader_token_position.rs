@@ -0,0 +1,103 @@
+use diesel::{AsChangeset, Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::trader_token_positions;
+
+#[derive(AsChangeset, Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = trader_token_positions)]
+/// Database representation of a trader's average cost position in a token.
+/// `quantity` is in smallest on-chain units, `cost_basis_usd` is the total cost of it.
+pub struct TraderTokenPosition {
+    pub trader_addr: String,
+    pub token_address: String,
+    pub quantity: String,
+    pub cost_basis_usd: String,
+    pub realized_pnl_usd: String,
+    pub last_tx_version: i64,
+    pub last_event_idx: i64,
+    pub last_update_timestamp: i64,
+}
+
+impl TraderTokenPosition {
+    pub fn new(trader_addr: String, token_address: String) -> Self {
+        Self {
+            trader_addr,
+            token_address,
+            quantity: "0".to_string(),
+            cost_basis_usd: "0".to_string(),
+            realized_pnl_usd: "0".to_string(),
+            last_tx_version: -1,
+            last_event_idx: -1,
+            last_update_timestamp: 0,
+        }
+    }
+
+    /// Whether the swap at `(tx_version, event_idx)` is already reflected in the position.
+    pub fn has_applied(&self, tx_version: i64, event_idx: i64) -> bool {
+        (tx_version, event_idx) <= (self.last_tx_version, self.last_event_idx)
+    }
+
+    fn quantity_and_cost(&self) -> (f64, f64) {
+        (
+            self.quantity.parse::<f64>().unwrap_or(0.0),
+            self.cost_basis_usd.parse::<f64>().unwrap_or(0.0),
+        )
+    }
+
+    pub fn acquire(&mut self, amount: f64, cost_usd: f64) {
+        let (quantity, cost_basis) = self.quantity_and_cost();
+        self.quantity = (quantity + amount).to_string();
+        self.cost_basis_usd = (cost_basis + cost_usd).to_string();
+    }
+
+    /// Sells `amount` at the average cost and returns the realized PnL. Only the part of
+    /// `amount` covered by the tracked quantity realizes PnL, tokens acquired outside of
+    /// indexed swaps have no known cost. Unpriced disposals (`proceeds_usd` of 0) release
+    /// their cost without realizing anything.
+    pub fn dispose(&mut self, amount: f64, proceeds_usd: f64) -> f64 {
+        let (quantity, cost_basis) = self.quantity_and_cost();
+        if quantity <= 0.0 || amount <= 0.0 {
+            return 0.0;
+        }
+        let sold = amount.min(quantity);
+        let released_cost = cost_basis * sold / quantity;
+        self.quantity = (quantity - sold).to_string();
+        self.cost_basis_usd = (cost_basis - released_cost).to_string();
+        if proceeds_usd <= 0.0 {
+            return 0.0;
+        }
+        let realized = proceeds_usd * sold / amount - released_cost;
+        let realized_pnl = self.realized_pnl_usd.parse::<f64>().unwrap_or(0.0);
+        self.realized_pnl_usd = (realized_pnl + realized).to_string();
+        realized
+    }
+
+    pub fn mark_applied(&mut self, tx_version: i64, event_idx: i64, timestamp: i64) {
+        self.last_tx_version = tx_version;
+        self.last_event_idx = event_idx;
+        self.last_update_timestamp = timestamp;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_average_cost_realized_pnl() {
+        let mut position = TraderTokenPosition::new("0x1".to_string(), "0x2".to_string());
+        position.acquire(100.0, 100.0);
+        position.acquire(100.0, 300.0);
+        // Average cost is 2 USD, selling half at 3 USD realizes 100 USD
+        let realized = position.dispose(100.0, 300.0);
+        assert!((realized - 100.0).abs() < 1e-9);
+        assert_eq!(position.quantity, "100");
+        assert_eq!(position.cost_basis_usd, "200");
+        // Only the tracked 100 units realize PnL, the rest has no known cost
+        let realized = position.dispose(200.0, 400.0);
+        assert!(realized.abs() < 1e-9);
+        assert_eq!(position.realized_pnl_usd, "100");
+        assert_eq!(position.quantity, "0");
+    }
+}
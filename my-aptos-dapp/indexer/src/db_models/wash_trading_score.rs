@@ -0,0 +1,261 @@
+use ahash::{AHashMap, AHashSet};
+use diesel::{AsChangeset, Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::indexer_processor_config::WashTradingConfig, schema::wash_trading_scores};
+
+#[derive(AsChangeset, Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = wash_trading_scores)]
+/// Database representation of an address' wash trading score in a pool, or in the trades
+/// of the trading contract when `pool_address` is empty.
+pub struct WashTradingScore {
+    pub address: String,
+    pub pool_address: String,
+    /// Share of the volume in round trips, in percent
+    pub score: String,
+    pub round_trips: i64,
+    pub wash_volume: String,
+    pub total_volume: String,
+    pub flagged: bool,
+    pub last_update_timestamp: i64,
+}
+
+/// One side of a potential round trip, a Hyperion swap or a trade.
+pub struct WashLeg {
+    /// swap_id or trade_obj_addr
+    pub id: String,
+    pub address: String,
+    /// Pool of a swap, empty for trades
+    pub pool_address: String,
+    /// Representative of the cluster the address belongs to
+    pub cluster: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: f64,
+    pub amount_out: f64,
+    pub volume: f64,
+    pub timestamp: i64,
+}
+
+fn find_root<'a>(parents: &'a AHashMap<String, String>, address: &'a str) -> &'a str {
+    let mut root = address;
+    while let Some(parent) = parents.get(root) {
+        root = parent;
+    }
+    root
+}
+
+/// Resolves every linked address to a representative of its cluster, the smallest address
+/// in it. Swapping with another address as recipient links both addresses.
+pub fn cluster_addresses<'a>(
+    links: impl Iterator<Item = (&'a str, &'a str)>,
+) -> AHashMap<String, String> {
+    let mut parents: AHashMap<String, String> = AHashMap::new();
+    let mut addresses = AHashSet::new();
+    for (address, linked) in links {
+        addresses.insert(address);
+        addresses.insert(linked);
+        let root = find_root(&parents, address).to_string();
+        let linked_root = find_root(&parents, linked).to_string();
+        if root != linked_root {
+            let (kept, merged) = if root < linked_root {
+                (root, linked_root)
+            } else {
+                (linked_root, root)
+            };
+            parents.insert(merged, kept);
+        }
+    }
+    addresses
+        .into_iter()
+        .map(|address| {
+            (
+                address.to_string(),
+                find_root(&parents, address).to_string(),
+            )
+        })
+        .collect()
+}
+
+fn within_tolerance(expected: f64, actual: f64, tolerance_percent: f64) -> bool {
+    expected > 0.0 && (expected - actual).abs() <= expected * tolerance_percent / 100.0
+}
+
+/// Pairs of legs, sorted by time, where a cluster swaps back what it just swapped within
+/// the window, leaving its position about unchanged. A leg is part of one round trip at most.
+pub fn match_round_trips(legs: &[WashLeg], config: &WashTradingConfig) -> Vec<(usize, usize)> {
+    let mut matched = vec![false; legs.len()];
+    let mut round_trips = vec![];
+    for (i, first) in legs.iter().enumerate() {
+        if matched[i] {
+            continue;
+        }
+        for (j, second) in legs.iter().enumerate().skip(i + 1) {
+            if second.timestamp - first.timestamp > config.round_trip_window_secs {
+                break;
+            }
+            let is_round_trip = !matched[j]
+                && second.pool_address == first.pool_address
+                && second.cluster == first.cluster
+                && second.token_in == first.token_out
+                && second.token_out == first.token_in
+                && within_tolerance(
+                    first.amount_in,
+                    second.amount_out,
+                    config.amount_tolerance_percent,
+                )
+                && within_tolerance(
+                    first.amount_out,
+                    second.amount_in,
+                    config.amount_tolerance_percent,
+                );
+            if is_round_trip {
+                matched[i] = true;
+                matched[j] = true;
+                round_trips.push((i, j));
+                break;
+            }
+        }
+    }
+    round_trips
+}
+
+/// Volume of an address in a pool within the score window, and the round trip it was matched
+/// in, if any.
+pub struct ScoredLeg {
+    pub address: String,
+    pub pool_address: String,
+    pub volume: f64,
+    pub round_trip_id: Option<String>,
+}
+
+/// Scores every address and pool of `legs`, all of their legs within the score window.
+pub fn score_legs(
+    legs: &[ScoredLeg],
+    config: &WashTradingConfig,
+    timestamp: i64,
+) -> Vec<WashTradingScore> {
+    // (total volume, wash volume, round trips) per address and pool
+    let mut totals: AHashMap<(&str, &str), (f64, f64, AHashSet<&str>)> = AHashMap::new();
+    for leg in legs {
+        let total = totals
+            .entry((leg.address.as_str(), leg.pool_address.as_str()))
+            .or_default();
+        total.0 += leg.volume;
+        // A round trip between two clustered addresses counts for both
+        if let Some(round_trip_id) = &leg.round_trip_id {
+            total.1 += leg.volume;
+            total.2.insert(round_trip_id);
+        }
+    }
+    totals
+        .into_iter()
+        .map(
+            |((address, pool_address), (total_volume, wash_volume, round_trips))| {
+                let score = if total_volume > 0.0 {
+                    wash_volume / total_volume * 100.0
+                } else {
+                    0.0
+                };
+                let round_trips = round_trips.len() as i64;
+                WashTradingScore {
+                    address: address.to_string(),
+                    pool_address: pool_address.to_string(),
+                    score: score.to_string(),
+                    round_trips,
+                    wash_volume: wash_volume.to_string(),
+                    total_volume: total_volume.to_string(),
+                    flagged: score >= config.flag_score && round_trips >= config.min_round_trips,
+                    last_update_timestamp: timestamp,
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leg(id: &str, address: &str, zero_for_one: bool, amount: f64, timestamp: i64) -> WashLeg {
+        let (token_in, token_out) = if zero_for_one {
+            ("0xa", "0xb")
+        } else {
+            ("0xb", "0xa")
+        };
+        WashLeg {
+            id: id.to_string(),
+            address: address.to_string(),
+            pool_address: "0xpool".to_string(),
+            cluster: address.to_string(),
+            token_in: token_in.to_string(),
+            token_out: token_out.to_string(),
+            amount_in: amount,
+            amount_out: amount * 2.0,
+            volume: 100.0,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_flags_repeated_round_trips() {
+        let mut legs = vec![
+            leg("1", "0xwash", true, 1000.0, 0),
+            leg("2", "0xwash", false, 2000.0, 60),
+            leg("3", "0xwash", true, 1000.0, 120),
+            leg("4", "0xwash", false, 2000.0, 180),
+            // Round trip outside the window
+            leg("5", "0xtrader", true, 1000.0, 0),
+            leg("6", "0xtrader", false, 2000.0, 7200),
+        ];
+        // Swapping back 2000 of 0xb returns 1000 of 0xa, the net position is unchanged
+        for leg in legs.iter_mut().filter(|leg| leg.token_in == "0xb") {
+            leg.amount_out = leg.amount_in / 2.0;
+        }
+        legs.sort_by_key(|leg| leg.timestamp);
+        let config = WashTradingConfig::default();
+        let round_trips = match_round_trips(&legs, &config);
+        assert_eq!(round_trips, vec![(0, 2), (3, 4)]);
+
+        let mut round_trip_ids = vec![None; legs.len()];
+        for (i, j) in round_trips {
+            round_trip_ids[i] = Some(legs[i].id.clone());
+            round_trip_ids[j] = Some(legs[i].id.clone());
+        }
+        let scored_legs = legs
+            .iter()
+            .zip(round_trip_ids)
+            .map(|(leg, round_trip_id)| ScoredLeg {
+                address: leg.address.clone(),
+                pool_address: leg.pool_address.clone(),
+                volume: leg.volume,
+                round_trip_id,
+            })
+            .collect::<Vec<_>>();
+        let scores = score_legs(&scored_legs, &config, 7200);
+
+        let wash = scores
+            .iter()
+            .find(|score| score.address == "0xwash")
+            .unwrap();
+        assert!(wash.flagged);
+        assert_eq!(wash.round_trips, 2);
+        assert_eq!(wash.score, "100");
+        let trader = scores
+            .iter()
+            .find(|score| score.address == "0xtrader")
+            .unwrap();
+        assert!(!trader.flagged);
+        assert_eq!(trader.score, "0");
+    }
+
+    #[test]
+    fn test_clusters_linked_addresses() {
+        let clusters =
+            cluster_addresses([("0xc", "0xb"), ("0xb", "0xa"), ("0xd", "0xd")].into_iter());
+        assert_eq!(clusters["0xc"], "0xa");
+        assert_eq!(clusters["0xb"], "0xa");
+        assert_eq!(clusters["0xd"], "0xd");
+    }
+}
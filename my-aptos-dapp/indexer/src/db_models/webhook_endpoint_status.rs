@@ -0,0 +1,20 @@
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::webhook_endpoint_status;
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Queryable, Serialize)]
+#[diesel(table_name = webhook_endpoint_status)]
+/// Database representation of the delivery health of a webhook endpoint
+pub struct WebhookEndpointStatus {
+    pub endpoint: String,
+    pub url: String,
+    pub delivered_count: i64,
+    /// Deliveries given up on after `max_attempts`
+    pub failed_count: i64,
+    pub consecutive_failures: i32,
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+    pub last_error: Option<String>,
+}
@@ -0,0 +1,52 @@
+use diesel::{Insertable, Queryable};
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::webhook_outbox;
+
+pub const WEBHOOK_STATUS_PENDING: &str = "pending";
+pub const WEBHOOK_STATUS_DELIVERED: &str = "delivered";
+pub const WEBHOOK_STATUS_FAILED: &str = "failed";
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Queryable, Serialize)]
+#[diesel(table_name = webhook_outbox)]
+/// Database representation of a webhook delivery waiting in, or done with, the outbox
+pub struct WebhookOutboxEntry {
+    pub id: i64,
+    pub endpoint: String,
+    pub dedup_key: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub delivered_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Serialize)]
+#[diesel(table_name = webhook_outbox)]
+/// New outbox row, `id` is assigned by the database
+pub struct NewWebhookOutboxEntry {
+    pub endpoint: String,
+    pub dedup_key: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: i64,
+    pub created_at: i64,
+}
+
+impl NewWebhookOutboxEntry {
+    pub fn new(endpoint: String, dedup_key: String, payload: String, now: i64) -> Self {
+        Self {
+            endpoint,
+            dedup_key,
+            payload,
+            status: WEBHOOK_STATUS_PENDING.to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            created_at: now,
+        }
+    }
+}
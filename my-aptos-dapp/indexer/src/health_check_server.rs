@@ -1,13 +1,35 @@
-//! This contains the health server, a basic server that for now always returns 200.
-//! This is necessary to run the processor in Cloud Run, which expects to be able to
-//! query a HTTP server to check for liveness.
+//! This contains the health server. It is necessary to run the processor in Cloud Run,
+//! which expects to be able to query a HTTP server to check for liveness.
+//! `/healthz` answers as long as the process serves requests, so an unreachable database
+//! never gets the processor restarted. `/readyz` fails when the database is unreachable or
+//! the processor lags behind the chain by more than the configured threshold. It also
+//! serves the processor's Prometheus metrics on `/metrics`.
 
 use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
 use poem::{
-    get, handler, http::Method, listener::TcpListener, middleware::Cors, EndpointExt, Route, Server,
+    get, handler,
+    http::{Method, StatusCode},
+    listener::TcpListener,
+    middleware::Cors,
+    web::{Data, Json},
+    EndpointExt, IntoResponse, Response, Route, Server,
 };
 use serde::{Deserialize, Serialize};
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    time::Duration,
+};
+
+use crate::{
+    db_models::processor_status::ProcessorStatusQuery,
+    utils::{
+        database_connection::get_db_connection, database_utils::ArcDbPool, metrics::encode_metrics,
+    },
+};
+
+// A probe that cannot reach the database within this time reports it as down
+const DB_CHECK_TIMEOUT_SECS: u64 = 5;
 
 /// This configures the health server.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -16,18 +38,102 @@ pub struct HealthServerConfig {
     pub listen_address: SocketAddrV4,
 }
 
+impl HealthServerConfig {
+    /// Listens on all interfaces, on the `health_check_port` of the config file.
+    pub fn from_port(port: u16) -> Self {
+        Self {
+            listen_address: SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port),
+        }
+    }
+}
+
 impl Default for HealthServerConfig {
     fn default() -> Self {
+        Self::from_port(8080)
+    }
+}
+
+/// Checks the processor's progress in `processor_status`.
+#[derive(Clone)]
+pub struct HealthProbe {
+    pool: ArcDbPool,
+    processor_name: String,
+    max_lag_secs: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthReport {
+    db_connected: bool,
+    processor: String,
+    last_success_version: Option<i64>,
+    last_transaction_timestamp: Option<NaiveDateTime>,
+    lag_secs: Option<i64>,
+    max_lag_secs: i64,
+}
+
+impl HealthReport {
+    fn is_ready(&self) -> bool {
+        self.db_connected
+            && self
+                .lag_secs
+                .is_some_and(|lag_secs| lag_secs <= self.max_lag_secs)
+    }
+}
+
+impl HealthProbe {
+    pub fn new(pool: ArcDbPool, processor_name: String, max_lag_secs: i64) -> Self {
         Self {
-            listen_address: SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 8080),
+            pool,
+            processor_name,
+            max_lag_secs,
+        }
+    }
+
+    async fn processor_status(&self) -> Result<Option<ProcessorStatusQuery>> {
+        let conn = &mut get_db_connection(&self.pool).await?;
+        Ok(ProcessorStatusQuery::get_by_processor(&self.processor_name, conn).await?)
+    }
+
+    async fn report(&self) -> HealthReport {
+        let status = tokio::time::timeout(
+            Duration::from_secs(DB_CHECK_TIMEOUT_SECS),
+            self.processor_status(),
+        )
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|status| status);
+        let (db_connected, status) = match status {
+            Ok(status) => (true, status),
+            Err(e) => {
+                tracing::warn!("Health check could not read processor status: {:?}", e);
+                (false, None)
+            }
+        };
+        let last_transaction_timestamp = status
+            .as_ref()
+            .and_then(|status| status.last_transaction_timestamp);
+        HealthReport {
+            db_connected,
+            processor: self.processor_name.clone(),
+            last_success_version: status.as_ref().map(|status| status.last_success_version),
+            last_transaction_timestamp,
+            lag_secs: last_transaction_timestamp
+                .map(|timestamp| (chrono::Utc::now().naive_utc() - timestamp).num_seconds()),
+            max_lag_secs: self.max_lag_secs,
         }
     }
 }
 
-pub async fn run(config: HealthServerConfig) -> Result<()> {
+pub async fn run(config: HealthServerConfig, probe: HealthProbe) -> Result<()> {
     tracing::info!("Health server starting at {}", config.listen_address);
     let cors = Cors::new().allow_methods(vec![Method::GET, Method::POST]);
-    let route = Route::new().nest("/", get(root)).with(cors);
+    let route = Route::new()
+        .at("/healthz", get(healthz))
+        .at("/readyz", get(readyz))
+        .at("/metrics", get(metrics))
+        .nest("/", get(root))
+        .data(probe)
+        .with(cors);
     Server::new(TcpListener::bind(config.listen_address))
         .name("health-server")
         .run(route)
@@ -39,3 +145,34 @@ pub async fn run(config: HealthServerConfig) -> Result<()> {
 async fn root() -> String {
     "Hello from the root!!".to_string()
 }
+
+#[handler]
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+#[handler]
+async fn readyz(probe: Data<&HealthProbe>) -> Response {
+    let report = probe.report().await;
+    let status = if report.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Json(report).with_status(status).into_response()
+}
+
+#[handler]
+async fn metrics() -> Response {
+    match encode_metrics() {
+        Ok(body) => Response::builder()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(e) => {
+            tracing::error!("Failed to encode metrics: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .finish()
+        }
+    }
+}
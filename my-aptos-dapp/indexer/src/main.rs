@@ -1,26 +1,119 @@
-use anyhow::Result;
-use aptos_indexer_processor_sdk_server_framework::ServerArgs;
+use anyhow::{anyhow, Result};
+use aptos_indexer_processor_sdk_server_framework::{
+    load, setup_logging, setup_panic_handler, GenericConfig, RunnableConfig, ServerArgs,
+};
 use clap::Parser;
 use indexer::{
-    config::indexer_processor_config::IndexerProcessorConfig,
-    health_check_server::{self, HealthServerConfig},
+    config::indexer_processor_config::{BackfillConfig, IndexerProcessorConfig, RecordConfig},
+    health_check_server::{self, HealthProbe, HealthServerConfig},
+    utils::{
+        database_connection::new_db_pool,
+        shutdown::{request_shutdown, termination_signal},
+    },
 };
+use std::{path::PathBuf, time::Duration};
 
 #[cfg(unix)]
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
-async fn run_health_server() -> Result<()> {
-    health_check_server::run(HealthServerConfig::default()).await
+#[derive(Parser)]
+struct IndexerArgs {
+    #[clap(flatten)]
+    server_args: ServerArgs,
+    /// Last version to process, overrides `ending_version` of the config
+    #[clap(long)]
+    ending_version: Option<u64>,
+    /// Backfills up to the ending version with this many parallel pipelines
+    #[clap(long)]
+    backfill_workers: Option<u64>,
+    /// Records every batch of the transaction stream to this directory
+    #[clap(long)]
+    record: Option<PathBuf>,
+}
+
+async fn run_health_server(config: &GenericConfig<IndexerProcessorConfig>) -> Result<()> {
+    let server_config = &config.server_config;
+    let pool = new_db_pool(
+        &server_config.db_config.postgres_connection_string,
+        server_config.health_config.db_pool_size,
+    )
+    .await;
+    let probe = HealthProbe::new(
+        pool,
+        server_config.processor_config.name().to_string(),
+        server_config.health_config.max_lag_secs,
+    );
+    health_check_server::run(
+        HealthServerConfig::from_port(config.health_check_port),
+        probe,
+    )
+    .await
 }
 
-async fn run_indexer() -> Result<()> {
-    ServerArgs::parse()
-        .run::<IndexerProcessorConfig>(tokio::runtime::Handle::current())
-        .await
+async fn run_indexer(config: &IndexerProcessorConfig) -> Result<()> {
+    // Runs the processor without the framework's own probe server, which would also bind
+    // health_check_port
+    config.run().await
+}
+
+/// Runs the health server and the processor until the processor is done, which only happens
+/// on its own once it reaches the ending version, or until SIGTERM. On SIGTERM the pipelines
+/// stop reading transactions and drain, and the health server keeps answering until they
+/// are done or the deadline passes.
+async fn run_until_shutdown(config: &GenericConfig<IndexerProcessorConfig>) -> Result<()> {
+    let health_server = run_health_server(config);
+    let indexer = run_indexer(&config.server_config);
+    tokio::pin!(health_server, indexer);
+    tokio::select! {
+        result = &mut health_server => return result,
+        result = &mut indexer => return result,
+        _ = termination_signal() => {}
+    }
+
+    let deadline_secs = config.server_config.shutdown_config.deadline_secs;
+    tracing::info!(
+        "Shutdown requested, draining the pipelines for up to {}s",
+        deadline_secs
+    );
+    request_shutdown();
+    tokio::select! {
+        result = &mut health_server => result,
+        result = tokio::time::timeout(Duration::from_secs(deadline_secs), &mut indexer) => {
+            match result {
+                Ok(result) => result,
+                Err(_) => Err(anyhow!(
+                    "Pipelines did not drain within {}s, the batches in flight are replayed on restart",
+                    deadline_secs
+                )),
+            }
+        }
+        // A second signal skips the drain
+        _ = termination_signal() => Err(anyhow!("Interrupted while draining the pipelines")),
+    }
 }
 
 fn main() -> Result<()> {
+    let args = IndexerArgs::parse();
+    let mut config = load::<GenericConfig<IndexerProcessorConfig>>(&args.server_args.config_path)?;
+    if args.ending_version.is_some() {
+        config.server_config.ending_version = args.ending_version;
+    }
+    if let Some(workers) = args.backfill_workers {
+        config.server_config.backfill_config = Some(BackfillConfig { workers });
+    }
+    if let Some(output_dir) = args.record {
+        let addresses = config
+            .server_config
+            .record_config
+            .take()
+            .map(|record_config| record_config.addresses)
+            .unwrap_or_default();
+        config.server_config.record_config = Some(RecordConfig {
+            output_dir,
+            addresses,
+        });
+    }
     let num_cpus = num_cpus::get();
     let worker_threads = (num_cpus).max(16);
 
@@ -32,7 +125,8 @@ fn main() -> Result<()> {
         .build()
         .unwrap()
         .block_on(async {
-            tokio::try_join!(run_health_server(), run_indexer())?;
-            Ok(())
+            setup_logging();
+            setup_panic_handler();
+            run_until_shutdown(&config).await
         })
 }
@@ -0,0 +1,262 @@
+use ahash::AHashMap;
+use anyhow::Result;
+use aptos_indexer_processor_sdk::{
+    traits::{async_step::AsyncRunType, AsyncStep, NamedStep, Processable},
+    types::transaction_context::TransactionContext,
+    utils::errors::ProcessorError,
+};
+use async_trait::async_trait;
+use diesel::{ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use std::sync::Arc;
+
+use super::{
+    alert_rules::{evaluate_rules, RuleBatch},
+    extractor::{ContractEvent, TransactionContextData},
+    storers::alert_storer::process_alerts,
+};
+use crate::{
+    config::indexer_processor_config::AlertConfig,
+    db_models::{
+        alert::{Alert, ALERT_TYPE_LARGE_SWAP, SEVERITY_CRITICAL, SEVERITY_WARNING},
+        hyperion_pool::HyperionPool,
+        hyperion_swap::HyperionSwap,
+    },
+    schema::{hyperion_pool_stats, hyperion_swaps},
+    utils::{
+        alert_sink::{build_alert_sinks, AlertSink, SinkEvent},
+        database_connection::get_db_connection,
+        database_utils::ArcDbPool,
+        metrics::record_rows_written,
+        query_retry::QueryRetryPolicy,
+    },
+};
+
+/// AlertDetector is a step that flags notable activity once a batch has been stored.
+pub struct AlertDetector
+where
+    Self: Sized + Send + 'static,
+{
+    pool: ArcDbPool,
+    alert_config: AlertConfig,
+    retry_policy: QueryRetryPolicy,
+    sinks: Vec<Arc<dyn AlertSink>>,
+}
+
+impl AsyncStep for AlertDetector {}
+
+impl NamedStep for AlertDetector {
+    fn name(&self) -> String {
+        "AlertDetector".to_string()
+    }
+}
+
+impl AlertDetector {
+    pub fn new(pool: ArcDbPool, alert_config: AlertConfig, retry_policy: QueryRetryPolicy) -> Self {
+        let sinks = build_alert_sinks(&alert_config.sinks, pool.clone());
+        Self {
+            pool,
+            alert_config,
+            retry_policy,
+            sinks,
+        }
+    }
+}
+
+/// Stored swaps of the version range, valued in USD by the Storer, and the current TVL of
+/// their pools.
+async fn load_swaps_and_tvl(
+    conn: &mut AsyncPgConnection,
+    start_version: i64,
+    end_version: i64,
+) -> QueryResult<(Vec<HyperionSwap>, AHashMap<String, f64>)> {
+    let swaps = hyperion_swaps::table
+        .filter(hyperion_swaps::tx_version.between(start_version, end_version))
+        .order((hyperion_swaps::tx_version, hyperion_swaps::event_idx))
+        .load::<HyperionSwap>(conn)
+        .await?;
+    let tvl_by_pool = hyperion_pool_stats::table
+        .filter(
+            hyperion_pool_stats::pool_address.eq_any(
+                swaps
+                    .iter()
+                    .map(|swap| swap.pool_address.clone())
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .select((
+            hyperion_pool_stats::pool_address,
+            hyperion_pool_stats::tvl_usd,
+        ))
+        .load::<(String, String)>(conn)
+        .await?
+        .into_iter()
+        .map(|(pool_address, tvl_usd)| (pool_address, tvl_usd.parse::<f64>().unwrap_or(0.0)))
+        .collect();
+    Ok((swaps, tvl_by_pool))
+}
+
+/// Swaps above the USD or percent-of-TVL thresholds. Unpriced swaps are never flagged.
+fn detect_large_swaps(
+    swaps: &[HyperionSwap],
+    tvl_by_pool: &AHashMap<String, f64>,
+    config: &AlertConfig,
+) -> Vec<Alert> {
+    swaps
+        .iter()
+        .filter_map(|swap| {
+            let value_usd = swap.amount_usd.parse::<f64>().unwrap_or(0.0);
+            if value_usd <= 0.0 {
+                return None;
+            }
+            let tvl_usd = tvl_by_pool.get(&swap.pool_address).copied().unwrap_or(0.0);
+            let tvl_percent = if tvl_usd > 0.0 {
+                value_usd / tvl_usd * 100.0
+            } else {
+                0.0
+            };
+            if value_usd < config.large_swap_usd && tvl_percent < config.large_swap_tvl_percent {
+                return None;
+            }
+            let severity = if value_usd >= config.critical_swap_usd
+                || tvl_percent >= config.critical_swap_tvl_percent
+            {
+                SEVERITY_CRITICAL
+            } else {
+                SEVERITY_WARNING
+            };
+            Some(Alert {
+                alert_id: format!("{}-{}", ALERT_TYPE_LARGE_SWAP, swap.swap_id),
+                alert_type: ALERT_TYPE_LARGE_SWAP.to_string(),
+                severity: severity.to_string(),
+                pool_address: swap.pool_address.clone(),
+                address: swap.sender.clone(),
+                tx_version: swap.tx_version,
+                value: value_usd.to_string(),
+                message: format!(
+                    "Swap of ${:.0} ({:.2}% of TVL) in pool {}",
+                    value_usd, tvl_percent, swap.pool_address
+                ),
+                timestamp: swap.timestamp,
+                rule_name: String::new(),
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Processable for AlertDetector {
+    type Input = TransactionContextData;
+    type Output = TransactionContextData;
+    type RunType = AsyncRunType;
+
+    async fn process(
+        &mut self,
+        transaction_context_data: TransactionContext<TransactionContextData>,
+    ) -> Result<Option<TransactionContext<TransactionContextData>>, ProcessorError> {
+        // Latest version at which each pool was touched, and the pools created by the batch
+        let mut touched_pools: AHashMap<String, i64> = AHashMap::new();
+        let mut created_pools: Vec<HyperionPool> = vec![];
+        let mut has_hyperion_swaps = false;
+        for event in &transaction_context_data.data.events {
+            let (pool_address, tx_version) = match event {
+                ContractEvent::HyperionPoolCreated(pool) => {
+                    created_pools.push(pool.clone());
+                    (&pool.pool_address, pool.last_update_version)
+                }
+                ContractEvent::HyperionPoolStateUpdate(pool) => {
+                    (&pool.pool_address, pool.last_update_version)
+                }
+                ContractEvent::HyperionSwap(swap) => {
+                    has_hyperion_swaps = true;
+                    (&swap.pool_address, swap.tx_version)
+                }
+                _ => continue,
+            };
+            let latest_version = touched_pools.entry(pool_address.clone()).or_default();
+            *latest_version = (*latest_version).max(tx_version);
+        }
+        if touched_pools.is_empty() {
+            return Ok(Some(transaction_context_data));
+        }
+        let chain_timestamp = transaction_context_data
+            .metadata
+            .end_transaction_timestamp
+            .as_ref()
+            .map(|t| t.seconds)
+            .unwrap_or_default();
+
+        let (alerts, swaps) = {
+            let conn = &mut get_db_connection(&self.pool).await?;
+            async {
+                let mut alerts = vec![];
+                let swaps = if has_hyperion_swaps {
+                    let (swaps, tvl_by_pool) = load_swaps_and_tvl(
+                        conn,
+                        transaction_context_data.metadata.start_version as i64,
+                        transaction_context_data.metadata.end_version as i64,
+                    )
+                    .await?;
+                    alerts.extend(detect_large_swaps(&swaps, &tvl_by_pool, &self.alert_config));
+                    swaps
+                } else {
+                    vec![]
+                };
+                let batch = RuleBatch {
+                    touched_pools: &touched_pools,
+                    created_pools: &created_pools,
+                    chain_timestamp,
+                };
+                alerts.extend(evaluate_rules(conn, &self.alert_config.rules, &batch).await?);
+                QueryResult::Ok((alerts, swaps))
+            }
+            .await
+            .map_err(|e| {
+                tracing::warn!("Error running query: {:?}", e);
+                ProcessorError::ProcessError {
+                    message: e.to_string(),
+                }
+            })?
+        };
+
+        // Sinks subscribed to indexed events get them whether or not anything was flagged. The
+        // enqueue is idempotent, a failure fails the batch so the events are enqueued on retry.
+        let events = created_pools
+            .into_iter()
+            .map(SinkEvent::PoolCreated)
+            .chain(swaps.into_iter().map(SinkEvent::Swap))
+            .collect::<Vec<_>>();
+        if !events.is_empty() {
+            for sink in &self.sinks {
+                sink.send_events(&events).await.map_err(|e| {
+                    tracing::warn!("Alert sink {} failed: {:?}", sink.name(), e);
+                    ProcessorError::ProcessError {
+                        message: format!("Alert sink {} failed: {:#}", sink.name(), e),
+                    }
+                })?;
+            }
+        }
+        if alerts.is_empty() {
+            return Ok(Some(transaction_context_data));
+        }
+
+        let new_alerts = process_alerts(
+            self.pool.clone(),
+            self.retry_policy,
+            AHashMap::new(),
+            alerts,
+            self.sinks.clone(),
+        )
+        .await?;
+        record_rows_written("alerts", new_alerts.len());
+        if !new_alerts.is_empty() {
+            for sink in &self.sinks {
+                if let Err(e) = sink.send(&new_alerts).await {
+                    tracing::warn!("Alert sink {} failed: {:?}", sink.name(), e);
+                }
+            }
+        }
+
+        Ok(Some(transaction_context_data))
+    }
+}
@@ -0,0 +1,490 @@
+//! Evaluates the user defined rules of `alert_config` against the pools touched by a batch.
+//! Rules only look at pools the batch touched, so evaluation stays incremental. Every rule
+//! has a per pool cooldown backed by the `alerts` table, and alert ids are derived from the
+//! rule, pool and version so re-processing a range never raises an alert twice.
+
+use ahash::AHashMap;
+use aptos_indexer_processor_sdk::utils::convert::standardize_address;
+use diesel::{dsl::max, ExpressionMethods, OptionalExtension, QueryDsl, QueryResult};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    config::indexer_processor_config::{AlertCondition, AlertRuleConfig, PriceChangeWindow},
+    db_models::{
+        alert::{Alert, ALERT_TYPE_RULE},
+        hyperion_pool::HyperionPool,
+        hyperion_pool_stat::{hour_bucket, SECONDS_PER_HOUR},
+    },
+    schema::{
+        alerts, hyperion_pool_hourly_stats, hyperion_pool_liquidity_snapshots, hyperion_pool_stats,
+        hyperion_pools,
+    },
+};
+
+pub struct RuleBatch<'a> {
+    /// Latest version at which each pool was touched by the batch
+    pub touched_pools: &'a AHashMap<String, i64>,
+    pub created_pools: &'a [HyperionPool],
+    /// Chain time of the batch, used as "now"
+    pub chain_timestamp: i64,
+}
+
+struct Trigger {
+    pool_address: String,
+    tx_version: i64,
+    value: f64,
+    message: String,
+}
+
+fn parse(value: &str) -> f64 {
+    value.parse::<f64>().unwrap_or(0.0)
+}
+
+async fn evaluate_volume_spike(
+    conn: &mut AsyncPgConnection,
+    pool_address: &str,
+    chain_timestamp: i64,
+    multiplier: f64,
+    trailing_hours: i64,
+) -> QueryResult<Option<(f64, String)>> {
+    if trailing_hours <= 0 {
+        return Ok(None);
+    }
+    let current_bucket = hour_bucket(chain_timestamp);
+    let buckets = hyperion_pool_hourly_stats::table
+        .filter(hyperion_pool_hourly_stats::pool_address.eq(pool_address))
+        .filter(
+            hyperion_pool_hourly_stats::bucket_start
+                .ge(current_bucket - trailing_hours * SECONDS_PER_HOUR),
+        )
+        .filter(hyperion_pool_hourly_stats::bucket_start.le(current_bucket))
+        .select((
+            hyperion_pool_hourly_stats::bucket_start,
+            hyperion_pool_hourly_stats::volume_usd,
+        ))
+        .load::<(i64, String)>(conn)
+        .await?;
+    Ok(volume_spike(
+        &buckets
+            .into_iter()
+            .map(|(bucket_start, volume_usd)| (bucket_start, parse(&volume_usd)))
+            .collect::<Vec<_>>(),
+        current_bucket,
+        multiplier,
+        trailing_hours,
+    ))
+}
+
+/// Fires when the USD volume of `current_bucket` is above `multiplier` times the hourly
+/// average of the `trailing_hours` buckets before it. `buckets` are `(bucket_start,
+/// volume_usd)` pairs over the current and trailing hours.
+fn volume_spike(
+    buckets: &[(i64, f64)],
+    current_bucket: i64,
+    multiplier: f64,
+    trailing_hours: i64,
+) -> Option<(f64, String)> {
+    if trailing_hours <= 0 {
+        return None;
+    }
+    let (mut current_volume, mut trailing_volume) = (0.0, 0.0);
+    for (bucket_start, volume_usd) in buckets {
+        if *bucket_start == current_bucket {
+            current_volume += volume_usd;
+        } else {
+            trailing_volume += volume_usd;
+        }
+    }
+    // Hours without swaps have no bucket and count as zero volume
+    let average_volume = trailing_volume / trailing_hours as f64;
+    if average_volume <= 0.0 || current_volume <= multiplier * average_volume {
+        return None;
+    }
+    let ratio = current_volume / average_volume;
+    Some((
+        ratio,
+        format!(
+            "Volume of ${:.0} this hour is {:.1}x the {}h hourly average",
+            current_volume, ratio, trailing_hours
+        ),
+    ))
+}
+
+async fn evaluate_liquidity_drop(
+    conn: &mut AsyncPgConnection,
+    pool_address: &str,
+    chain_timestamp: i64,
+    percent: f64,
+    window_minutes: i64,
+) -> QueryResult<Option<(f64, String)>> {
+    let Some(current_liquidity) = hyperion_pools::table
+        .find(pool_address)
+        .select(hyperion_pools::liquidity)
+        .first::<String>(conn)
+        .await
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let snapshots = hyperion_pool_liquidity_snapshots::table
+        .filter(hyperion_pool_liquidity_snapshots::pool_address.eq(pool_address))
+        .filter(
+            hyperion_pool_liquidity_snapshots::timestamp.ge(chain_timestamp - window_minutes * 60),
+        )
+        .select(hyperion_pool_liquidity_snapshots::liquidity)
+        .load::<String>(conn)
+        .await?
+        .iter()
+        .map(|liquidity| parse(liquidity))
+        .collect::<Vec<_>>();
+    Ok(liquidity_drop(
+        parse(&current_liquidity),
+        &snapshots,
+        percent,
+        window_minutes,
+    ))
+}
+
+/// Fires when `current_liquidity` is at least `percent` below the peak of the snapshots
+/// taken over the last `window_minutes`.
+fn liquidity_drop(
+    current_liquidity: f64,
+    snapshots: &[f64],
+    percent: f64,
+    window_minutes: i64,
+) -> Option<(f64, String)> {
+    let peak_liquidity = snapshots.iter().copied().fold(current_liquidity, f64::max);
+    if peak_liquidity <= 0.0 {
+        return None;
+    }
+    let drop_percent = (peak_liquidity - current_liquidity) / peak_liquidity * 100.0;
+    if drop_percent < percent {
+        return None;
+    }
+    Some((
+        drop_percent,
+        format!(
+            "Liquidity dropped {:.2}% from its peak over the last {} minutes",
+            drop_percent, window_minutes
+        ),
+    ))
+}
+
+fn apr_above(pool_apr: f64, apr: f64) -> Option<(f64, String)> {
+    (pool_apr > apr).then(|| {
+        (
+            pool_apr,
+            format!("APR of {:.2}% is above {:.2}%", pool_apr, apr),
+        )
+    })
+}
+
+fn price_move(price_change: f64, percent: f64, window_label: &str) -> Option<(f64, String)> {
+    (price_change.abs() >= percent).then(|| {
+        (
+            price_change,
+            format!("Price moved {:+.2}% over {}", price_change, window_label),
+        )
+    })
+}
+
+async fn evaluate_pool_condition(
+    conn: &mut AsyncPgConnection,
+    condition: &AlertCondition,
+    pool_address: &str,
+    chain_timestamp: i64,
+) -> QueryResult<Option<(f64, String)>> {
+    match condition {
+        AlertCondition::VolumeSpike {
+            multiplier,
+            trailing_hours,
+        } => {
+            evaluate_volume_spike(
+                conn,
+                pool_address,
+                chain_timestamp,
+                *multiplier,
+                *trailing_hours,
+            )
+            .await
+        }
+        AlertCondition::LiquidityDrop {
+            percent,
+            window_minutes,
+        } => {
+            evaluate_liquidity_drop(
+                conn,
+                pool_address,
+                chain_timestamp,
+                *percent,
+                *window_minutes,
+            )
+            .await
+        }
+        AlertCondition::AprAbove { apr } => {
+            let pool_apr = hyperion_pool_stats::table
+                .find(pool_address)
+                .select(hyperion_pool_stats::apr)
+                .first::<String>(conn)
+                .await
+                .optional()?
+                .map(|pool_apr| parse(&pool_apr))
+                .unwrap_or(0.0);
+            Ok(apr_above(pool_apr, *apr))
+        }
+        AlertCondition::PriceMove { percent, window } => {
+            let stats = hyperion_pool_stats::table.find(pool_address);
+            let (price_change, label) = match window {
+                PriceChangeWindow::OneHour => (
+                    stats
+                        .select(hyperion_pool_stats::price_change_1h)
+                        .first::<String>(conn)
+                        .await
+                        .optional()?,
+                    "1h",
+                ),
+                PriceChangeWindow::OneDay => (
+                    stats
+                        .select(hyperion_pool_stats::price_change_24h)
+                        .first::<String>(conn)
+                        .await
+                        .optional()?,
+                    "24h",
+                ),
+                PriceChangeWindow::SevenDays => (
+                    stats
+                        .select(hyperion_pool_stats::price_change_7d)
+                        .first::<String>(conn)
+                        .await
+                        .optional()?,
+                    "7d",
+                ),
+            };
+            let price_change = price_change.map(|change| parse(&change)).unwrap_or(0.0);
+            Ok(price_move(price_change, *percent, label))
+        }
+        // Only raised from the pools created in the batch
+        AlertCondition::NewPool { .. } => Ok(None),
+    }
+}
+
+/// Whether `pool_address` is one of the pools the rule is restricted to, if any.
+fn rule_applies_to(rule: &AlertRuleConfig, pool_address: &str) -> bool {
+    let pool_address = standardize_address(pool_address);
+    rule.pools.is_empty()
+        || rule
+            .pools
+            .iter()
+            .any(|pool| standardize_address(pool) == pool_address)
+}
+
+fn new_pool_trigger(pool: &HyperionPool, token: &str) -> Option<Trigger> {
+    let token = standardize_address(token);
+    let has_token = standardize_address(&pool.token0_address) == token
+        || standardize_address(&pool.token1_address) == token;
+    has_token.then(|| Trigger {
+        pool_address: pool.pool_address.clone(),
+        tx_version: pool.last_update_version,
+        value: 0.0,
+        message: format!(
+            "New pool {}/{} created at {}",
+            pool.token0_symbol, pool.token1_symbol, pool.pool_address
+        ),
+    })
+}
+
+async fn evaluate_rule(
+    conn: &mut AsyncPgConnection,
+    rule: &AlertRuleConfig,
+    batch: &RuleBatch<'_>,
+) -> QueryResult<Vec<Trigger>> {
+    let mut triggers = vec![];
+    if let AlertCondition::NewPool { token } = &rule.condition {
+        triggers.extend(
+            batch
+                .created_pools
+                .iter()
+                .filter(|pool| rule_applies_to(rule, &pool.pool_address))
+                .filter_map(|pool| new_pool_trigger(pool, token)),
+        );
+        return Ok(triggers);
+    }
+
+    for (pool_address, tx_version) in batch.touched_pools {
+        if !rule_applies_to(rule, pool_address) {
+            continue;
+        }
+        if let Some((value, message)) =
+            evaluate_pool_condition(conn, &rule.condition, pool_address, batch.chain_timestamp)
+                .await?
+        {
+            triggers.push(Trigger {
+                pool_address: pool_address.clone(),
+                tx_version: *tx_version,
+                value,
+                message,
+            });
+        }
+    }
+    Ok(triggers)
+}
+
+/// Alerts raised by the rules for this batch, after cooldowns.
+pub async fn evaluate_rules(
+    conn: &mut AsyncPgConnection,
+    rules: &[AlertRuleConfig],
+    batch: &RuleBatch<'_>,
+) -> QueryResult<Vec<Alert>> {
+    let mut rule_alerts = vec![];
+    for rule in rules {
+        for trigger in evaluate_rule(conn, rule, batch).await? {
+            let last_raised = alerts::table
+                .filter(alerts::rule_name.eq(&rule.name))
+                .filter(alerts::pool_address.eq(&trigger.pool_address))
+                .select(max(alerts::timestamp))
+                .first::<Option<i64>>(conn)
+                .await?;
+            if is_cooling_down(rule, last_raised, batch.chain_timestamp) {
+                continue;
+            }
+            rule_alerts.push(rule_alert(rule, trigger, batch.chain_timestamp));
+        }
+    }
+    Ok(rule_alerts)
+}
+
+/// Whether the rule raised an alert for the pool less than `cooldown_secs` before now.
+fn is_cooling_down(rule: &AlertRuleConfig, last_raised: Option<i64>, chain_timestamp: i64) -> bool {
+    last_raised.is_some_and(|last| chain_timestamp - last < rule.cooldown_secs)
+}
+
+/// The alert of a trigger. Its id is the dedup key: a rule fires at most once per pool and
+/// version, however often the range is processed.
+fn rule_alert(rule: &AlertRuleConfig, trigger: Trigger, chain_timestamp: i64) -> Alert {
+    Alert {
+        alert_id: format!(
+            "rule-{}-{}-{}",
+            rule.name, trigger.pool_address, trigger.tx_version
+        ),
+        alert_type: ALERT_TYPE_RULE.to_string(),
+        severity: rule.severity.clone(),
+        pool_address: trigger.pool_address,
+        address: String::new(),
+        tx_version: trigger.tx_version,
+        value: trigger.value.to_string(),
+        message: format!("[{}] {}", rule.name, trigger.message),
+        timestamp: chain_timestamp,
+        rule_name: rule.name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rule(condition: AlertCondition, pools: &[&str]) -> AlertRuleConfig {
+        AlertRuleConfig {
+            name: "test_rule".to_string(),
+            severity: AlertRuleConfig::default_severity(),
+            cooldown_secs: 600,
+            pools: pools.iter().map(|pool| pool.to_string()).collect(),
+            condition,
+        }
+    }
+
+    fn pool(pool_address: &str, token0: &str, token1: &str) -> HyperionPool {
+        HyperionPool {
+            pool_address: pool_address.to_string(),
+            token0_address: token0.to_string(),
+            token1_address: token1.to_string(),
+            token0_symbol: "A".to_string(),
+            token1_symbol: "B".to_string(),
+            fee_tier: 3000,
+            tick_spacing: 60,
+            liquidity: "0".to_string(),
+            sqrt_price_x96: "0".to_string(),
+            tick: 0,
+            creation_timestamp: 0,
+            last_update_timestamp: 0,
+            last_update_version: 42,
+        }
+    }
+
+    #[test]
+    fn test_volume_spike_compares_the_current_hour_with_the_trailing_average() {
+        let current = 10 * SECONDS_PER_HOUR;
+        // 4 trailing hours averaging 100, one of them without swaps
+        let buckets = [
+            (current - 4 * SECONDS_PER_HOUR, 200.0),
+            (current - 2 * SECONDS_PER_HOUR, 100.0),
+            (current - SECONDS_PER_HOUR, 100.0),
+            (current, 350.0),
+        ];
+        let (ratio, _) = volume_spike(&buckets, current, 3.0, 4).unwrap();
+        assert_eq!(ratio, 3.5);
+        assert!(volume_spike(&buckets, current, 4.0, 4).is_none());
+        // At exactly the multiplier the rule stays quiet
+        assert!(volume_spike(&buckets, current, 3.5, 4).is_none());
+        // No trailing volume, no average to compare with
+        assert!(volume_spike(&[(current, 350.0)], current, 3.0, 4).is_none());
+        assert!(volume_spike(&buckets, current, 3.0, 0).is_none());
+    }
+
+    #[test]
+    fn test_liquidity_drop_measures_from_the_peak_of_the_window() {
+        let (drop_percent, _) = liquidity_drop(600.0, &[1_000.0, 800.0], 40.0, 30).unwrap();
+        assert_eq!(drop_percent, 40.0);
+        assert!(liquidity_drop(601.0, &[1_000.0, 800.0], 40.0, 30).is_none());
+        // A pool above every snapshot has not dropped
+        assert!(liquidity_drop(1_200.0, &[1_000.0], 0.0, 30).is_some_and(|(drop, _)| drop == 0.0));
+        assert!(liquidity_drop(0.0, &[], 10.0, 30).is_none());
+    }
+
+    #[test]
+    fn test_apr_and_price_move_thresholds() {
+        assert!(apr_above(50.1, 50.0).is_some());
+        assert!(apr_above(50.0, 50.0).is_none());
+
+        let (change, message) = price_move(-12.5, 10.0, "24h").unwrap();
+        assert_eq!(change, -12.5);
+        assert_eq!(message, "Price moved -12.50% over 24h");
+        assert!(price_move(10.0, 10.0, "1h").is_some());
+        assert!(price_move(9.99, 10.0, "1h").is_none());
+    }
+
+    #[test]
+    fn test_new_pool_matches_either_side_and_the_pool_filter() {
+        let token = "0x1";
+        assert!(new_pool_trigger(&pool("0xa", "0x1", "0x2"), token).is_some());
+        assert!(new_pool_trigger(&pool("0xa", "0x2", "0x01"), token).is_some());
+        assert!(new_pool_trigger(&pool("0xa", "0x2", "0x3"), token).is_none());
+
+        let condition = AlertCondition::NewPool {
+            token: token.to_string(),
+        };
+        assert!(rule_applies_to(&rule(condition.clone(), &[]), "0xa"));
+        assert!(rule_applies_to(&rule(condition.clone(), &["0x0a"]), "0xa"));
+        assert!(!rule_applies_to(&rule(condition, &["0xb"]), "0xa"));
+    }
+
+    #[test]
+    fn test_cooldown_and_dedup_key() {
+        let rule = rule(AlertCondition::AprAbove { apr: 10.0 }, &[]);
+        assert!(!is_cooling_down(&rule, None, 1_000));
+        assert!(is_cooling_down(&rule, Some(1_000), 1_599));
+        assert!(!is_cooling_down(&rule, Some(1_000), 1_600));
+
+        let trigger = || Trigger {
+            pool_address: "0xa".to_string(),
+            tx_version: 42,
+            value: 12.0,
+            message: "APR of 12.00% is above 10.00%".to_string(),
+        };
+        let alert = rule_alert(&rule, trigger(), 1_000);
+        assert_eq!(alert.alert_id, "rule-test_rule-0xa-42");
+        assert_eq!(alert.message, "[test_rule] APR of 12.00% is above 10.00%");
+        assert_eq!(alert.severity, "warning");
+        // The same trigger seen again, e.g. when the range is replayed, keeps its id
+        assert_eq!(rule_alert(&rule, trigger(), 2_000).alert_id, alert.alert_id);
+    }
+}
@@ -0,0 +1,363 @@
+use anyhow::{bail, ensure, Context, Result};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+
+use super::{
+    processor::ContractProcessor,
+    storers::{
+        hyperion_pool_stats_storer::process_hyperion_pool_stats,
+        mev_sandwich_storer::process_mev_sandwiches, token_stats_storer::process_token_stats,
+        trader_position_storer::process_trader_positions,
+        wash_trading_storer::process_wash_trading,
+    },
+};
+use crate::{
+    db_models::{
+        hyperion_pool_stat::hour_bucket,
+        hyperion_swap::HyperionSwap,
+        processor_status::{ProcessorStatus, ProcessorStatusQuery},
+    },
+    schema::{hyperion_swaps, trades},
+    utils::{
+        database_connection::get_db_connection,
+        latest_processed_version_tracker::upsert_processor_status, metrics::spawn_db_pool_metrics,
+        query_retry::QueryRetryPolicy, shutdown::is_shutdown_requested,
+        starting_version::get_latest_processed_version_from_db,
+    },
+};
+
+// Versions replayed at a time once the workers are done
+const REPLAY_CHUNK_VERSIONS: u64 = 100_000;
+// Trades scored at a time by the wash trading replay
+const REPLAY_CHUNK_TRADES: usize = 10_000;
+
+/// Splits `[start, end]` into at most `workers` contiguous ranges of about the same size.
+pub fn split_range(start: u64, end: u64, workers: u64) -> Vec<(u64, u64)> {
+    let total = end - start + 1;
+    let workers = workers.clamp(1, total);
+    let size = total.div_ceil(workers);
+    (0..workers)
+        .map(|i| start + i * size)
+        .take_while(|range_start| *range_start <= end)
+        .map(|range_start| (range_start, (range_start + size - 1).min(end)))
+        .collect()
+}
+
+/// Name of the `processor_status` row of the backfill worker indexing the `index`th range.
+pub fn backfill_tracker_name(processor_name: &str, index: usize) -> String {
+    format!("{}_backfill_{}", processor_name, index)
+}
+
+/// BackfillProcessor indexes `[starting_version, ending_version]` with several pipelines
+/// over disjoint ranges, then replays the order dependent state over the whole range and
+/// moves the live processor's cursor past it.
+pub struct BackfillProcessor {
+    processor: ContractProcessor,
+}
+
+impl BackfillProcessor {
+    pub fn new(processor: ContractProcessor) -> Self {
+        Self { processor }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        let config = &self.processor.config;
+        let workers = config
+            .backfill_config
+            .as_ref()
+            .map(|backfill_config| backfill_config.workers)
+            .unwrap_or_default();
+        let start = config
+            .transaction_stream_config
+            .starting_version()
+            .unwrap_or(0);
+        let end = self
+            .processor
+            .ending_version()
+            .context("A backfill needs an ending_version")?;
+        ensure!(
+            start <= end,
+            "Backfill starting version {} is after its ending version {}",
+            start,
+            end
+        );
+        let processor_name = config.processor_config.name();
+
+        self.processor.check_chain_id().await?;
+        // The storers add to the trader and user stats, events the live processor already
+        // indexed would be counted twice
+        let live = {
+            let conn = &mut get_db_connection(&self.processor.db_pool).await?;
+            ProcessorStatusQuery::get_by_processor(processor_name, conn).await?
+        };
+        if let Some(live) = &live {
+            if live.last_success_version >= start as i64 {
+                bail!(
+                    "{} already indexed up to version {}, start the backfill at {} or reset the tables first",
+                    processor_name,
+                    live.last_success_version,
+                    live.last_success_version + 1
+                );
+            }
+        }
+        spawn_db_pool_metrics(
+            self.processor.db_pool.clone(),
+            config.db_config.db_pool_size,
+        );
+
+        let ranges = split_range(start, end, workers);
+        tracing::info!(
+            "Backfilling versions [{}, {}] with {} workers",
+            start,
+            end,
+            ranges.len()
+        );
+        let pipelines = ranges
+            .iter()
+            .enumerate()
+            .map(|(index, (range_start, range_end))| {
+                self.run_worker(
+                    backfill_tracker_name(processor_name, index),
+                    *range_start,
+                    *range_end,
+                )
+            })
+            .collect::<Vec<_>>();
+        futures_util::future::try_join_all(pipelines).await?;
+        // Workers resume from their own rows on restart, the replay and the merge wait for
+        // the whole range
+        if is_shutdown_requested() {
+            tracing::info!("Backfill stopped by the shutdown request, rerun it to resume");
+            return Ok(());
+        }
+
+        let last_tracker_name = backfill_tracker_name(processor_name, ranges.len() - 1);
+        self.replay_ordered_state(start, end).await?;
+        self.replay_trade_wash_trading(
+            live.and_then(|live| live.last_transaction_timestamp),
+            &last_tracker_name,
+        )
+        .await?;
+        self.merge_into_live_cursor(processor_name, &last_tracker_name, start, end)
+            .await
+    }
+
+    /// Runs one worker over its range, resuming from its own `processor_status` row.
+    async fn run_worker(
+        &self,
+        tracker_name: String,
+        range_start: u64,
+        range_end: u64,
+    ) -> Result<()> {
+        let starting_version =
+            get_latest_processed_version_from_db(&tracker_name, self.processor.db_pool.clone())
+                .await?
+                .unwrap_or(range_start)
+                .max(range_start);
+        if starting_version > range_end {
+            tracing::info!("{} already processed up to {}", tracker_name, range_end);
+            return Ok(());
+        }
+        self.processor
+            .run_pipeline(tracker_name, starting_version, Some(range_end), true)
+            .await
+    }
+
+    /// Positions, sandwiches and wash trading round trips depend on the order of swaps, and
+    /// the buckets at the edges of the ranges may have been rebuilt before the neighbouring
+    /// worker stored its swaps. They are replayed in version order over the whole range.
+    async fn replay_ordered_state(&self, start: u64, end: u64) -> Result<()> {
+        let pool = self.processor.db_pool.clone();
+        let config = &self.processor.config;
+        let retry_policy = QueryRetryPolicy::from_config(&config.db_config);
+        let mut chunk_start = start;
+        while chunk_start <= end {
+            let chunk_end = (chunk_start + REPLAY_CHUNK_VERSIONS - 1).min(end);
+            // The stored swaps are the replay's batch, valued and filled in by the workers
+            let swaps = {
+                let conn = &mut get_db_connection(&pool).await?;
+                hyperion_swaps::table
+                    .filter(
+                        hyperion_swaps::tx_version.between(chunk_start as i64, chunk_end as i64),
+                    )
+                    .order((hyperion_swaps::tx_version, hyperion_swaps::event_idx))
+                    .load::<HyperionSwap>(conn)
+                    .await?
+            };
+            if !swaps.is_empty() {
+                let chain_timestamp = swaps
+                    .iter()
+                    .map(|swap| swap.timestamp)
+                    .max()
+                    .unwrap_or_default();
+                let mut touched_pools = swaps
+                    .iter()
+                    .map(|swap| swap.pool_address.clone())
+                    .collect::<Vec<_>>();
+                touched_pools.sort();
+                touched_pools.dedup();
+                let mut touched_buckets = swaps
+                    .iter()
+                    .map(|swap| (swap.pool_address.clone(), hour_bucket(swap.timestamp)))
+                    .collect::<Vec<_>>();
+                let swap_ids = swaps
+                    .iter()
+                    .map(|swap| swap.swap_id.clone())
+                    .collect::<Vec<_>>();
+
+                process_trader_positions(pool.clone(), retry_policy, swaps.clone()).await?;
+                process_mev_sandwiches(
+                    pool.clone(),
+                    retry_policy,
+                    swaps,
+                    chunk_start as i64,
+                    config.mev_config.sandwich_max_version_gap,
+                )
+                .await?;
+                // Newly flagged swaps can sit in buckets before the chunk
+                let wash_buckets = process_wash_trading(
+                    pool.clone(),
+                    retry_policy,
+                    config.wash_trading_config.clone(),
+                    swap_ids,
+                    vec![],
+                    chain_timestamp,
+                )
+                .await?;
+                if config.wash_trading_config.exclude_flagged_volume {
+                    touched_pools.extend(wash_buckets.iter().map(|(pool, _)| pool.clone()));
+                    touched_pools.sort();
+                    touched_pools.dedup();
+                    touched_buckets.extend(wash_buckets);
+                }
+                touched_buckets.sort();
+                touched_buckets.dedup();
+                process_hyperion_pool_stats(
+                    pool.clone(),
+                    retry_policy,
+                    touched_pools.clone(),
+                    touched_buckets,
+                    chain_timestamp,
+                    config.wash_trading_config.exclude_flagged_volume,
+                )
+                .await?;
+                process_token_stats(pool.clone(), retry_policy, touched_pools, chain_timestamp)
+                    .await?;
+            }
+            chunk_start = chunk_end + 1;
+        }
+        Ok(())
+    }
+
+    /// Trades carry no version, the ones the workers stored are the unmatched trades created
+    /// after the live processor's last transaction and up to the end of the backfill. They are
+    /// paired and scored in creation order.
+    async fn replay_trade_wash_trading(
+        &self,
+        live_timestamp: Option<chrono::NaiveDateTime>,
+        last_tracker_name: &str,
+    ) -> Result<()> {
+        let pool = self.processor.db_pool.clone();
+        let config = &self.processor.config;
+        let retry_policy = QueryRetryPolicy::from_config(&config.db_config);
+        let trades = {
+            let conn = &mut get_db_connection(&pool).await?;
+            let Some(end_timestamp) =
+                ProcessorStatusQuery::get_by_processor(last_tracker_name, conn)
+                    .await?
+                    .and_then(|status| status.last_transaction_timestamp)
+            else {
+                return Ok(());
+            };
+            let start_timestamp = live_timestamp
+                .map(|timestamp| timestamp.and_utc().timestamp())
+                .unwrap_or_default();
+            trades::table
+                .filter(trades::round_trip_id.is_null())
+                .filter(trades::creation_timestamp.gt(start_timestamp))
+                .filter(trades::creation_timestamp.le(end_timestamp.and_utc().timestamp()))
+                .order(trades::creation_timestamp)
+                .select((trades::trade_obj_addr, trades::creation_timestamp))
+                .load::<(String, i64)>(conn)
+                .await?
+        };
+        for chunk in trades.chunks(REPLAY_CHUNK_TRADES) {
+            let chain_timestamp = chunk
+                .last()
+                .map(|(_, timestamp)| *timestamp)
+                .unwrap_or_default();
+            process_wash_trading(
+                pool.clone(),
+                retry_policy,
+                config.wash_trading_config.clone(),
+                vec![],
+                chunk.iter().map(|(trade_id, _)| trade_id.clone()).collect(),
+                chain_timestamp,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Moves the live processor's cursor to the end of the backfill, unless it has not
+    /// reached the start yet, or has no cursor at all, which would skip the versions in
+    /// between.
+    async fn merge_into_live_cursor(
+        &self,
+        processor_name: &str,
+        last_tracker_name: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<()> {
+        let conn = &mut get_db_connection(&self.processor.db_pool).await?;
+        let live_next_version = ProcessorStatusQuery::get_by_processor(processor_name, conn)
+            .await?
+            .map(|live| (live.last_success_version + 1) as u64)
+            .unwrap_or(0);
+        if live_next_version < start {
+            tracing::warn!(
+                "{} resumes at version {}, before the backfill start {}, its cursor is left as is",
+                processor_name,
+                live_next_version,
+                start
+            );
+            return Ok(());
+        }
+        let last_transaction_timestamp =
+            ProcessorStatusQuery::get_by_processor(last_tracker_name, conn)
+                .await?
+                .and_then(|status| status.last_transaction_timestamp);
+        let status = ProcessorStatus {
+            processor: processor_name.to_string(),
+            last_success_version: end as i64,
+            last_transaction_timestamp,
+        };
+        // The live processor may already be past the end of the backfill
+        upsert_processor_status(conn, &status)
+            .await
+            .context("Failed to merge the backfill into the live processor status")?;
+        tracing::info!(
+            "Backfill of [{}, {}] merged into {}",
+            start,
+            end,
+            processor_name
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_range_covers_range() {
+        assert_eq!(
+            split_range(10, 109, 4),
+            vec![(10, 34), (35, 59), (60, 84), (85, 109)]
+        );
+        assert_eq!(split_range(0, 9, 4), vec![(0, 2), (3, 5), (6, 8), (9, 9)]);
+        assert_eq!(split_range(5, 6, 4), vec![(5, 5), (6, 6)]);
+        assert_eq!(split_range(5, 5, 0), vec![(5, 5)]);
+    }
+}
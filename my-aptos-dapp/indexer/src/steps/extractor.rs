@@ -12,6 +12,7 @@ use aptos_indexer_processor_sdk::{
 use async_trait::async_trait;
 use rayon::prelude::*;
 
+use crate::config::processor_config::ProcessorDomain;
 use crate::db_models::{
     message::{CreateMessageEventOnChain, Message, UpdateMessageEventOnChain},
     module_upgrade::ModuleUpgrade,
@@ -20,6 +21,7 @@ use crate::db_models::{
     hyperion_pool::{HyperionPool, PoolCreatedEventOnChain, PoolStateUpdateEventOnChain},
     hyperion_swap::{HyperionSwap, SwapEventOnChain},
 };
+use crate::utils::metrics::{EVENTS_EXTRACTED, STEP_BATCH_LATENCY};
 
 /// Extractor is a step that extracts events and their metadata from transactions.
 pub struct Extractor
@@ -27,11 +29,15 @@ where
     Self: Sized + Send + 'static,
 {
     contract_address: String,
+    domains: Vec<ProcessorDomain>,
 }
 
 impl Extractor {
-    pub fn new(contract_address: String) -> Self {
-        Self { contract_address }
+    pub fn new(contract_address: String, domains: Vec<ProcessorDomain>) -> Self {
+        Self {
+            contract_address,
+            domains,
+        }
     }
 }
 
@@ -53,6 +59,9 @@ impl Processable for Extractor {
         &mut self,
         item: TransactionContext<Vec<Transaction>>,
     ) -> Result<Option<TransactionContext<TransactionContextData>>, ProcessorError> {
+        let _timer = STEP_BATCH_LATENCY
+            .with_label_values(&["Extractor"])
+            .start_timer();
         let results: Vec<(Vec<ContractEvent>, Vec<ContractUpgradeChange>)> = item
             .data
             .par_iter()
@@ -91,15 +100,23 @@ impl Processable for Extractor {
                     _ => &vec![],
                 };
 
-                let txn_events =
-                    ContractEvent::from_events(self.contract_address.as_str(), raw_events);
-
-                let txn_changes = ContractUpgradeChange::from_changes(
+                let txn_events = ContractEvent::from_events(
                     self.contract_address.as_str(),
+                    &self.domains,
                     txn_version,
-                    txn_info.changes.as_slice(),
+                    raw_events,
                 );
 
+                let txn_changes = if self.domains.contains(&ProcessorDomain::UpgradeHistory) {
+                    ContractUpgradeChange::from_changes(
+                        self.contract_address.as_str(),
+                        txn_version,
+                        txn_info.changes.as_slice(),
+                    )
+                } else {
+                    vec![]
+                };
+
                 (txn_events, txn_changes)
             })
             .collect::<Vec<(Vec<ContractEvent>, Vec<ContractUpgradeChange>)>>();
@@ -114,6 +131,10 @@ impl Processable for Extractor {
                 },
             );
 
+        for event in &events {
+            EVENTS_EXTRACTED.with_label_values(&[event.name()]).inc();
+        }
+
         Ok(Some(TransactionContext {
             data: TransactionContextData { events, changes },
             metadata: item.metadata,
@@ -141,11 +162,43 @@ pub enum ContractEvent {
 }
 
 impl ContractEvent {
-    fn from_event(contract_address: &str, event_idx: usize, event: &EventPB) -> Option<Self> {
+    /// Name of the variant, used as a metric label
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContractEvent::CreateMessageEvent(_) => "CreateMessageEvent",
+            ContractEvent::UpdateMessageEvent(_) => "UpdateMessageEvent",
+            ContractEvent::CreateTradeEvent(..) => "CreateTradeEvent",
+            ContractEvent::UpdateTradeEvent(..) => "UpdateTradeEvent",
+            ContractEvent::CompleteTradeEvent(..) => "CompleteTradeEvent",
+            ContractEvent::CancelTradeEvent(..) => "CancelTradeEvent",
+            ContractEvent::HyperionPoolCreated(_) => "HyperionPoolCreated",
+            ContractEvent::HyperionPoolStateUpdate(_) => "HyperionPoolStateUpdate",
+            ContractEvent::HyperionSwap(_) => "HyperionSwap",
+        }
+    }
+
+    /// Domain of the events of `module`, None for modules that are not indexed.
+    fn domain(module: &str) -> Option<ProcessorDomain> {
+        match module {
+            "pool_v3" => Some(ProcessorDomain::Hyperion),
+            "custom_indexer_ex_message_board" => Some(ProcessorDomain::MessageBoard),
+            "trade_radar" => Some(ProcessorDomain::TradeRadar),
+            _ => None,
+        }
+    }
+
+    fn from_event(
+        contract_address: &str,
+        domains: &[ProcessorDomain],
+        txn_version: i64,
+        event_idx: usize,
+        event: &EventPB,
+    ) -> Option<Self> {
         // use standardize_address to pad the address in event type before processing
         let parts = event.type_str.split("::").collect::<Vec<_>>();
         let t = standardize_address(parts[0]) + "::" + parts[1] + "::" + parts[2];
-        let should_include = t.starts_with(contract_address);
+        let should_include = t.starts_with(contract_address)
+            && Self::domain(parts[1]).is_some_and(|domain| domains.contains(&domain));
 
         if should_include {
             // Trade events
@@ -245,7 +298,7 @@ impl ContractEvent {
                             Some(ContractEvent::HyperionPoolCreated(
                                 HyperionPool::from_pool_created_event(
                                     &pool_created_event,
-                                    event_idx as i64,
+                                    txn_version,
                                 )
                             ))
                         },
@@ -261,8 +314,8 @@ impl ContractEvent {
                             Some(ContractEvent::HyperionSwap(
                                 HyperionSwap::from_swap_event(
                                     &swap_event,
+                                    txn_version,
                                     event_idx as i64,
-                                    event.sequence_number as i64,
                                 )
                             ))
                         },
@@ -288,9 +341,9 @@ impl ContractEvent {
                                     tick: state_update_event.tick.clone(),
                                     timestamp: state_update_event.timestamp.clone(),
                                 },
-                                event_idx as i64,
+                                txn_version,
                             );
-                            pool.update_from_state_event(&state_update_event, event_idx as i64);
+                            pool.update_from_state_event(&state_update_event, txn_version);
                             Some(ContractEvent::HyperionPoolStateUpdate(pool))
                         },
                         Err(e) => {
@@ -309,11 +362,18 @@ impl ContractEvent {
         }
     }
 
-    pub fn from_events(contract_address: &str, events: &[EventPB]) -> Vec<Self> {
+    pub fn from_events(
+        contract_address: &str,
+        domains: &[ProcessorDomain],
+        txn_version: i64,
+        events: &[EventPB],
+    ) -> Vec<Self> {
         events
             .iter()
             .enumerate()
-            .filter_map(|(idx, event)| Self::from_event(contract_address, idx, event))
+            .filter_map(|(idx, event)| {
+                Self::from_event(contract_address, domains, txn_version, idx, event)
+            })
             .collect()
     }
 }
@@ -431,3 +491,33 @@ impl ContractUpgradeChange {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_events_only_extracts_processor_domains() {
+        let contract_address = standardize_address("0xcafe");
+        let event = EventPB {
+            type_str: "0xcafe::custom_indexer_ex_message_board::CreateMessageEvent".to_string(),
+            data: serde_json::json!({
+                "message_obj_addr": "0x1",
+                "message": {
+                    "creator": "0x2",
+                    "content": "hello",
+                    "creation_timestamp": "100",
+                    "last_update_timestamp": "100",
+                },
+            })
+            .to_string(),
+            ..Default::default()
+        };
+        let extract = |domains: &[ProcessorDomain]| {
+            ContractEvent::from_events(&contract_address, domains, 1, &[event.clone()]).len()
+        };
+
+        assert_eq!(extract(&[ProcessorDomain::MessageBoard]), 1);
+        assert_eq!(extract(&[ProcessorDomain::TradeRadar, ProcessorDomain::Hyperion]), 0);
+    }
+}
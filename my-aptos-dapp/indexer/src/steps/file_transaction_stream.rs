@@ -0,0 +1,142 @@
+use anyhow::Result;
+use aptos_indexer_processor_sdk::{
+    aptos_protos::transaction::v1::Transaction,
+    traits::{NamedStep, PollableAsyncRunType, PollableAsyncStep, Processable},
+    types::transaction_context::{TransactionContext, TransactionMetadata},
+    utils::errors::ProcessorError,
+};
+use async_trait::async_trait;
+use prost::Message;
+use std::{collections::VecDeque, path::PathBuf, time::Duration};
+
+use crate::{
+    config::indexer_processor_config::FileTransactionSourceConfig,
+    utils::transaction_files::{list_transaction_files, read_transactions_file},
+};
+
+/// FileTransactionStream is a first step that replays transactions from local files in
+/// place of the gRPC `TransactionStreamStep`.
+pub struct FileTransactionStream
+where
+    Self: Sized + Send + 'static,
+{
+    files: VecDeque<PathBuf>,
+    // Next version to hand out. Batches cover the versions missing from the files too, so
+    // the tracker sees contiguous ranges.
+    next_version: u64,
+    ending_version: Option<u64>,
+    batch_size: usize,
+    is_done: bool,
+}
+
+impl FileTransactionStream {
+    pub fn new(
+        config: &FileTransactionSourceConfig,
+        starting_version: u64,
+        ending_version: Option<u64>,
+    ) -> Result<Self> {
+        let files = list_transaction_files(&config.source_path)?;
+        tracing::info!(
+            "Replaying transactions from {} files in {}",
+            files.len(),
+            config.source_path.display()
+        );
+        Ok(Self {
+            files: files.into(),
+            next_version: starting_version,
+            ending_version,
+            batch_size: config.batch_size.max(1),
+            is_done: false,
+        })
+    }
+
+    fn is_in_range(&self, version: u64) -> bool {
+        version >= self.next_version
+            && self
+                .ending_version
+                .map_or(true, |ending_version| version <= ending_version)
+    }
+
+    fn to_batch(&mut self, transactions: Vec<Transaction>) -> TransactionContext<Vec<Transaction>> {
+        let start_version = self.next_version;
+        let end_version = transactions[transactions.len() - 1].version;
+        self.next_version = end_version + 1;
+        TransactionContext {
+            metadata: TransactionMetadata {
+                start_version,
+                end_version,
+                start_transaction_timestamp: transactions[0].timestamp.clone(),
+                end_transaction_timestamp: transactions[transactions.len() - 1].timestamp.clone(),
+                total_size_in_bytes: transactions
+                    .iter()
+                    .map(|txn| txn.encoded_len() as u64)
+                    .sum(),
+            },
+            data: transactions,
+        }
+    }
+}
+
+#[async_trait]
+impl Processable for FileTransactionStream {
+    type Input = ();
+    type Output = Vec<Transaction>;
+    type RunType = PollableAsyncRunType;
+
+    async fn process(
+        &mut self,
+        _item: TransactionContext<()>,
+    ) -> Result<Option<TransactionContext<Vec<Transaction>>>, ProcessorError> {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl PollableAsyncStep for FileTransactionStream
+where
+    Self: Sized + Send + Sync + 'static,
+{
+    fn poll_interval(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    async fn poll(
+        &mut self,
+    ) -> Result<Option<Vec<TransactionContext<Vec<Transaction>>>>, ProcessorError> {
+        while let Some(path) = self.files.pop_front() {
+            let transactions =
+                read_transactions_file(&path).map_err(|e| ProcessorError::ProcessError {
+                    message: format!("{:?}", e),
+                })?;
+            if let (Some(ending_version), Some(last)) = (self.ending_version, transactions.last()) {
+                if last.version >= ending_version {
+                    self.files.clear();
+                }
+            }
+            let transactions = transactions
+                .into_iter()
+                .filter(|txn| self.is_in_range(txn.version))
+                .collect::<Vec<_>>();
+            if transactions.is_empty() {
+                continue;
+            }
+            let batches = transactions
+                .chunks(self.batch_size)
+                .map(|chunk| self.to_batch(chunk.to_vec()))
+                .collect();
+            return Ok(Some(batches));
+        }
+        self.is_done = true;
+        Ok(None)
+    }
+
+    async fn should_continue_polling(&mut self) -> bool {
+        !self.is_done
+    }
+}
+
+impl NamedStep for FileTransactionStream {
+    fn name(&self) -> String {
+        "FileTransactionStream".to_string()
+    }
+}
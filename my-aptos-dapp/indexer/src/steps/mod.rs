@@ -1,4 +1,11 @@
+pub mod alert_detector;
+pub mod alert_rules;
+pub mod backfill_processor;
 pub mod extractor;
+pub mod file_transaction_stream;
 pub mod storer;
 pub mod processor;
+pub mod stoppable_stream;
 pub mod storers;
+pub mod transaction_recorder;
+pub mod wash_trading_detector;
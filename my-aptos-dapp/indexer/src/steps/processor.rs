@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use aptos_indexer_processor_sdk::{
     aptos_indexer_transaction_stream::{TransactionStream, TransactionStreamConfig},
     builder::ProcessorBuilder,
@@ -6,13 +6,27 @@ use aptos_indexer_processor_sdk::{
     traits::IntoRunnableStep,
 };
 
-use super::{extractor::Extractor, storer::Storer};
+use super::{
+    alert_detector::AlertDetector, extractor::Extractor,
+    file_transaction_stream::FileTransactionStream, stoppable_stream::StoppableStream,
+    storer::Storer, transaction_recorder::TransactionRecorder,
+    wash_trading_detector::WashTradingDetector,
+};
 use crate::{
-    config::indexer_processor_config::IndexerProcessorConfig,
+    config::{
+        indexer_processor_config::{IndexerProcessorConfig, TransactionSourceConfig},
+        processor_config::ProcessorDomain,
+    },
     utils::{
-        chain_id::check_or_update_chain_id, database_connection::new_db_pool,
-        database_utils::ArcDbPool, latest_processed_version_tracker::LatestVersionProcessedTracker,
-        starting_version::get_starting_version,
+        chain_id::check_or_update_chain_id,
+        database_connection::new_db_pool,
+        database_utils::ArcDbPool,
+        latest_processed_version_tracker::LatestVersionProcessedTracker,
+        metrics::spawn_db_pool_metrics,
+        query_retry::QueryRetryPolicy,
+        shutdown::is_shutdown_requested,
+        starting_version::{get_latest_processed_version_from_db, get_starting_version},
+        webhook::spawn_webhook_dispatchers,
     },
 };
 
@@ -39,41 +53,169 @@ impl ContractProcessor {
         // Merge the starting version from config and the latest processed version from the DB
         let starting_version = get_starting_version(&self.config, self.db_pool.clone()).await?;
 
+        let ending_version = self.ending_version();
+
         tracing::info!(
-            "Starting events processor with starting version: {:?}",
-            starting_version
+            "Starting events processor with starting version: {:?}, ending version: {:?}",
+            starting_version,
+            ending_version
         );
+        if let Some(ending_version) = ending_version {
+            if starting_version > ending_version {
+                tracing::info!(
+                    "Already processed up to ending version {}, nothing to do",
+                    ending_version
+                );
+                return Ok(());
+            }
+        }
 
-        // Check and update the ledger chain id to ensure we're indexing the correct chain
-        let grpc_chain_id = TransactionStream::new(self.config.transaction_stream_config.clone())
-            .await?
-            .get_chain_id()
-            .await?;
-        check_or_update_chain_id(grpc_chain_id as i64, self.db_pool.clone()).await?;
+        self.check_chain_id().await?;
+        spawn_db_pool_metrics(self.db_pool.clone(), self.config.db_config.db_pool_size);
+        // Webhook sinks only enqueue, their outboxes are delivered in the background. Alerts
+        // are only raised on Hyperion events, so other processors leave the outboxes alone.
+        if self.indexes(ProcessorDomain::Hyperion) {
+            spawn_webhook_dispatchers(&self.config.alert_config.sinks, self.db_pool.clone())?;
+        }
 
-        // Define processor steps
-        let transaction_stream = TransactionStreamStep::new(TransactionStreamConfig {
-            starting_version: Some(starting_version),
-            ..self.config.transaction_stream_config
-        })
-        .await?;
-        let events_extractor = Extractor::new(self.config.contract_config.contract_address);
-        let events_storer = Storer::new(self.db_pool.clone());
+        self.run_pipeline(
+            self.config.processor_config.name().to_string(),
+            starting_version,
+            ending_version,
+            false,
+        )
+        .await
+    }
+
+    /// Whether the processor extracts and stores the events of `domain`.
+    pub fn indexes(&self, domain: ProcessorDomain) -> bool {
+        self.config.processor_config.domains().contains(&domain)
+    }
+
+    /// Last version to process, from the processor config or the transaction stream config.
+    pub fn ending_version(&self) -> Option<u64> {
+        self.config.ending_version.or(self
+            .config
+            .transaction_stream_config
+            .request_ending_version())
+    }
+
+    /// Checks and updates the ledger chain id to ensure we're indexing the correct chain
+    pub async fn check_chain_id(&self) -> Result<()> {
+        let chain_id = match &self.config.transaction_stream_config {
+            TransactionSourceConfig::Grpc(config) => {
+                TransactionStream::new(config.clone())
+                    .await?
+                    .get_chain_id()
+                    .await?
+            }
+            TransactionSourceConfig::File(config) => match config.chain_id {
+                Some(chain_id) => chain_id,
+                None => {
+                    tracing::info!(
+                        "No chain id configured for the transaction files, skipping check"
+                    );
+                    return Ok(());
+                }
+            },
+        };
+        check_or_update_chain_id(chain_id as i64, self.db_pool.clone()).await?;
+        Ok(())
+    }
+
+    /// Indexes from `starting_version` until `ending_version`, or forever when unset, saving
+    /// progress to the `processor_status` row of `tracker_name`. Backfill pipelines skip the
+    /// steps that depend on processing versions in order, and the alerts.
+    pub async fn run_pipeline(
+        &self,
+        tracker_name: String,
+        starting_version: u64,
+        ending_version: Option<u64>,
+        backfill: bool,
+    ) -> Result<()> {
+        // Define processor steps. The first step stops polling once the ending version is
+        // reached or a shutdown is requested, and the tracker flushes processor_status as the
+        // pipeline drains
+        let builder = match &self.config.transaction_stream_config {
+            TransactionSourceConfig::Grpc(config) => {
+                let transaction_stream = TransactionStreamStep::new(TransactionStreamConfig {
+                    starting_version: Some(starting_version),
+                    request_ending_version: ending_version,
+                    ..config.clone()
+                })
+                .await?;
+                ProcessorBuilder::new_with_inputless_first_step(
+                    StoppableStream::new(transaction_stream).into_runnable_step(),
+                )
+            }
+            TransactionSourceConfig::File(config) => {
+                let transaction_stream =
+                    FileTransactionStream::new(config, starting_version, ending_version)?;
+                ProcessorBuilder::new_with_inputless_first_step(
+                    StoppableStream::new(transaction_stream).into_runnable_step(),
+                )
+            }
+        };
+        let events_extractor = Extractor::new(
+            self.config.contract_config.contract_address.clone(),
+            self.config.processor_config.domains().to_vec(),
+        );
+        let retry_policy = QueryRetryPolicy::from_config(&self.config.db_config);
+        let events_storer = Storer::new(
+            self.db_pool.clone(),
+            self.config.pricing_config.clone(),
+            self.config.mev_config.clone(),
+            self.config.wash_trading_config.clone(),
+            retry_policy,
+            backfill,
+            self.config.db_config.batch_commit_mode,
+            tracker_name.clone(),
+        );
         let version_tracker = LatestVersionProcessedTracker::new(
-            self.config.db_config,
+            self.config.db_config.clone(),
             starting_version,
-            self.config.processor_config.name().to_string(),
+            tracker_name.clone(),
         )
         .await?;
 
         // Connect processor steps together
-        let (_, buffer_receiver) = ProcessorBuilder::new_with_inputless_first_step(
-            transaction_stream.into_runnable_step(),
-        )
-        .connect_to(events_extractor.into_runnable_step(), 10)
-        .connect_to(events_storer.into_runnable_step(), 10)
-        .connect_to(version_tracker.into_runnable_step(), 10)
-        .end_and_return_output_receiver(10);
+        let builder = match &self.config.record_config {
+            Some(record_config) => builder.connect_to(
+                TransactionRecorder::new(record_config)?.into_runnable_step(),
+                10,
+            ),
+            None => builder,
+        };
+        let builder = builder
+            .connect_to(events_extractor.into_runnable_step(), 10)
+            .connect_to(events_storer.into_runnable_step(), 10);
+        // Wash trading is scored from both swaps and trades, alerts only from Hyperion events
+        let builder = if backfill
+            || !(self.indexes(ProcessorDomain::Hyperion)
+                || self.indexes(ProcessorDomain::TradeRadar))
+        {
+            builder
+        } else {
+            let wash_trading_detector = WashTradingDetector::new(
+                self.db_pool.clone(),
+                self.config.wash_trading_config.clone(),
+                retry_policy,
+            );
+            builder.connect_to(wash_trading_detector.into_runnable_step(), 10)
+        };
+        let builder = if backfill || !self.indexes(ProcessorDomain::Hyperion) {
+            builder
+        } else {
+            let alert_detector = AlertDetector::new(
+                self.db_pool.clone(),
+                self.config.alert_config.clone(),
+                retry_policy,
+            );
+            builder.connect_to(alert_detector.into_runnable_step(), 10)
+        };
+        let (_, buffer_receiver) = builder
+            .connect_to(version_tracker.into_runnable_step(), 10)
+            .end_and_return_output_receiver(10);
 
         // (Optional) Parse the results
         loop {
@@ -83,13 +225,38 @@ impl ContractProcessor {
                         continue;
                     }
                     tracing::info!(
-                        "Finished processing events from versions [{:?}, {:?}]",
+                        "{} finished processing events from versions [{:?}, {:?}]",
+                        tracker_name,
                         txn_context.metadata.start_version,
                         txn_context.metadata.end_version,
                     );
                 }
                 Err(_) => {
-                    tracing::error!("Channel is closed");
+                    if is_shutdown_requested() {
+                        tracing::info!("{} drained after the shutdown request", tracker_name);
+                        return Ok(());
+                    }
+                    let Some(ending_version) = ending_version else {
+                        bail!("{} stopped, its output channel is closed", tracker_name);
+                    };
+                    // A failing step also tears the pipeline down, only the saved cursor
+                    // tells whether the range was processed
+                    let next_version =
+                        get_latest_processed_version_from_db(&tracker_name, self.db_pool.clone())
+                            .await?
+                            .unwrap_or(starting_version);
+                    ensure!(
+                        next_version > ending_version,
+                        "{} stopped before its ending version {}, processed up to {}",
+                        tracker_name,
+                        ending_version,
+                        next_version as i64 - 1
+                    );
+                    tracing::info!(
+                        "{} finished processing up to ending version {}",
+                        tracker_name,
+                        ending_version
+                    );
                     return Ok(());
                 }
             }
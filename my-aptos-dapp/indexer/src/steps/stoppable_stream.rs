@@ -0,0 +1,90 @@
+use anyhow::Result;
+use aptos_indexer_processor_sdk::{
+    traits::{NamedStep, PollableAsyncRunType, PollableAsyncStep, Processable},
+    types::transaction_context::TransactionContext,
+    utils::errors::ProcessorError,
+};
+use async_trait::async_trait;
+use std::time::Duration;
+
+use crate::utils::shutdown::{is_shutdown_requested, shutdown_requested};
+
+/// StoppableStream wraps the first step of a pipeline, the gRPC or the file transaction
+/// stream, and stops polling it once a shutdown is requested. Its output channel then closes,
+/// so the steps after it drain the batches in flight and the tracker saves the last version.
+pub struct StoppableStream<S>
+where
+    Self: Sized + Send + 'static,
+    S: PollableAsyncStep,
+{
+    stream: S,
+}
+
+impl<S> StoppableStream<S>
+where
+    S: PollableAsyncStep,
+{
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+}
+
+#[async_trait]
+impl<S> Processable for StoppableStream<S>
+where
+    S: PollableAsyncStep,
+{
+    type Input = S::Input;
+    type Output = S::Output;
+    type RunType = PollableAsyncRunType;
+
+    async fn init(&mut self) {
+        self.stream.init().await
+    }
+
+    async fn process(
+        &mut self,
+        item: TransactionContext<S::Input>,
+    ) -> Result<Option<TransactionContext<S::Output>>, ProcessorError> {
+        self.stream.process(item).await
+    }
+
+    async fn cleanup(
+        &mut self,
+    ) -> Result<Option<Vec<TransactionContext<S::Output>>>, ProcessorError> {
+        self.stream.cleanup().await
+    }
+}
+
+#[async_trait]
+impl<S> PollableAsyncStep for StoppableStream<S>
+where
+    Self: Sized + Send + Sync + 'static,
+    S: PollableAsyncStep,
+{
+    fn poll_interval(&self) -> Duration {
+        self.stream.poll_interval()
+    }
+
+    async fn poll(&mut self) -> Result<Option<Vec<TransactionContext<S::Output>>>, ProcessorError> {
+        // The gRPC stream waits for new transactions at the tip of the chain. A batch received
+        // after the shutdown request is dropped, it is streamed again on restart.
+        tokio::select! {
+            batches = self.stream.poll() => batches,
+            _ = shutdown_requested() => Ok(None),
+        }
+    }
+
+    async fn should_continue_polling(&mut self) -> bool {
+        !is_shutdown_requested() && self.stream.should_continue_polling().await
+    }
+}
+
+impl<S> NamedStep for StoppableStream<S>
+where
+    S: PollableAsyncStep,
+{
+    fn name(&self) -> String {
+        self.stream.name()
+    }
+}
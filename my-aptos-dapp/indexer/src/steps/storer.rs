@@ -2,27 +2,58 @@ use ahash::AHashMap;
 use anyhow::Result;
 use aptos_indexer_processor_sdk::{
     traits::{async_step::AsyncRunType, AsyncStep, NamedStep, Processable},
-    types::transaction_context::TransactionContext,
+    types::transaction_context::{TransactionContext, TransactionMetadata},
     utils::errors::ProcessorError,
 };
 use async_trait::async_trait;
+use diesel::QueryResult;
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use std::sync::{Arc, OnceLock};
 
 use super::{
     extractor::{ContractEvent, ContractUpgradeChange, TransactionContextData},
     storers::{
-        create_message_event_storer::process_create_message_events,
-        update_message_event_storer::process_update_message_events,
-        upgrade_module_change_storer::process_upgrade_module_changes,
-        upgrade_package_change_storer::process_upgrade_package_changes,
-        create_trade_event_storer::process_create_trade_events,
-        update_trade_event_storer::process_update_trade_events,
-        complete_trade_event_storer::process_complete_trade_events,
-        cancel_trade_event_storer::process_cancel_trade_events,
-        hyperion_pool_storer::process_hyperion_pool_events,
-        hyperion_swap_storer::process_hyperion_swap_events,
+        cancel_trade_event_storer::{process_cancel_trade_events, store_cancel_trade_events},
+        complete_trade_event_storer::{process_complete_trade_events, store_complete_trade_events},
+        create_message_event_storer::{process_create_message_events, store_create_message_events},
+        create_trade_event_storer::{process_create_trade_events, store_create_trade_events},
+        hyperion_pool_stats_storer::{
+            execute_hyperion_pool_stats_sql, process_hyperion_pool_stats,
+        },
+        hyperion_pool_storer::{process_hyperion_pool_events, store_hyperion_pool_events},
+        hyperion_swap_storer::{process_hyperion_swap_events, store_hyperion_swap_events},
+        mev_sandwich_storer::{execute_mev_sandwiches_sql, process_mev_sandwiches},
+        swap_route_storer::{execute_swap_routes_sql, process_swap_routes},
+        token_price_storer::{execute_token_prices_sql, process_token_prices},
+        token_stats_storer::{execute_token_stats_sql, process_token_stats},
+        trader_position_storer::{execute_trader_positions_sql, process_trader_positions},
+        update_message_event_storer::{process_update_message_events, store_update_message_events},
+        update_trade_event_storer::{process_update_trade_events, store_update_trade_events},
+        upgrade_module_change_storer::{
+            process_upgrade_module_changes, store_upgrade_module_changes,
+        },
+        upgrade_package_change_storer::{
+            process_upgrade_package_changes, store_upgrade_package_changes,
+        },
+    },
+};
+use crate::{
+    config::indexer_processor_config::{
+        BatchCommitMode, MevConfig, PricingConfig, WashTradingConfig,
+    },
+    db_models::{
+        hyperion_pool::HyperionPool, hyperion_pool_stat::hour_bucket, hyperion_swap::HyperionSwap,
+        message::Message, module_upgrade::ModuleUpgrade, package_upgrade::PackageUpgrade,
+        processor_status::ProcessorStatus, trade::Trade,
+    },
+    utils::{
+        database_utils::ArcDbPool,
+        latest_processed_version_tracker::{batch_processor_status, upsert_processor_status},
+        metrics::{record_rows_written, STEP_BATCH_LATENCY},
+        query_retry::{with_query_retries, QueryRetryPolicy},
+        write_scheduler::WriteScheduler,
     },
 };
-use crate::utils::database_utils::ArcDbPool;
 
 /// Storer is a step that inserts events in the database.
 pub struct Storer
@@ -30,6 +61,16 @@ where
     Self: Sized + Send + 'static,
 {
     pool: ArcDbPool,
+    pricing_config: PricingConfig,
+    mev_config: MevConfig,
+    wash_trading_config: WashTradingConfig,
+    retry_policy: QueryRetryPolicy,
+    // Backfill workers write ranges out of order, so positions and sandwiches, which
+    // depend on the order of swaps, are left to the replay that follows them
+    backfill: bool,
+    commit_mode: BatchCommitMode,
+    // Row of processor_status moved along with the batch in AtomicWithCursor mode
+    tracker_name: String,
 }
 
 impl AsyncStep for Storer {}
@@ -41,140 +82,545 @@ impl NamedStep for Storer {
 }
 
 impl Storer {
-    pub fn new(pool: ArcDbPool) -> Self {
-        Self { pool }
+    pub fn new(
+        pool: ArcDbPool,
+        pricing_config: PricingConfig,
+        mev_config: MevConfig,
+        wash_trading_config: WashTradingConfig,
+        retry_policy: QueryRetryPolicy,
+        backfill: bool,
+        commit_mode: BatchCommitMode,
+        tracker_name: String,
+    ) -> Self {
+        Self {
+            pool,
+            pricing_config,
+            mev_config,
+            wash_trading_config,
+            retry_policy,
+            backfill,
+            commit_mode,
+            tracker_name,
+        }
     }
-}
-
-#[async_trait]
-impl Processable for Storer {
-    type Input = TransactionContextData;
-    type Output = TransactionContextData;
-    type RunType = AsyncRunType;
 
-    async fn process(
-        &mut self,
-        transaction_context_data: TransactionContext<TransactionContextData>,
-    ) -> Result<Option<TransactionContext<TransactionContextData>>, ProcessorError> {
+    /// Writes every table in its own transactions, in chunks over several connections. Writes
+    /// to disjoint tables run concurrently, the ones that read or update rows of another write
+    /// wait for it.
+    async fn store_chunked(&self, writes: BatchWrites) -> Result<(), ProcessorError> {
         let per_table_chunk_sizes: AHashMap<String, usize> = AHashMap::new();
-        let data = transaction_context_data.data.clone();
-        let (create_msg_events, update_msg_events, create_trade_events, update_trade_events, complete_trade_events, cancel_trade_events, hyperion_pools, hyperion_swaps) = data.events.into_iter().fold(
-            (vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]),
-            |(mut create_msg, mut update_msg, mut create_trade, mut update_trade, mut complete_trade, mut cancel_trade, mut h_pools, mut h_swaps), event| {
-                match event {
-                    ContractEvent::CreateMessageEvent(message) => {
-                        create_msg.push(message);
-                    }
-                    ContractEvent::UpdateMessageEvent(message) => {
-                        update_msg.push(message);
-                    }
-                    ContractEvent::CreateTradeEvent(trade, seq) => {
-                        create_trade.push((trade, seq));
-                    }
-                    ContractEvent::UpdateTradeEvent(trade, seq) => {
-                        update_trade.push((trade, seq));
-                    }
-                    ContractEvent::CompleteTradeEvent(trade, seq) => {
-                        complete_trade.push((trade, seq));
-                    }
-                    ContractEvent::CancelTradeEvent(trade, seq) => {
-                        cancel_trade.push((trade, seq));
-                    }
-                    ContractEvent::HyperionPoolCreated(pool) | ContractEvent::HyperionPoolStateUpdate(pool) => {
-                        h_pools.push(pool);
-                    }
-                    ContractEvent::HyperionSwap(swap) => {
-                        h_swaps.push(swap);
-                    }
+        let has_hyperion_swaps = writes.has_hyperion_swaps();
+        let has_hyperion_events = writes.has_hyperion_events();
+        let BatchWrites {
+            create_msg_events,
+            update_msg_events,
+            create_trade_events,
+            update_trade_events,
+            complete_trade_events,
+            cancel_trade_events,
+            hyperion_pools,
+            hyperion_swaps,
+            touched_pools,
+            touched_buckets,
+            module_upgrades,
+            package_upgrades,
+            chain_timestamp,
+            start_version,
+            end_version,
+        } = writes;
+        let mut scheduler = WriteScheduler::new();
+
+        scheduler.add(
+            "create_messages",
+            "messages",
+            &[],
+            process_create_message_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                create_msg_events,
+            ),
+        );
+        scheduler.add(
+            "update_messages",
+            "messages",
+            &["create_messages"],
+            process_update_message_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                update_msg_events,
+            ),
+        );
+
+        // A trade can be created, updated and closed within one batch
+        scheduler.add(
+            "create_trades",
+            "trades",
+            &[],
+            process_create_trade_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                create_trade_events,
+            ),
+        );
+        scheduler.add(
+            "update_trades",
+            "trades",
+            &["create_trades"],
+            process_update_trade_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                update_trade_events,
+            ),
+        );
+        scheduler.add(
+            "complete_trades",
+            "trades",
+            &["update_trades"],
+            process_complete_trade_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                complete_trade_events,
+            ),
+        );
+        scheduler.add(
+            "cancel_trades",
+            "trades",
+            &["complete_trades"],
+            process_cancel_trade_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                cancel_trade_events,
+            ),
+        );
+
+        scheduler.add(
+            "hyperion_pools",
+            "hyperion_pools",
+            &[],
+            process_hyperion_pool_events(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                hyperion_pools,
+            ),
+        );
+        // Prices are routed before swaps so each swap can be valued in USD
+        if has_hyperion_events {
+            scheduler.add(
+                "token_prices",
+                "token_prices",
+                &["hyperion_pools"],
+                process_token_prices(
+                    self.pool.clone(),
+                    self.retry_policy,
+                    self.pricing_config.clone(),
+                    chain_timestamp,
+                    end_version,
+                ),
+            );
+        }
+        // Swaps valued and filled in by the swap storer, handed to the writes derived from them
+        // instead of being read back
+        let stored_swaps: Arc<OnceLock<Vec<HyperionSwap>>> = Arc::default();
+        scheduler.add(
+            "hyperion_swaps",
+            "hyperion_swaps",
+            &["hyperion_pools", "token_prices"],
+            {
+                let (chunk_sizes, stored_swaps) =
+                    (per_table_chunk_sizes.clone(), stored_swaps.clone());
+                async move {
+                    process_hyperion_swap_events(
+                        self.pool.clone(),
+                        self.retry_policy,
+                        chunk_sizes,
+                        hyperion_swaps,
+                    )
+                    .await
+                    .map(|swaps| {
+                        let _ = stored_swaps.set(swaps);
+                    })
                 }
-                (create_msg, update_msg, create_trade, update_trade, complete_trade, cancel_trade, h_pools, h_swaps)
             },
         );
+        if has_hyperion_swaps && !self.backfill {
+            let swaps = stored_swaps.clone();
+            scheduler.add(
+                "trader_positions",
+                "trader_token_positions",
+                &["hyperion_swaps"],
+                async move {
+                    let swaps = swaps.get().cloned().unwrap_or_default();
+                    process_trader_positions(self.pool.clone(), self.retry_policy, swaps).await
+                },
+            );
+            let swaps = stored_swaps.clone();
+            scheduler.add(
+                "mev_sandwiches",
+                "mev_sandwiches",
+                &["hyperion_swaps"],
+                async move {
+                    let swaps = swaps.get().cloned().unwrap_or_default();
+                    process_mev_sandwiches(
+                        self.pool.clone(),
+                        self.retry_policy,
+                        swaps,
+                        start_version,
+                        self.mev_config.sandwich_max_version_gap,
+                    )
+                    .await
+                },
+            );
+        }
+        if has_hyperion_swaps {
+            scheduler.add(
+                "swap_routes",
+                "swap_routes",
+                &["hyperion_swaps"],
+                async move {
+                    let swaps = stored_swaps.get().cloned().unwrap_or_default();
+                    process_swap_routes(self.pool.clone(), self.retry_policy, swaps).await
+                },
+            );
+        }
+        scheduler.add(
+            "hyperion_pool_stats",
+            "hyperion_pool_stats",
+            &["hyperion_pools", "hyperion_swaps"],
+            process_hyperion_pool_stats(
+                self.pool.clone(),
+                self.retry_policy,
+                touched_pools.clone(),
+                touched_buckets,
+                chain_timestamp,
+                self.wash_trading_config.exclude_flagged_volume,
+            ),
+        );
+        // Token stats read the hourly buckets, so they run after the pool stats are rebuilt
+        scheduler.add(
+            "token_stats",
+            "token_stats",
+            &["hyperion_pool_stats", "token_prices"],
+            process_token_stats(
+                self.pool.clone(),
+                self.retry_policy,
+                touched_pools,
+                chain_timestamp,
+            ),
+        );
 
-        process_create_message_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
-            create_msg_events,
-        )
-        .await?;
+        scheduler.add(
+            "module_upgrades",
+            "module_upgrade_history",
+            &[],
+            process_upgrade_module_changes(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                module_upgrades,
+            ),
+        );
+        scheduler.add(
+            "package_upgrades",
+            "package_upgrade_history",
+            &[],
+            process_upgrade_package_changes(
+                self.pool.clone(),
+                self.retry_policy,
+                per_table_chunk_sizes.clone(),
+                package_upgrades,
+            ),
+        );
+        scheduler.run().await
+    }
 
-        process_update_message_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
-            update_msg_events,
-        )
-        .await?;
+    /// Writes the whole batch, and `cursor` when set, in a single transaction. The writes run
+    /// in the same order as in chunked mode, their own transactions become savepoints. On a
+    /// transient error the whole batch is rolled back and written again.
+    async fn store_atomic(
+        &self,
+        writes: BatchWrites,
+        cursor: Option<ProcessorStatus>,
+    ) -> Result<(), ProcessorError> {
+        with_query_retries(&self.pool, self.retry_policy, "atomic_batch", |conn| {
+            let (writes, cursor) = (writes.clone(), cursor.clone());
+            Box::pin(
+                conn.transaction(move |conn| {
+                    Box::pin(self.execute_batch_sql(conn, writes, cursor))
+                }),
+            )
+        })
+        .await
+    }
 
-        process_create_trade_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
+    async fn execute_batch_sql(
+        &self,
+        conn: &mut AsyncPgConnection,
+        writes: BatchWrites,
+        cursor: Option<ProcessorStatus>,
+    ) -> QueryResult<()> {
+        let per_table_chunk_sizes: AHashMap<String, usize> = AHashMap::new();
+        let has_hyperion_swaps = writes.has_hyperion_swaps();
+        let has_hyperion_events = writes.has_hyperion_events();
+        let BatchWrites {
+            create_msg_events,
+            update_msg_events,
             create_trade_events,
-        )
-        .await?;
-
-        process_update_trade_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
             update_trade_events,
-        )
-        .await?;
-
-        process_complete_trade_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
             complete_trade_events,
-        )
-        .await?;
-
-        process_cancel_trade_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
             cancel_trade_events,
-        )
-        .await?;
-
-        process_hyperion_pool_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
             hyperion_pools,
-        )
-        .await?;
-
-        process_hyperion_swap_events(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
             hyperion_swaps,
+            touched_pools,
+            touched_buckets,
+            module_upgrades,
+            package_upgrades,
+            chain_timestamp,
+            start_version,
+            end_version,
+        } = writes;
+
+        store_create_message_events(conn, &per_table_chunk_sizes, create_msg_events).await?;
+        store_update_message_events(conn, &per_table_chunk_sizes, update_msg_events).await?;
+        store_create_trade_events(conn, &per_table_chunk_sizes, create_trade_events).await?;
+        store_update_trade_events(conn, &per_table_chunk_sizes, update_trade_events).await?;
+        store_complete_trade_events(conn, &per_table_chunk_sizes, complete_trade_events).await?;
+        store_cancel_trade_events(conn, &per_table_chunk_sizes, cancel_trade_events).await?;
+
+        store_hyperion_pool_events(conn, &per_table_chunk_sizes, hyperion_pools).await?;
+        if has_hyperion_events {
+            execute_token_prices_sql(
+                conn,
+                self.pricing_config.clone(),
+                chain_timestamp,
+                end_version,
+            )
+            .await?;
+        }
+        let stored_swaps =
+            store_hyperion_swap_events(conn, &per_table_chunk_sizes, hyperion_swaps).await?;
+
+        if has_hyperion_swaps && !self.backfill {
+            execute_trader_positions_sql(conn, stored_swaps.clone()).await?;
+            execute_mev_sandwiches_sql(
+                conn,
+                stored_swaps.clone(),
+                start_version,
+                self.mev_config.sandwich_max_version_gap,
+            )
+            .await?;
+        }
+        if has_hyperion_swaps {
+            execute_swap_routes_sql(conn, stored_swaps).await?;
+        }
+        execute_hyperion_pool_stats_sql(
+            conn,
+            touched_pools.clone(),
+            touched_buckets,
+            chain_timestamp,
+            self.wash_trading_config.exclude_flagged_volume,
         )
         .await?;
+        if !touched_pools.is_empty() {
+            execute_token_stats_sql(conn, touched_pools, chain_timestamp).await?;
+        }
 
-        let (module_upgrades, package_upgrades) = data.changes.into_iter().fold(
-            (vec![], vec![]),
-            |(mut module_upgrades, mut package_upgrades), upgrade_change| {
-                match upgrade_change {
-                    ContractUpgradeChange::ModuleUpgradeChange(module_upgrade) => {
-                        module_upgrades.push(module_upgrade);
-                    }
-                    ContractUpgradeChange::PackageUpgradeChange(package_upgrade) => {
-                        package_upgrades.push(package_upgrade);
-                    }
+        store_upgrade_module_changes(conn, &per_table_chunk_sizes, module_upgrades).await?;
+        store_upgrade_package_changes(conn, &per_table_chunk_sizes, package_upgrades).await?;
+
+        if let Some(status) = cursor {
+            upsert_processor_status(conn, &status).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Rows of a batch, grouped by the storer that writes them.
+#[derive(Clone)]
+struct BatchWrites {
+    create_msg_events: Vec<Message>,
+    update_msg_events: Vec<Message>,
+    create_trade_events: Vec<(Trade, i64)>,
+    update_trade_events: Vec<(Trade, i64)>,
+    complete_trade_events: Vec<(Trade, i64)>,
+    cancel_trade_events: Vec<(Trade, i64)>,
+    hyperion_pools: Vec<HyperionPool>,
+    hyperion_swaps: Vec<HyperionSwap>,
+    // Pools whose state changed in this batch, either directly or through a swap
+    touched_pools: Vec<String>,
+    // Pool state updates also close a bucket, so price history has no gaps between swaps
+    touched_buckets: Vec<(String, i64)>,
+    module_upgrades: Vec<ModuleUpgrade>,
+    package_upgrades: Vec<PackageUpgrade>,
+    // Chain time of the batch, used as "now" for all rolling windows
+    chain_timestamp: i64,
+    start_version: i64,
+    end_version: i64,
+}
+
+impl BatchWrites {
+    fn new(data: TransactionContextData, metadata: &TransactionMetadata) -> Self {
+        let mut writes = Self {
+            create_msg_events: vec![],
+            update_msg_events: vec![],
+            create_trade_events: vec![],
+            update_trade_events: vec![],
+            complete_trade_events: vec![],
+            cancel_trade_events: vec![],
+            hyperion_pools: vec![],
+            hyperion_swaps: vec![],
+            touched_pools: vec![],
+            touched_buckets: vec![],
+            module_upgrades: vec![],
+            package_upgrades: vec![],
+            chain_timestamp: metadata
+                .end_transaction_timestamp
+                .as_ref()
+                .map(|t| t.seconds)
+                .unwrap_or_default(),
+            start_version: metadata.start_version as i64,
+            end_version: metadata.end_version as i64,
+        };
+        for event in data.events {
+            match event {
+                ContractEvent::CreateMessageEvent(message) => {
+                    writes.create_msg_events.push(message);
                 }
-                (module_upgrades, package_upgrades)
-            },
-        );
+                ContractEvent::UpdateMessageEvent(message) => {
+                    writes.update_msg_events.push(message);
+                }
+                ContractEvent::CreateTradeEvent(trade, seq) => {
+                    writes.create_trade_events.push((trade, seq));
+                }
+                ContractEvent::UpdateTradeEvent(trade, seq) => {
+                    writes.update_trade_events.push((trade, seq));
+                }
+                ContractEvent::CompleteTradeEvent(trade, seq) => {
+                    writes.complete_trade_events.push((trade, seq));
+                }
+                ContractEvent::CancelTradeEvent(trade, seq) => {
+                    writes.cancel_trade_events.push((trade, seq));
+                }
+                ContractEvent::HyperionPoolCreated(pool)
+                | ContractEvent::HyperionPoolStateUpdate(pool) => {
+                    writes.hyperion_pools.push(pool);
+                }
+                ContractEvent::HyperionSwap(swap) => {
+                    writes.hyperion_swaps.push(swap);
+                }
+            }
+        }
+        for change in data.changes {
+            match change {
+                ContractUpgradeChange::ModuleUpgradeChange(module_upgrade) => {
+                    writes.module_upgrades.push(module_upgrade);
+                }
+                ContractUpgradeChange::PackageUpgradeChange(package_upgrade) => {
+                    writes.package_upgrades.push(package_upgrade);
+                }
+            }
+        }
 
-        process_upgrade_module_changes(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
-            module_upgrades,
-        )
-        .await?;
+        writes.touched_pools = writes
+            .hyperion_pools
+            .iter()
+            .map(|pool| pool.pool_address.clone())
+            .chain(
+                writes
+                    .hyperion_swaps
+                    .iter()
+                    .map(|swap| swap.pool_address.clone()),
+            )
+            .collect();
+        writes.touched_pools.sort();
+        writes.touched_pools.dedup();
+        writes.touched_buckets = writes
+            .hyperion_pools
+            .iter()
+            .map(|pool| {
+                (
+                    pool.pool_address.clone(),
+                    hour_bucket(pool.last_update_timestamp),
+                )
+            })
+            .chain(
+                writes
+                    .hyperion_swaps
+                    .iter()
+                    .map(|swap| (swap.pool_address.clone(), hour_bucket(swap.timestamp))),
+            )
+            .collect();
+        writes.touched_buckets.sort();
+        writes.touched_buckets.dedup();
+        writes
+    }
 
-        process_upgrade_package_changes(
-            self.pool.clone(),
-            per_table_chunk_sizes.clone(),
-            package_upgrades,
-        )
-        .await?;
+    fn has_hyperion_swaps(&self) -> bool {
+        !self.hyperion_swaps.is_empty()
+    }
+
+    fn has_hyperion_events(&self) -> bool {
+        !self.hyperion_pools.is_empty() || self.has_hyperion_swaps()
+    }
+
+    /// Rows written per table, recorded once the batch is stored.
+    fn rows_written(&self) -> [(&'static str, usize); 6] {
+        [
+            (
+                "messages",
+                self.create_msg_events.len() + self.update_msg_events.len(),
+            ),
+            (
+                "trades",
+                self.create_trade_events.len()
+                    + self.update_trade_events.len()
+                    + self.complete_trade_events.len()
+                    + self.cancel_trade_events.len(),
+            ),
+            ("hyperion_pools", self.hyperion_pools.len()),
+            ("hyperion_swaps", self.hyperion_swaps.len()),
+            ("module_upgrade_history", self.module_upgrades.len()),
+            ("package_upgrade_history", self.package_upgrades.len()),
+        ]
+    }
+}
+
+#[async_trait]
+impl Processable for Storer {
+    type Input = TransactionContextData;
+    type Output = TransactionContextData;
+    type RunType = AsyncRunType;
+
+    async fn process(
+        &mut self,
+        transaction_context_data: TransactionContext<TransactionContextData>,
+    ) -> Result<Option<TransactionContext<TransactionContextData>>, ProcessorError> {
+        let _timer = STEP_BATCH_LATENCY
+            .with_label_values(&["Storer"])
+            .start_timer();
+        let writes = BatchWrites::new(
+            transaction_context_data.data.clone(),
+            &transaction_context_data.metadata,
+        );
+        let rows_written = writes.rows_written();
+        match self.commit_mode {
+            BatchCommitMode::Chunked => self.store_chunked(writes).await?,
+            BatchCommitMode::Atomic => self.store_atomic(writes, None).await?,
+            BatchCommitMode::AtomicWithCursor => {
+                let cursor =
+                    batch_processor_status(&self.tracker_name, &transaction_context_data.metadata);
+                self.store_atomic(writes, Some(cursor)).await?
+            }
+        }
+        for (table, rows) in rows_written {
+            record_rows_written(table, rows);
+        }
 
         Ok(Some(transaction_context_data))
     }
@@ -0,0 +1,79 @@
+use ahash::AHashMap;
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
+use diesel::{insert_into, QueryResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::sync::Arc;
+
+use crate::{
+    db_models::alert::Alert,
+    schema::alerts,
+    utils::{
+        alert_sink::AlertSink,
+        database_utils::{get_config_table_chunk_size, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
+        webhook::enqueue_outbox_entries,
+    },
+};
+
+async fn execute_alerts_sql(
+    conn: &mut AsyncPgConnection,
+    items_to_insert: Vec<Alert>,
+    sinks: Vec<Arc<dyn AlertSink>>,
+) -> QueryResult<Vec<Alert>> {
+    let now = chrono::Utc::now().timestamp();
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            // Alerts are immutable once raised, a known alert_id means it was already stored
+            let query = insert_into(alerts::table)
+                .values(items_to_insert)
+                .on_conflict(alerts::alert_id)
+                .do_nothing()
+                .returning(alerts::all_columns);
+            let new_alerts = query.get_results::<Alert>(conn).await?;
+            // Enqueued with the alerts, so a crash can't store an alert without its deliveries
+            let entries = sinks
+                .iter()
+                .flat_map(|sink| sink.outbox_entries(&new_alerts, now))
+                .collect::<Vec<_>>();
+            enqueue_outbox_entries(conn, &entries).await?;
+            QueryResult::Ok(new_alerts)
+        })
+    })
+    .await
+}
+
+/// Stores the alerts with the outbox rows of the sinks, and returns the ones that were not
+/// stored before.
+pub async fn process_alerts(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+    alerts: Vec<Alert>,
+    sinks: Vec<Arc<dyn AlertSink>>,
+) -> Result<Vec<Alert>, ProcessorError> {
+    let chunk_size = get_config_table_chunk_size::<Alert>("alerts", &per_table_chunk_sizes);
+    let tasks = alerts
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let pool = pool.clone();
+            let items = chunk.to_vec();
+            let sinks = sinks.clone();
+            tokio::spawn(async move {
+                with_query_retries(&pool, retry_policy, "alerts", |conn| {
+                    Box::pin(execute_alerts_sql(conn, items.clone(), sinks.clone()))
+                })
+                .await
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let results = futures_util::future::try_join_all(tasks)
+        .await
+        .map_err(task_error)?;
+    let mut new_alerts = vec![];
+    for res in results {
+        new_alerts.extend(res?);
+    }
+    Ok(new_alerts)
+}
@@ -1,15 +1,18 @@
 use ahash::AHashMap;
 use anyhow::Result;
 use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
-use diesel::{insert_into, upsert::excluded, ExpressionMethods, QueryResult};
+use diesel::{
+    insert_into, query_dsl::methods::FilterDsl, upsert::excluded, BoolExpressionMethods,
+    ExpressionMethods, QueryResult,
+};
 use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 
 use crate::{
     db_models::{trade::Trade, trader_stat::TraderStat},
     schema::{trades, trader_stats},
     utils::{
-        database_connection::get_db_connection,
-        database_utils::{get_config_table_chunk_size, ArcDbPool},
+        database_utils::{get_config_table_chunk_size, greatest, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
@@ -28,7 +31,18 @@ async fn execute_cancel_trade_events_sql(
                     trades::status.eq(excluded(trades::status)),
                     trades::last_update_timestamp.eq(excluded(trades::last_update_timestamp)),
                     trades::last_update_event_idx.eq(excluded(trades::last_update_event_idx)),
-                ));
+                ))
+                .filter(
+                    // Ranges can be written out of order, keep the latest update
+                    trades::last_update_timestamp
+                        .lt(excluded(trades::last_update_timestamp))
+                        .or(trades::last_update_timestamp
+                            .eq(excluded(trades::last_update_timestamp))
+                            .and(
+                                trades::last_update_event_idx
+                                    .lt(excluded(trades::last_update_event_idx)),
+                            )),
+                );
             update_trade_query.execute(conn).await?;
 
             if !trader_stats_updates.is_empty() {
@@ -37,8 +51,10 @@ async fn execute_cancel_trade_events_sql(
                     .on_conflict(trader_stats::trader_addr)
                     .do_update()
                     .set((
-                        trader_stats::last_update_timestamp
-                            .eq(excluded(trader_stats::last_update_timestamp)),
+                        trader_stats::last_update_timestamp.eq(greatest(
+                            trader_stats::last_update_timestamp,
+                            excluded(trader_stats::last_update_timestamp),
+                        )),
                         trader_stats::cancelled_trades
                             .eq(trader_stats::cancelled_trades + excluded(trader_stats::cancelled_trades)),
                     ));
@@ -51,11 +67,8 @@ async fn execute_cancel_trade_events_sql(
     .await
 }
 
-pub async fn process_cancel_trade_events(
-    pool: ArcDbPool,
-    per_table_chunk_sizes: AHashMap<String, usize>,
-    cancel_events: Vec<(Trade, i64)>,
-) -> Result<(), ProcessorError> {
+/// The trades to write and the trader stat increments of the events.
+fn cancel_trade_changes(cancel_events: &[(Trade, i64)]) -> (Vec<Trade>, Vec<TraderStat>) {
     let mut trader_stats_map: AHashMap<String, TraderStat> = AHashMap::new();
     let trades: Vec<Trade> = cancel_events
         .iter()
@@ -70,6 +83,16 @@ pub async fn process_cancel_trade_events(
         .collect();
 
     let trader_stats: Vec<TraderStat> = trader_stats_map.into_values().collect();
+    (trades, trader_stats)
+}
+
+pub async fn process_cancel_trade_events(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+    cancel_events: Vec<(Trade, i64)>,
+) -> Result<(), ProcessorError> {
+    let (trades, trader_stats) = cancel_trade_changes(&cancel_events);
 
     let chunk_size = get_config_table_chunk_size::<Trade>("trades", &per_table_chunk_sizes);
     let tasks = trades
@@ -84,24 +107,38 @@ pub async fn process_cancel_trade_events(
                 vec![]
             };
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing cancel trade events",
-                );
-                execute_cancel_trade_events_sql(conn, items, stats).await
+                with_query_retries(&pool, retry_policy, "cancel_trade_events", |conn| {
+                    Box::pin(execute_cancel_trade_events_sql(conn, items.clone(), stats.clone()))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::warn!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(())
+}
+
+/// `process_cancel_trade_events` on a single connection, one chunk after the other.
+pub async fn store_cancel_trade_events(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    cancel_events: Vec<(Trade, i64)>,
+) -> QueryResult<()> {
+    let (trades, trader_stats) = cancel_trade_changes(&cancel_events);
+    let chunk_size = get_config_table_chunk_size::<Trade>("trades", per_table_chunk_sizes);
+    for (i, chunk) in trades.chunks(chunk_size).enumerate() {
+        let stats = if i == 0 {
+            trader_stats.clone()
+        } else {
+            vec![]
+        };
+        execute_cancel_trade_events_sql(conn, chunk.to_vec(), stats).await?;
     }
     Ok(())
 }
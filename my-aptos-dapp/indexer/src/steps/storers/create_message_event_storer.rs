@@ -9,8 +9,8 @@ use crate::{
     db_models::{message::Message, user_stat::UserStat},
     schema::{messages, user_stats},
     utils::{
-        database_connection::get_db_connection,
-        database_utils::{get_config_table_chunk_size, ArcDbPool},
+        database_utils::{get_config_table_chunk_size, greatest, least, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
@@ -62,9 +62,15 @@ async fn execute_create_message_events_sql(
                 .do_update()
                 .set((
                     user_stats::user_addr.eq(user_stats::user_addr),
-                    user_stats::creation_timestamp.eq(user_stats::creation_timestamp),
-                    user_stats::last_update_timestamp
-                        .eq(excluded(user_stats::last_update_timestamp)),
+                    // Ranges can be written out of order
+                    user_stats::creation_timestamp.eq(least(
+                        user_stats::creation_timestamp,
+                        excluded(user_stats::creation_timestamp),
+                    )),
+                    user_stats::last_update_timestamp.eq(greatest(
+                        user_stats::last_update_timestamp,
+                        excluded(user_stats::last_update_timestamp),
+                    )),
                     user_stats::created_messages
                         .eq(user_stats::created_messages + excluded(user_stats::created_messages)),
                     user_stats::updated_messages.eq(user_stats::updated_messages),
@@ -81,15 +87,13 @@ async fn execute_create_message_events_sql(
     .await
 }
 
-pub async fn process_create_message_events(
-    pool: ArcDbPool,
-    per_table_chunk_sizes: AHashMap<String, usize>,
-    create_events: Vec<Message>,
-) -> Result<(), ProcessorError> {
-    // Key is user address
-    // Value is (number of new messages, earliest create message time, latest create message time)
+/// Key is user address
+/// Value is (number of new messages, earliest create message time, latest create message time)
+fn create_message_user_stats_changes(
+    create_events: &[Message],
+) -> AHashMap<String, (i64, i64, i64)> {
     let mut user_stats_changes: AHashMap<String, (i64, i64, i64)> = AHashMap::new();
-    for message in create_events.clone() {
+    for message in create_events {
         let (new_count, earliest_time, latest_time) = user_stats_changes
             .get(&message.creator_addr)
             .cloned()
@@ -103,6 +107,16 @@ pub async fn process_create_message_events(
             ),
         );
     }
+    user_stats_changes
+}
+
+pub async fn process_create_message_events(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+    create_events: Vec<Message>,
+) -> Result<(), ProcessorError> {
+    let user_stats_changes = create_message_user_stats_changes(&create_events);
 
     let chunk_size = get_config_table_chunk_size::<Message>("messages", &per_table_chunk_sizes);
     let tasks = create_events
@@ -112,24 +126,38 @@ pub async fn process_create_message_events(
             let items = chunk.to_vec();
             let user_stats_changes = user_stats_changes.clone();
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing create message events",
-                );
-                execute_create_message_events_sql(conn, items, user_stats_changes).await
+                with_query_retries(&pool, retry_policy, "create_message_events", |conn| {
+                    Box::pin(execute_create_message_events_sql(
+                        conn,
+                        items.clone(),
+                        user_stats_changes.clone(),
+                    ))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::warn!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(())
+}
+
+/// `process_create_message_events` on a single connection, one chunk after the other.
+pub async fn store_create_message_events(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    create_events: Vec<Message>,
+) -> QueryResult<()> {
+    let user_stats_changes = create_message_user_stats_changes(&create_events);
+    let chunk_size = get_config_table_chunk_size::<Message>("messages", per_table_chunk_sizes);
+    for chunk in create_events.chunks(chunk_size) {
+        execute_create_message_events_sql(conn, chunk.to_vec(), user_stats_changes.clone())
+            .await?;
     }
     Ok(())
 }
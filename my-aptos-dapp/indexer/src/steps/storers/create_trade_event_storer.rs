@@ -9,8 +9,8 @@ use crate::{
     db_models::{trade::Trade, trader_stat::TraderStat},
     schema::{trades, trader_stats},
     utils::{
-        database_connection::get_db_connection,
-        database_utils::{get_config_table_chunk_size, ArcDbPool},
+        database_utils::{get_config_table_chunk_size, greatest, least, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
@@ -32,8 +32,15 @@ async fn execute_create_trade_events_sql(
                 .on_conflict(trader_stats::trader_addr)
                 .do_update()
                 .set((
-                    trader_stats::last_update_timestamp
-                        .eq(excluded(trader_stats::last_update_timestamp)),
+                    // Ranges can be written out of order
+                    trader_stats::creation_timestamp.eq(least(
+                        trader_stats::creation_timestamp,
+                        excluded(trader_stats::creation_timestamp),
+                    )),
+                    trader_stats::last_update_timestamp.eq(greatest(
+                        trader_stats::last_update_timestamp,
+                        excluded(trader_stats::last_update_timestamp),
+                    )),
                     trader_stats::total_trades
                         .eq(trader_stats::total_trades + excluded(trader_stats::total_trades)),
                     trader_stats::total_buy_trades
@@ -55,11 +62,8 @@ async fn execute_create_trade_events_sql(
     .await
 }
 
-pub async fn process_create_trade_events(
-    pool: ArcDbPool,
-    per_table_chunk_sizes: AHashMap<String, usize>,
-    create_events: Vec<(Trade, i64)>,
-) -> Result<(), ProcessorError> {
+/// The trades to write and the trader stat increments of the events.
+fn create_trade_changes(create_events: &[(Trade, i64)]) -> (Vec<Trade>, Vec<TraderStat>) {
     let mut trader_stats_map: AHashMap<String, TraderStat> = AHashMap::new();
     let trades: Vec<Trade> = create_events
         .iter()
@@ -74,6 +78,16 @@ pub async fn process_create_trade_events(
         .collect();
 
     let trader_stats: Vec<TraderStat> = trader_stats_map.into_values().collect();
+    (trades, trader_stats)
+}
+
+pub async fn process_create_trade_events(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+    create_events: Vec<(Trade, i64)>,
+) -> Result<(), ProcessorError> {
+    let (trades, trader_stats) = create_trade_changes(&create_events);
 
     let chunk_size = get_config_table_chunk_size::<Trade>("trades", &per_table_chunk_sizes);
     let tasks = trades
@@ -88,24 +102,38 @@ pub async fn process_create_trade_events(
                 vec![]
             };
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing create trade events",
-                );
-                execute_create_trade_events_sql(conn, items, stats).await
+                with_query_retries(&pool, retry_policy, "create_trade_events", |conn| {
+                    Box::pin(execute_create_trade_events_sql(conn, items.clone(), stats.clone()))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::warn!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(())
+}
+
+/// `process_create_trade_events` on a single connection, one chunk after the other.
+pub async fn store_create_trade_events(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    create_events: Vec<(Trade, i64)>,
+) -> QueryResult<()> {
+    let (trades, trader_stats) = create_trade_changes(&create_events);
+    let chunk_size = get_config_table_chunk_size::<Trade>("trades", per_table_chunk_sizes);
+    for (i, chunk) in trades.chunks(chunk_size).enumerate() {
+        let stats = if i == 0 {
+            trader_stats.clone()
+        } else {
+            vec![]
+        };
+        execute_create_trade_events_sql(conn, chunk.to_vec(), stats).await?;
     }
     Ok(())
 }
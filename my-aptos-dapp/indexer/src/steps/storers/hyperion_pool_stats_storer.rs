@@ -0,0 +1,376 @@
+use ahash::AHashMap;
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::{convert::standardize_address, errors::ProcessorError};
+use diesel::{
+    dsl::count_distinct, insert_into, upsert::excluded, ExpressionMethods, OptionalExtension,
+    QueryDsl, QueryResult,
+};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    db_models::{
+        hyperion_pool::{price_from_sqrt_price_x96, HyperionPool},
+        hyperion_pool_hourly_stat::HyperionPoolHourlyStat,
+        hyperion_pool_liquidity_snapshot::HyperionPoolLiquiditySnapshot,
+        hyperion_pool_stat::{
+            hour_bucket, last_bucket_ended_before, window_start_bucket, HyperionPoolStat,
+            SECONDS_PER_HOUR, WINDOW_24H_SECS, WINDOW_7D_SECS,
+        },
+        token_price::TokenPrice,
+    },
+    schema::{
+        hyperion_pool_hourly_stats, hyperion_pool_hourly_traders,
+        hyperion_pool_liquidity_snapshots, hyperion_pool_stats, hyperion_pools, hyperion_swaps,
+    },
+    utils::{
+        database_utils::ArcDbPool,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+// Pools without new swaps refreshed per batch, the rest wait for the next batches
+const STALE_POOLS_PER_BATCH: i64 = 100;
+
+async fn count_unique_traders(
+    conn: &mut AsyncPgConnection,
+    pool_address: &str,
+    since_bucket: i64,
+) -> QueryResult<i64> {
+    hyperion_pool_hourly_traders::table
+        .filter(hyperion_pool_hourly_traders::pool_address.eq(pool_address))
+        .filter(hyperion_pool_hourly_traders::bucket_start.ge(since_bucket))
+        .select(count_distinct(hyperion_pool_hourly_traders::trader_addr))
+        .get_result::<i64>(conn)
+        .await
+}
+
+/// Rebuilds an hourly bucket from the swaps stored in it. A bucket without swaps (i.e. only
+/// touched by a pool state update) closes at the current pool price. Token amounts need to
+/// know which side is token0, so they stay at 0 for pools that were never indexed. Wash
+/// swaps still move the price and pay fees, `exclude_wash_volume` only drops their volume.
+async fn compute_hourly_stat(
+    conn: &mut AsyncPgConnection,
+    pool_address: &str,
+    bucket_start: i64,
+    pool: Option<&HyperionPool>,
+    exclude_wash_volume: bool,
+) -> QueryResult<HyperionPoolHourlyStat> {
+    let swaps = hyperion_swaps::table
+        .filter(hyperion_swaps::pool_address.eq(pool_address))
+        .filter(hyperion_swaps::timestamp.ge(bucket_start))
+        .filter(hyperion_swaps::timestamp.lt(bucket_start + SECONDS_PER_HOUR))
+        .order((hyperion_swaps::tx_version, hyperion_swaps::event_idx))
+        .select((
+            hyperion_swaps::token_in,
+            hyperion_swaps::amount_in,
+            hyperion_swaps::amount_out,
+            hyperion_swaps::amount_usd,
+            hyperion_swaps::sqrt_price_x96_after,
+            hyperion_swaps::is_wash,
+        ))
+        .load::<(String, String, String, String, String, bool)>(conn)
+        .await?;
+    let token0_address = pool.map(|pool| standardize_address(&pool.token0_address));
+    let (mut volume0, mut volume1, mut volume_usd, mut fee_volume_usd) = (0.0, 0.0, 0.0, 0.0);
+    for (token_in, amount_in, amount_out, amount_usd, _, is_wash) in &swaps {
+        let amount_usd = amount_usd.parse::<f64>().unwrap_or(0.0);
+        fee_volume_usd += amount_usd;
+        if exclude_wash_volume && *is_wash {
+            continue;
+        }
+        volume_usd += amount_usd;
+        let Some(token0_address) = &token0_address else {
+            continue;
+        };
+        let amount_in = amount_in.parse::<f64>().unwrap_or(0.0);
+        let amount_out = amount_out.parse::<f64>().unwrap_or(0.0);
+        if standardize_address(token_in) == *token0_address {
+            volume0 += amount_in;
+            volume1 += amount_out;
+        } else {
+            volume0 += amount_out;
+            volume1 += amount_in;
+        }
+    }
+    // fee_tier is in hundredths of a basis point, e.g. 3000 = 0.3%
+    let fee_tier = pool.map(|pool| pool.fee_tier).unwrap_or(3000);
+    let fees_usd = fee_volume_usd * fee_tier as f64 / 1_000_000.0;
+    let close_price = swaps
+        .last()
+        .map(|(_, _, _, _, sqrt_price_x96, _)| price_from_sqrt_price_x96(sqrt_price_x96))
+        .or_else(|| pool.map(|pool| pool.price()))
+        .unwrap_or(0.0);
+    Ok(HyperionPoolHourlyStat {
+        pool_address: pool_address.to_string(),
+        bucket_start,
+        volume_usd: volume_usd.to_string(),
+        fees_usd: fees_usd.to_string(),
+        swap_count: swaps.len() as i64,
+        close_price: close_price.to_string(),
+        volume0: volume0.to_string(),
+        volume1: volume1.to_string(),
+    })
+}
+
+/// Percent change between the current price and the close of the last bucket that ended
+/// at least `window_secs` ago, or 0 if the pool has no price history that old.
+async fn compute_price_change(
+    conn: &mut AsyncPgConnection,
+    pool_address: &str,
+    chain_timestamp: i64,
+    window_secs: i64,
+    current_price: f64,
+) -> QueryResult<f64> {
+    let reference_price = hyperion_pool_hourly_stats::table
+        .filter(hyperion_pool_hourly_stats::pool_address.eq(pool_address))
+        .filter(
+            hyperion_pool_hourly_stats::bucket_start
+                .le(last_bucket_ended_before(chain_timestamp, window_secs)),
+        )
+        .filter(hyperion_pool_hourly_stats::close_price.ne("0"))
+        .order(hyperion_pool_hourly_stats::bucket_start.desc())
+        .select(hyperion_pool_hourly_stats::close_price)
+        .first::<String>(conn)
+        .await
+        .optional()?
+        .and_then(|price| price.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    if reference_price <= 0.0 || current_price <= 0.0 {
+        return Ok(0.0);
+    }
+    Ok((current_price / reference_price - 1.0) * 100.0)
+}
+
+pub async fn execute_hyperion_pool_stats_sql(
+    conn: &mut AsyncPgConnection,
+    mut pool_addresses: Vec<String>,
+    touched_buckets: Vec<(String, i64)>,
+    chain_timestamp: i64,
+    exclude_wash_volume: bool,
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            // The windows roll forward every hour, also for pools without new swaps. Their
+            // stats are refreshed once the hour of their last refresh is over, oldest first.
+            let stale_pools = hyperion_pool_stats::table
+                .filter(hyperion_pool_stats::last_update_timestamp.lt(hour_bucket(chain_timestamp)))
+                .order(hyperion_pool_stats::last_update_timestamp.asc())
+                .limit(STALE_POOLS_PER_BATCH)
+                .select(hyperion_pool_stats::pool_address)
+                .load::<String>(conn)
+                .await?;
+            pool_addresses.extend(stale_pools);
+            pool_addresses.sort();
+            pool_addresses.dedup();
+            if pool_addresses.is_empty() {
+                return Ok(());
+            }
+
+            let pools = hyperion_pools::table
+                .filter(hyperion_pools::pool_address.eq_any(pool_addresses.clone()))
+                .load::<HyperionPool>(conn)
+                .await?
+                .into_iter()
+                .map(|pool| (pool.pool_address.clone(), pool))
+                .collect::<AHashMap<_, _>>();
+
+            // Buckets are recomputed from the swaps rather than incremented, so the windows
+            // stay exact across restarts and re-processed ranges.
+            let mut hourly_stats = vec![];
+            for (pool_address, bucket_start) in touched_buckets {
+                let pool = pools.get(&pool_address);
+                hourly_stats.push(
+                    compute_hourly_stat(
+                        conn,
+                        &pool_address,
+                        bucket_start,
+                        pool,
+                        exclude_wash_volume,
+                    )
+                    .await?,
+                );
+            }
+            insert_into(hyperion_pool_hourly_stats::table)
+                .values(hourly_stats)
+                .on_conflict((
+                    hyperion_pool_hourly_stats::pool_address,
+                    hyperion_pool_hourly_stats::bucket_start,
+                ))
+                .do_update()
+                .set((
+                    hyperion_pool_hourly_stats::volume_usd
+                        .eq(excluded(hyperion_pool_hourly_stats::volume_usd)),
+                    hyperion_pool_hourly_stats::fees_usd
+                        .eq(excluded(hyperion_pool_hourly_stats::fees_usd)),
+                    hyperion_pool_hourly_stats::swap_count
+                        .eq(excluded(hyperion_pool_hourly_stats::swap_count)),
+                    hyperion_pool_hourly_stats::close_price
+                        .eq(excluded(hyperion_pool_hourly_stats::close_price)),
+                    hyperion_pool_hourly_stats::volume0
+                        .eq(excluded(hyperion_pool_hourly_stats::volume0)),
+                    hyperion_pool_hourly_stats::volume1
+                        .eq(excluded(hyperion_pool_hourly_stats::volume1)),
+                ))
+                .execute(conn)
+                .await?;
+
+            let tokens = pools
+                .values()
+                .flat_map(|pool| [pool.token0_address.clone(), pool.token1_address.clone()])
+                .collect::<Vec<_>>();
+            let usd_prices = TokenPrice::get_usd_prices(conn, tokens).await?;
+            let usd_price = |token: &str| {
+                usd_prices
+                    .get(&standardize_address(token))
+                    .copied()
+                    .unwrap_or(0.0)
+            };
+
+            let start_24h = window_start_bucket(chain_timestamp, WINDOW_24H_SECS);
+            let start_7d = window_start_bucket(chain_timestamp, WINDOW_7D_SECS);
+
+            let mut snapshots = vec![];
+            for pool_address in pool_addresses {
+                let mut stat = hyperion_pool_stats::table
+                    .find(&pool_address)
+                    .first::<HyperionPoolStat>(conn)
+                    .await
+                    .optional()?
+                    .unwrap_or_else(|| HyperionPoolStat::new(pool_address.clone()));
+                // A newer batch already refreshed the windows, as can happen when ranges are
+                // written out of order. Only the buckets of this one are rebuilt.
+                if stat.last_update_timestamp > chain_timestamp {
+                    continue;
+                }
+
+                let buckets = hyperion_pool_hourly_stats::table
+                    .filter(hyperion_pool_hourly_stats::pool_address.eq(&pool_address))
+                    .filter(hyperion_pool_hourly_stats::bucket_start.ge(start_7d))
+                    .load::<HyperionPoolHourlyStat>(conn)
+                    .await?;
+                let (mut volume_24h, mut fees_24h, mut swap_count_24h) = (0.0, 0.0, 0);
+                let (mut volume_7d, mut fees_7d, mut swap_count_7d) = (0.0, 0.0, 0);
+                for bucket in buckets {
+                    let volume = bucket.volume_usd.parse::<f64>().unwrap_or(0.0);
+                    let fees = bucket.fees_usd.parse::<f64>().unwrap_or(0.0);
+                    volume_7d += volume;
+                    fees_7d += fees;
+                    swap_count_7d += bucket.swap_count;
+                    if bucket.bucket_start >= start_24h {
+                        volume_24h += volume;
+                        fees_24h += fees;
+                        swap_count_24h += bucket.swap_count;
+                    }
+                }
+                stat.volume_24h = volume_24h.to_string();
+                stat.volume_7d = volume_7d.to_string();
+                stat.fees_24h = fees_24h.to_string();
+                stat.fees_7d = fees_7d.to_string();
+                stat.swap_count_24h = swap_count_24h;
+                stat.swap_count_7d = swap_count_7d;
+
+                stat.unique_traders_24h =
+                    count_unique_traders(conn, &pool_address, start_24h).await?;
+                stat.unique_traders_7d =
+                    count_unique_traders(conn, &pool_address, start_7d).await?;
+
+                if let Some(pool) = pools.get(&pool_address) {
+                    let (reserve0, reserve1) = pool.virtual_reserves();
+                    let tvl_usd = reserve0 * usd_price(&pool.token0_address)
+                        + reserve1 * usd_price(&pool.token1_address);
+                    stat.reserve0 = reserve0.to_string();
+                    stat.reserve1 = reserve1.to_string();
+                    stat.tvl_usd = tvl_usd.to_string();
+                    stat.calculate_apr(tvl_usd);
+                    snapshots.push(HyperionPoolLiquiditySnapshot {
+                        pool_address: pool_address.clone(),
+                        timestamp: chain_timestamp,
+                        liquidity: pool.liquidity.clone(),
+                        tvl_usd: tvl_usd.to_string(),
+                    });
+
+                    let current_price = pool.price();
+                    stat.price_change_1h = compute_price_change(
+                        conn,
+                        &pool_address,
+                        chain_timestamp,
+                        SECONDS_PER_HOUR,
+                        current_price,
+                    )
+                    .await?
+                    .to_string();
+                    stat.price_change_24h = compute_price_change(
+                        conn,
+                        &pool_address,
+                        chain_timestamp,
+                        WINDOW_24H_SECS,
+                        current_price,
+                    )
+                    .await?
+                    .to_string();
+                    stat.price_change_7d = compute_price_change(
+                        conn,
+                        &pool_address,
+                        chain_timestamp,
+                        WINDOW_7D_SECS,
+                        current_price,
+                    )
+                    .await?
+                    .to_string();
+                }
+                stat.last_update_timestamp = stat.last_update_timestamp.max(chain_timestamp);
+
+                insert_into(hyperion_pool_stats::table)
+                    .values(&stat)
+                    .on_conflict(hyperion_pool_stats::pool_address)
+                    .do_update()
+                    .set(&stat)
+                    .execute(conn)
+                    .await?;
+            }
+
+            insert_into(hyperion_pool_liquidity_snapshots::table)
+                .values(snapshots)
+                .on_conflict((
+                    hyperion_pool_liquidity_snapshots::pool_address,
+                    hyperion_pool_liquidity_snapshots::timestamp,
+                ))
+                .do_update()
+                .set((
+                    hyperion_pool_liquidity_snapshots::liquidity
+                        .eq(excluded(hyperion_pool_liquidity_snapshots::liquidity)),
+                    hyperion_pool_liquidity_snapshots::tvl_usd
+                        .eq(excluded(hyperion_pool_liquidity_snapshots::tvl_usd)),
+                ))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Rebuilds the hourly buckets touched by the batch, then refreshes the rolling windows,
+/// reserves, TVL and APR of `hyperion_pool_stats` for the given pools and for the pools last
+/// refreshed in an earlier hour, using the chain time of the batch as "now". Also snapshots
+/// the liquidity of each pool at that time.
+pub async fn process_hyperion_pool_stats(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    pool_addresses: Vec<String>,
+    touched_buckets: Vec<(String, i64)>,
+    chain_timestamp: i64,
+    exclude_wash_volume: bool,
+) -> Result<(), ProcessorError> {
+    with_query_retries(&pool, retry_policy, "hyperion_pool_stats", |conn| {
+        Box::pin(execute_hyperion_pool_stats_sql(
+            conn,
+            pool_addresses.clone(),
+            touched_buckets.clone(),
+            chain_timestamp,
+            exclude_wash_volume,
+        ))
+    })
+    .await
+}
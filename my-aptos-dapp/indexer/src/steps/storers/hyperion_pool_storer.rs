@@ -1,15 +1,17 @@
 use ahash::AHashMap;
 use anyhow::Result;
 use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
-use diesel::{insert_into, upsert::excluded, ExpressionMethods, QueryResult};
+use diesel::{
+    insert_into, query_dsl::methods::FilterDsl, upsert::excluded, ExpressionMethods, QueryResult,
+};
 use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 
 use crate::{
     db_models::hyperion_pool::HyperionPool,
     db_migrations::schema::hyperion_pools,
     utils::{
-        database_connection::get_db_connection,
         database_utils::{get_config_table_chunk_size, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
@@ -29,7 +31,9 @@ async fn execute_hyperion_pool_sql(
                     hyperion_pools::tick.eq(excluded(hyperion_pools::tick)),
                     hyperion_pools::last_update_timestamp.eq(excluded(hyperion_pools::last_update_timestamp)),
                     hyperion_pools::last_update_version.eq(excluded(hyperion_pools::last_update_version)),
-                ));
+                ))
+                // Ranges can be written out of order, keep the latest state
+                .filter(hyperion_pools::last_update_version.le(excluded(hyperion_pools::last_update_version)));
             query.execute(conn).await?;
             Ok(())
         })
@@ -39,6 +43,7 @@ async fn execute_hyperion_pool_sql(
 
 pub async fn process_hyperion_pool_events(
     pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
     per_table_chunk_sizes: AHashMap<String, usize>,
     pools: Vec<HyperionPool>,
 ) -> Result<(), ProcessorError> {
@@ -53,24 +58,33 @@ pub async fn process_hyperion_pool_events(
             let pool = pool.clone();
             let items = chunk.to_vec();
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing hyperion pool events",
-                );
-                execute_hyperion_pool_sql(conn, items).await
+                with_query_retries(&pool, retry_policy, "hyperion_pools", |conn| {
+                    Box::pin(execute_hyperion_pool_sql(conn, items.clone()))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::warn!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(())
+}
+
+/// `process_hyperion_pool_events` on a single connection, one chunk after the other.
+pub async fn store_hyperion_pool_events(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    pools: Vec<HyperionPool>,
+) -> QueryResult<()> {
+    let chunk_size =
+        get_config_table_chunk_size::<HyperionPool>("hyperion_pools", per_table_chunk_sizes);
+    for chunk in pools.chunks(chunk_size) {
+        execute_hyperion_pool_sql(conn, chunk.to_vec()).await?;
     }
     Ok(())
 }
@@ -1,79 +1,249 @@
 use ahash::AHashMap;
 use anyhow::Result;
 use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
-use diesel::{insert_into, upsert::excluded, ExpressionMethods, QueryResult};
+use diesel::{
+    insert_into, upsert::excluded, BoolExpressionMethods, ExpressionMethods, OptionalExtension,
+    QueryDsl, QueryResult,
+};
 use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 
 use crate::{
-    db_models::{hyperion_swap::HyperionSwap, hyperion_pool_stat::HyperionPoolStat},
-    db_migrations::schema::{hyperion_swaps, hyperion_pool_stats},
+    db_models::{
+        hyperion_pool::HyperionPool, hyperion_pool_stat::HyperionPoolStat,
+        hyperion_pool_trader::HyperionPoolHourlyTrader, hyperion_swap::HyperionSwap,
+        token_price::TokenPriceHistory,
+    },
+    schema::{hyperion_pool_hourly_traders, hyperion_pool_stats, hyperion_pools, hyperion_swaps},
     utils::{
-        database_connection::get_db_connection,
         database_utils::{get_config_table_chunk_size, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
+/// State of a pool right before a swap, as needed to value and execute it.
+struct PoolStateBefore {
+    token0_address: String,
+    sqrt_price_x96: String,
+    // In hundredths of a basis point, e.g. 3000 = 0.3%
+    fee_tier: i32,
+}
+
+/// Token0, fee tier and `sqrt_price_x96` of each pool right before the given swap, its first
+/// swap in the batch. The previous indexed swap is authoritative, the pool row is only trusted
+/// when it has not moved past the swap yet, e.g. for the first swap of a freshly created pool.
+async fn load_pool_states_before(
+    conn: &mut AsyncPgConnection,
+    first_swaps: Vec<&HyperionSwap>,
+) -> QueryResult<AHashMap<String, PoolStateBefore>> {
+    let mut states = AHashMap::new();
+    for swap in first_swaps {
+        let Some(pool) = hyperion_pools::table
+            .find(&swap.pool_address)
+            .first::<HyperionPool>(conn)
+            .await
+            .optional()?
+        else {
+            continue;
+        };
+        let previous_sqrt_price = hyperion_swaps::table
+            .filter(hyperion_swaps::pool_address.eq(&swap.pool_address))
+            .filter(
+                hyperion_swaps::tx_version
+                    .lt(swap.tx_version)
+                    .or(hyperion_swaps::tx_version
+                        .eq(swap.tx_version)
+                        .and(hyperion_swaps::event_idx.lt(swap.event_idx))),
+            )
+            .order((
+                hyperion_swaps::tx_version.desc(),
+                hyperion_swaps::event_idx.desc(),
+            ))
+            .select(hyperion_swaps::sqrt_price_x96_after)
+            .first::<String>(conn)
+            .await
+            .optional()?;
+        let sqrt_price_before = previous_sqrt_price
+            .or((pool.last_update_version < swap.tx_version).then_some(pool.sqrt_price_x96))
+            .unwrap_or_else(|| "0".to_string());
+        states.insert(
+            swap.pool_address.clone(),
+            PoolStateBefore {
+                token0_address: pool.token0_address,
+                sqrt_price_x96: sqrt_price_before,
+                fee_tier: pool.fee_tier,
+            },
+        );
+    }
+    Ok(states)
+}
+
 async fn execute_hyperion_swap_sql(
     conn: &mut AsyncPgConnection,
     items_to_insert: Vec<HyperionSwap>,
     pool_stats_updates: Vec<HyperionPoolStat>,
+    pool_state_updates: Vec<HyperionSwap>,
 ) -> QueryResult<()> {
     conn.transaction(|conn| {
         Box::pin(async move {
+            // Distinct swappers per pool and hour. Duplicates collapse on the primary key,
+            // so replaying a range leaves the unique trader counts untouched.
+            let hourly_traders = items_to_insert
+                .iter()
+                .map(|swap| {
+                    HyperionPoolHourlyTrader::new(
+                        swap.pool_address.clone(),
+                        swap.sender.clone(),
+                        swap.timestamp,
+                    )
+                })
+                .collect::<Vec<_>>();
+
             let swap_query = insert_into(hyperion_swaps::table)
                 .values(items_to_insert)
                 .on_conflict(hyperion_swaps::swap_id)
                 .do_nothing();
             swap_query.execute(conn).await?;
 
-            let stats_query = insert_into(hyperion_pool_stats::table)
+            let hourly_traders_query = insert_into(hyperion_pool_hourly_traders::table)
+                .values(hourly_traders)
+                .on_conflict_do_nothing();
+            hourly_traders_query.execute(conn).await?;
+
+            let stats_upsert = insert_into(hyperion_pool_stats::table)
                 .values(pool_stats_updates)
                 .on_conflict(hyperion_pool_stats::pool_address)
                 .do_update()
+                // Rolling windows, TVL and APR are owned by the pool stats storer
                 .set((
-                    hyperion_pool_stats::volume_24h.eq(excluded(hyperion_pool_stats::volume_24h)),
-                    hyperion_pool_stats::fees_24h.eq(excluded(hyperion_pool_stats::fees_24h)),
-                    hyperion_pool_stats::swap_count_24h.eq(excluded(hyperion_pool_stats::swap_count_24h)),
                     hyperion_pool_stats::last_price.eq(excluded(hyperion_pool_stats::last_price)),
                     hyperion_pool_stats::last_update_timestamp.eq(excluded(hyperion_pool_stats::last_update_timestamp)),
                 ));
+            // Ranges can be written out of order, keep the latest price
+            let stats_query = diesel::query_dsl::methods::FilterDsl::filter(
+                stats_upsert,
+                hyperion_pool_stats::last_update_timestamp
+                    .le(excluded(hyperion_pool_stats::last_update_timestamp)),
+            );
             stats_query.execute(conn).await?;
 
+            // Swaps move the pool price and active liquidity, keep the pool state current
+            for swap in pool_state_updates {
+                diesel::update(
+                    hyperion_pools::table
+                        .filter(hyperion_pools::pool_address.eq(&swap.pool_address))
+                        .filter(hyperion_pools::last_update_version.le(swap.tx_version)),
+                )
+                .set((
+                    hyperion_pools::liquidity.eq(&swap.liquidity_after),
+                    hyperion_pools::sqrt_price_x96.eq(&swap.sqrt_price_x96_after),
+                    hyperion_pools::tick.eq(swap.tick_after),
+                    hyperion_pools::last_update_timestamp.eq(swap.timestamp),
+                    hyperion_pools::last_update_version.eq(swap.tx_version),
+                ))
+                .execute(conn)
+                .await?;
+            }
+
             Ok(())
         })
     })
     .await
 }
 
-pub async fn process_hyperion_swap_events(
-    pool: ArcDbPool,
-    per_table_chunk_sizes: AHashMap<String, usize>,
-    swaps: Vec<HyperionSwap>,
-) -> Result<(), ProcessorError> {
-    if swaps.is_empty() {
-        return Ok(());
+/// Values the swaps in USD and fills in their execution, reading prices and pool states
+/// through `conn`. Returns the swaps, the pool stats they update and the latest swap of each
+/// pool, whose post-swap state becomes the pool's current state.
+async fn prepare_swaps(
+    conn: &mut AsyncPgConnection,
+    mut swaps: Vec<HyperionSwap>,
+) -> QueryResult<(Vec<HyperionSwap>, Vec<HyperionPoolStat>, Vec<HyperionSwap>)> {
+    // Swaps are replayed in chain order so each one sees the price left by the previous one
+    swaps.sort_by_key(|swap| (swap.tx_version, swap.event_idx));
+    let mut first_swaps: AHashMap<&str, &HyperionSwap> = AHashMap::new();
+    for swap in &swaps {
+        first_swaps.entry(&swap.pool_address).or_insert(swap);
     }
 
+    // Value every swap in USD with the prices routed as of its own time, so replayed and
+    // backfilled swaps are not valued at today's prices
+    let tokens = swaps
+        .iter()
+        .flat_map(|swap| [swap.token_in.clone(), swap.token_out.clone()])
+        .collect::<Vec<_>>();
+    let first_timestamp = swaps
+        .iter()
+        .map(|swap| swap.timestamp)
+        .min()
+        .unwrap_or_default();
+    let last_timestamp = swaps
+        .iter()
+        .map(|swap| swap.timestamp)
+        .max()
+        .unwrap_or_default();
+    let price_histories =
+        TokenPriceHistory::get_usd_price_history(conn, tokens, first_timestamp, last_timestamp)
+            .await?;
+    let mut states_before =
+        load_pool_states_before(conn, first_swaps.into_values().collect()).await?;
+
     let mut pool_stats_map: AHashMap<String, HyperionPoolStat> = AHashMap::new();
     let swaps_vec: Vec<HyperionSwap> = swaps
         .into_iter()
-        .map(|swap| {
+        .map(|mut swap| {
+            swap.amount_usd = swap.value_usd(&price_histories).to_string();
+            let state = states_before.get_mut(&swap.pool_address);
+            let fee_tier = state.as_ref().map(|state| state.fee_tier).unwrap_or(3000);
+            if let Some(state) = state {
+                swap.set_execution(&state.token0_address, &state.sqrt_price_x96);
+                state.sqrt_price_x96 = swap.sqrt_price_x96_after.clone();
+            }
+
             let stat = pool_stats_map
                 .entry(swap.pool_address.clone())
                 .or_insert_with(|| HyperionPoolStat::new(swap.pool_address.clone()));
 
-            stat.update_from_swap(
-                &swap.amount_in,
-                &swap.amount_out,
-                3000, // default fee tier 0.3%
-                swap.timestamp,
-            );
+            stat.update_from_swap(&swap.amount_in, &swap.amount_out, fee_tier, swap.timestamp);
             swap
         })
         .collect();
 
     let pool_stats: Vec<HyperionPoolStat> = pool_stats_map.into_values().collect();
 
+    // Latest swap per pool, its post-swap state becomes the pool's current state
+    let mut latest_swaps: AHashMap<String, HyperionSwap> = AHashMap::new();
+    for swap in swaps_vec.iter() {
+        latest_swaps
+            .entry(swap.pool_address.clone())
+            .and_modify(|existing| {
+                if (swap.tx_version, swap.event_idx) > (existing.tx_version, existing.event_idx) {
+                    *existing = swap.clone();
+                }
+            })
+            .or_insert_with(|| swap.clone());
+    }
+    let pool_states: Vec<HyperionSwap> = latest_swaps.into_values().collect();
+
+    Ok((swaps_vec, pool_stats, pool_states))
+}
+
+/// Stores the swaps and returns them in chain order, valued in USD and with their execution
+/// filled in, for the writes derived from them.
+pub async fn process_hyperion_swap_events(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+    swaps: Vec<HyperionSwap>,
+) -> Result<Vec<HyperionSwap>, ProcessorError> {
+    if swaps.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (swaps_vec, pool_stats, pool_states) =
+        with_query_retries(&pool, retry_policy, "prepare_hyperion_swaps", |conn| {
+            Box::pin(prepare_swaps(conn, swaps.clone()))
+        })
+        .await?;
+
     let chunk_size = get_config_table_chunk_size::<HyperionSwap>("hyperion_swaps", &per_table_chunk_sizes);
     let tasks = swaps_vec
         .chunks(chunk_size)
@@ -81,30 +251,54 @@ pub async fn process_hyperion_swap_events(
         .map(|(i, chunk)| {
             let pool = pool.clone();
             let items = chunk.to_vec();
-            let stats = if i == 0 {
-                pool_stats.clone()
+            let (stats, states) = if i == 0 {
+                (pool_stats.clone(), pool_states.clone())
             } else {
-                vec![]
+                (vec![], vec![])
             };
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing hyperion swap events",
-                );
-                execute_hyperion_swap_sql(conn, items, stats).await
+                with_query_retries(&pool, retry_policy, "hyperion_swaps", |conn| {
+                    Box::pin(execute_hyperion_swap_sql(
+                        conn,
+                        items.clone(),
+                        stats.clone(),
+                        states.clone(),
+                    ))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::warn!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(swaps_vec)
+}
+
+/// `process_hyperion_swap_events` on a single connection, one chunk after the other.
+pub async fn store_hyperion_swap_events(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    swaps: Vec<HyperionSwap>,
+) -> QueryResult<Vec<HyperionSwap>> {
+    if swaps.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (swaps_vec, pool_stats, pool_states) = prepare_swaps(conn, swaps).await?;
+    let chunk_size =
+        get_config_table_chunk_size::<HyperionSwap>("hyperion_swaps", per_table_chunk_sizes);
+    for (i, chunk) in swaps_vec.chunks(chunk_size).enumerate() {
+        let (stats, states) = if i == 0 {
+            (pool_stats.clone(), pool_states.clone())
+        } else {
+            (vec![], vec![])
+        };
+        execute_hyperion_swap_sql(conn, chunk.to_vec(), stats, states).await?;
     }
-    Ok(())
+    Ok(swaps_vec)
 }
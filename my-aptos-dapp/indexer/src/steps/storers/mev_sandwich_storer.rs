@@ -0,0 +1,74 @@
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
+use diesel::{insert_into, ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    db_models::{hyperion_swap::HyperionSwap, mev_sandwich::detect_sandwiches},
+    schema::{hyperion_swaps, mev_sandwiches},
+    utils::{
+        database_utils::ArcDbPool,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+/// `swaps` are the batch's swaps in chain order, as prepared by the swap storer, so they carry
+/// the direction and pre-swap price it derived.
+pub async fn execute_mev_sandwiches_sql(
+    conn: &mut AsyncPgConnection,
+    swaps: Vec<HyperionSwap>,
+    start_version: i64,
+    max_version_gap: i64,
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            // Only the lookback is read back, it catches sandwiches whose front-run was in an
+            // earlier batch
+            let mut window = hyperion_swaps::table
+                .filter(hyperion_swaps::tx_version.ge(start_version - max_version_gap))
+                .filter(hyperion_swaps::tx_version.lt(start_version))
+                .order((hyperion_swaps::tx_version, hyperion_swaps::event_idx))
+                .load::<HyperionSwap>(conn)
+                .await?;
+            window.extend(swaps);
+            let sandwiches = detect_sandwiches(&window, max_version_gap)
+                .into_iter()
+                .filter(|sandwich| sandwich.back_run_version >= start_version)
+                .collect::<Vec<_>>();
+            if sandwiches.is_empty() {
+                return Ok(());
+            }
+            insert_into(mev_sandwiches::table)
+                .values(sandwiches)
+                .on_conflict(mev_sandwiches::victim_swap_id)
+                .do_nothing()
+                .execute(conn)
+                .await?;
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Detects sandwiches completed by the Hyperion swaps of the batch, starting at
+/// `start_version`, and records them in `mev_sandwiches`.
+pub async fn process_mev_sandwiches(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    swaps: Vec<HyperionSwap>,
+    start_version: i64,
+    max_version_gap: i64,
+) -> Result<(), ProcessorError> {
+    if swaps.is_empty() {
+        return Ok(());
+    }
+    with_query_retries(&pool, retry_policy, "mev_sandwiches", |conn| {
+        Box::pin(execute_mev_sandwiches_sql(
+            conn,
+            swaps.clone(),
+            start_version,
+            max_version_gap,
+        ))
+    })
+    .await
+}
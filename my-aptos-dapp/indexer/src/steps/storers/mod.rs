@@ -8,3 +8,11 @@ pub mod complete_trade_event_storer;
 pub mod cancel_trade_event_storer;
 pub mod hyperion_pool_storer;
 pub mod hyperion_swap_storer;
+pub mod hyperion_pool_stats_storer;
+pub mod token_price_storer;
+pub mod token_stats_storer;
+pub mod trader_position_storer;
+pub mod alert_storer;
+pub mod mev_sandwich_storer;
+pub mod swap_route_storer;
+pub mod wash_trading_storer;
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
+use diesel::{insert_into, QueryResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    db_models::{hyperion_swap::HyperionSwap, swap_route::build_routes},
+    schema::swap_routes,
+    utils::{
+        database_utils::ArcDbPool,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+/// `swaps` are the batch's swaps in chain order, as prepared by the swap storer.
+pub async fn execute_swap_routes_sql(
+    conn: &mut AsyncPgConnection,
+    swaps: Vec<HyperionSwap>,
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            // A transaction never spans batches, so the batch holds every hop
+            let routes = build_routes(&swaps);
+            if routes.is_empty() {
+                return Ok(());
+            }
+            insert_into(swap_routes::table)
+                .values(routes)
+                .on_conflict(swap_routes::route_id)
+                .do_nothing()
+                .execute(conn)
+                .await?;
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Reconstructs the multi-hop routes of the batch's swaps into `swap_routes`.
+pub async fn process_swap_routes(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    swaps: Vec<HyperionSwap>,
+) -> Result<(), ProcessorError> {
+    if swaps.is_empty() {
+        return Ok(());
+    }
+    with_query_retries(&pool, retry_policy, "swap_routes", |conn| {
+        Box::pin(execute_swap_routes_sql(conn, swaps.clone()))
+    })
+    .await
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
+use diesel::{
+    insert_into, upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl, QueryResult,
+};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    config::indexer_processor_config::PricingConfig,
+    db_models::{
+        hyperion_pool::HyperionPool,
+        token_price::{TokenPrice, TokenPriceHistory},
+    },
+    schema::{hyperion_pools, hyperion_swaps, token_price_history, token_prices},
+    utils::{
+        database_utils::ArcDbPool,
+        price_router::route_token_prices,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+/// Pools as they were at `end_version`. When the batch is behind the pool rows, e.g. a
+/// replayed or backfilled range, a pool takes the state left by its last swap up to that
+/// version. Pools without one are left out rather than priced with their later state.
+async fn load_pools_as_of(
+    conn: &mut AsyncPgConnection,
+    end_version: i64,
+) -> QueryResult<Vec<HyperionPool>> {
+    let mut pools = vec![];
+    for mut pool in hyperion_pools::table.load::<HyperionPool>(conn).await? {
+        if pool.last_update_version <= end_version {
+            pools.push(pool);
+            continue;
+        }
+        let state = hyperion_swaps::table
+            .filter(hyperion_swaps::pool_address.eq(&pool.pool_address))
+            .filter(hyperion_swaps::tx_version.le(end_version))
+            .order((
+                hyperion_swaps::tx_version.desc(),
+                hyperion_swaps::event_idx.desc(),
+            ))
+            .select((
+                hyperion_swaps::sqrt_price_x96_after,
+                hyperion_swaps::liquidity_after,
+                hyperion_swaps::tick_after,
+                hyperion_swaps::timestamp,
+                hyperion_swaps::tx_version,
+            ))
+            .first::<(String, String, i32, i64, i64)>(conn)
+            .await
+            .optional()?;
+        if let Some((sqrt_price_x96, liquidity, tick, timestamp, tx_version)) = state {
+            pool.sqrt_price_x96 = sqrt_price_x96;
+            pool.liquidity = liquidity;
+            pool.tick = tick;
+            pool.last_update_timestamp = timestamp;
+            pool.last_update_version = tx_version;
+            pools.push(pool);
+        }
+    }
+    Ok(pools)
+}
+
+pub async fn execute_token_prices_sql(
+    conn: &mut AsyncPgConnection,
+    pricing_config: PricingConfig,
+    chain_timestamp: i64,
+    end_version: i64,
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            // Routing reads every pool, so it runs once per refresh interval of chain time.
+            // Ranges written out of order are routed on their own, swaps look prices up by time.
+            let routed_recently = token_price_history::table
+                .filter(
+                    token_price_history::timestamp
+                        .gt(chain_timestamp - pricing_config.refresh_interval_secs),
+                )
+                .filter(token_price_history::timestamp.le(chain_timestamp))
+                .select(token_price_history::timestamp)
+                .first::<i64>(conn)
+                .await
+                .optional()?
+                .is_some();
+            if routed_recently {
+                return Ok(());
+            }
+
+            let pools = load_pools_as_of(conn, end_version).await?;
+            let prices = route_token_prices(&pools, &pricing_config)
+                .into_iter()
+                .map(|price| TokenPrice {
+                    token_address: price.token_address,
+                    price_usd: price.price_usd.to_string(),
+                    liquidity_usd: price.liquidity_usd.to_string(),
+                    anchor_token: price.anchor_token,
+                    hops: price.path.len() as i32,
+                    path: price.path.join(","),
+                    timestamp: chain_timestamp,
+                })
+                .collect::<Vec<_>>();
+
+            let history_query = insert_into(token_price_history::table)
+                .values(
+                    prices
+                        .iter()
+                        .map(TokenPriceHistory::from)
+                        .collect::<Vec<_>>(),
+                )
+                .on_conflict_do_nothing();
+            history_query.execute(conn).await?;
+
+            let upsert = insert_into(token_prices::table)
+                .values(prices)
+                .on_conflict(token_prices::token_address)
+                .do_update()
+                .set((
+                    token_prices::price_usd.eq(excluded(token_prices::price_usd)),
+                    token_prices::liquidity_usd.eq(excluded(token_prices::liquidity_usd)),
+                    token_prices::anchor_token.eq(excluded(token_prices::anchor_token)),
+                    token_prices::path.eq(excluded(token_prices::path)),
+                    token_prices::hops.eq(excluded(token_prices::hops)),
+                    token_prices::timestamp.eq(excluded(token_prices::timestamp)),
+                ));
+            // Never let an older batch overwrite a newer price
+            let query = diesel::query_dsl::methods::FilterDsl::filter(
+                upsert,
+                token_prices::timestamp.le(excluded(token_prices::timestamp)),
+            );
+            query.execute(conn).await?;
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Re-routes USD prices for every token across the indexed pools as of `end_version`, adds
+/// them to `token_price_history` and stores the latest price of each token in
+/// `token_prices`, unless prices were routed less than `refresh_interval_secs` of chain time
+/// before this batch.
+pub async fn process_token_prices(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    pricing_config: PricingConfig,
+    chain_timestamp: i64,
+    end_version: i64,
+) -> Result<(), ProcessorError> {
+    with_query_retries(&pool, retry_policy, "token_prices", |conn| {
+        Box::pin(execute_token_prices_sql(
+            conn,
+            pricing_config.clone(),
+            chain_timestamp,
+            end_version,
+        ))
+    })
+    .await
+}
@@ -0,0 +1,196 @@
+use ahash::AHashMap;
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::{convert::standardize_address, errors::ProcessorError};
+use diesel::{
+    insert_into, upsert::excluded, BoolExpressionMethods, ExpressionMethods, QueryDsl, QueryResult,
+};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    db_models::{
+        hyperion_pool::HyperionPool,
+        hyperion_pool_hourly_stat::HyperionPoolHourlyStat,
+        hyperion_pool_stat::{window_start_bucket, WINDOW_24H_SECS, WINDOW_7D_SECS},
+        token_price::TokenPrice,
+        token_stat::TokenStat,
+    },
+    schema::{hyperion_pool_hourly_stats, hyperion_pools, token_stats},
+    utils::{
+        database_utils::ArcDbPool,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+pub async fn execute_token_stats_sql(
+    conn: &mut AsyncPgConnection,
+    pool_addresses: Vec<String>,
+    chain_timestamp: i64,
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            let touched_tokens = hyperion_pools::table
+                .filter(hyperion_pools::pool_address.eq_any(pool_addresses))
+                .select((
+                    hyperion_pools::token0_address,
+                    hyperion_pools::token1_address,
+                ))
+                .load::<(String, String)>(conn)
+                .await?
+                .into_iter()
+                .flat_map(|(token0, token1)| [token0, token1])
+                .collect::<Vec<_>>();
+            if touched_tokens.is_empty() {
+                return Ok(());
+            }
+
+            // Every pool containing a touched token, not only the touched pools
+            let pools = hyperion_pools::table
+                .filter(
+                    hyperion_pools::token0_address
+                        .eq_any(touched_tokens.clone())
+                        .or(hyperion_pools::token1_address.eq_any(touched_tokens.clone())),
+                )
+                .load::<HyperionPool>(conn)
+                .await?;
+
+            let start_24h = window_start_bucket(chain_timestamp, WINDOW_24H_SECS);
+            let start_7d = window_start_bucket(chain_timestamp, WINDOW_7D_SECS);
+            let mut buckets_by_pool: AHashMap<String, Vec<HyperionPoolHourlyStat>> =
+                AHashMap::new();
+            for bucket in hyperion_pool_hourly_stats::table
+                .filter(
+                    hyperion_pool_hourly_stats::pool_address.eq_any(
+                        pools
+                            .iter()
+                            .map(|pool| pool.pool_address.clone())
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+                .filter(hyperion_pool_hourly_stats::bucket_start.ge(start_7d))
+                .load::<HyperionPoolHourlyStat>(conn)
+                .await?
+            {
+                buckets_by_pool
+                    .entry(bucket.pool_address.clone())
+                    .or_default()
+                    .push(bucket);
+            }
+
+            let usd_prices = TokenPrice::get_usd_prices(conn, touched_tokens.clone()).await?;
+
+            // (pool, whether the token is token0) for every pool of every touched token
+            let mut pools_by_token: AHashMap<String, Vec<(&HyperionPool, bool)>> = AHashMap::new();
+            for token in &touched_tokens {
+                pools_by_token
+                    .entry(standardize_address(token))
+                    .or_default();
+            }
+            for pool in &pools {
+                for (token, is_token0) in
+                    [(&pool.token0_address, true), (&pool.token1_address, false)]
+                {
+                    if let Some(token_pools) = pools_by_token.get_mut(&standardize_address(token)) {
+                        token_pools.push((pool, is_token0));
+                    }
+                }
+            }
+
+            let mut stats = vec![];
+            for (token_address, token_pools) in pools_by_token {
+                let (mut liquidity, mut best_reserve, mut best_pool_address) = (0.0, -1.0, "");
+                let (mut volume_24h, mut volume_usd_24h, mut swap_count_24h) = (0.0, 0.0, 0);
+                let (mut volume_7d, mut volume_usd_7d, mut swap_count_7d) = (0.0, 0.0, 0);
+                for (pool, is_token0) in &token_pools {
+                    let (reserve0, reserve1) = pool.virtual_reserves();
+                    let reserve = if *is_token0 { reserve0 } else { reserve1 };
+                    liquidity += reserve;
+                    // The same token is priced identically in every pool, so the deepest pool
+                    // in token units is also the deepest in USD
+                    if reserve > best_reserve {
+                        (best_reserve, best_pool_address) = (reserve, pool.pool_address.as_str());
+                    }
+
+                    for bucket in buckets_by_pool
+                        .get(&pool.pool_address)
+                        .into_iter()
+                        .flatten()
+                    {
+                        let volume = if *is_token0 {
+                            &bucket.volume0
+                        } else {
+                            &bucket.volume1
+                        }
+                        .parse::<f64>()
+                        .unwrap_or(0.0);
+                        let volume_usd = bucket.volume_usd.parse::<f64>().unwrap_or(0.0);
+                        volume_7d += volume;
+                        volume_usd_7d += volume_usd;
+                        swap_count_7d += bucket.swap_count;
+                        if bucket.bucket_start >= start_24h {
+                            volume_24h += volume;
+                            volume_usd_24h += volume_usd;
+                            swap_count_24h += bucket.swap_count;
+                        }
+                    }
+                }
+
+                let price_usd = usd_prices.get(&token_address).copied().unwrap_or(0.0);
+                stats.push(TokenStat {
+                    token_address,
+                    liquidity: liquidity.to_string(),
+                    liquidity_usd: (liquidity * price_usd).to_string(),
+                    volume_24h: volume_24h.to_string(),
+                    volume_7d: volume_7d.to_string(),
+                    volume_usd_24h: volume_usd_24h.to_string(),
+                    volume_usd_7d: volume_usd_7d.to_string(),
+                    swap_count_24h,
+                    swap_count_7d,
+                    pool_count: token_pools.len() as i32,
+                    best_pool_address: best_pool_address.to_string(),
+                    last_update_timestamp: chain_timestamp,
+                });
+            }
+
+            for stat in stats {
+                let upsert = insert_into(token_stats::table)
+                    .values(&stat)
+                    .on_conflict(token_stats::token_address)
+                    .do_update()
+                    .set(&stat);
+                // Stats of an older batch must not replace newer windows
+                diesel::query_dsl::methods::FilterDsl::filter(
+                    upsert,
+                    token_stats::last_update_timestamp
+                        .le(excluded(token_stats::last_update_timestamp)),
+                )
+                .execute(conn)
+                .await?;
+            }
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Refreshes `token_stats` for every token of the given pools, aggregating liquidity and the
+/// rolling 24h/7d windows across all pools containing each token.
+pub async fn process_token_stats(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    pool_addresses: Vec<String>,
+    chain_timestamp: i64,
+) -> Result<(), ProcessorError> {
+    if pool_addresses.is_empty() {
+        return Ok(());
+    }
+
+    with_query_retries(&pool, retry_policy, "token_stats", |conn| {
+        Box::pin(execute_token_stats_sql(
+            conn,
+            pool_addresses.clone(),
+            chain_timestamp,
+        ))
+    })
+    .await
+}
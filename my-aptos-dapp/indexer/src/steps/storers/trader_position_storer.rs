@@ -0,0 +1,184 @@
+use ahash::AHashMap;
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::{convert::standardize_address, errors::ProcessorError};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    db_models::{
+        hyperion_swap::HyperionSwap,
+        trader_pnl_daily::{day_bucket, TraderPnlDaily},
+        trader_token_position::TraderTokenPosition,
+    },
+    schema::{trader_pnl_daily, trader_token_positions},
+    utils::{
+        database_utils::ArcDbPool,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+/// `swaps` are the batch's swaps in chain order, as prepared by the swap storer, so they carry
+/// the USD value they were stored with.
+pub async fn execute_trader_positions_sql(
+    conn: &mut AsyncPgConnection,
+    swaps: Vec<HyperionSwap>,
+) -> QueryResult<()> {
+    let Some(start_version) = swaps.first().map(|swap| swap.tx_version) else {
+        return Ok(());
+    };
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            let traders = swaps
+                .iter()
+                .map(|swap| swap.sender.clone())
+                .collect::<Vec<_>>();
+            let tokens = swaps
+                .iter()
+                .flat_map(|swap| {
+                    [
+                        standardize_address(&swap.token_in),
+                        standardize_address(&swap.token_out),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            let mut positions = trader_token_positions::table
+                .filter(trader_token_positions::trader_addr.eq_any(traders.clone()))
+                .filter(trader_token_positions::token_address.eq_any(tokens))
+                .load::<TraderTokenPosition>(conn)
+                .await?
+                .into_iter()
+                .map(|position| {
+                    (
+                        (position.trader_addr.clone(), position.token_address.clone()),
+                        position,
+                    )
+                })
+                .collect::<AHashMap<_, _>>();
+
+            // (realized PnL, volume, swap count) per trader and day
+            let mut daily: AHashMap<(String, i64), (f64, f64, i64)> = AHashMap::new();
+            for swap in &swaps {
+                let amount_usd = swap.amount_usd.parse::<f64>().unwrap_or(0.0);
+                let position_in = positions
+                    .entry((swap.sender.clone(), standardize_address(&swap.token_in)))
+                    .or_insert_with_key(|(trader, token)| {
+                        TraderTokenPosition::new(trader.clone(), token.clone())
+                    });
+                // Both legs of a swap are applied together, so the input side guards the swap
+                if position_in.has_applied(swap.tx_version, swap.event_idx) {
+                    continue;
+                }
+                let realized =
+                    position_in.dispose(swap.amount_in.parse::<f64>().unwrap_or(0.0), amount_usd);
+                position_in.mark_applied(swap.tx_version, swap.event_idx, swap.timestamp);
+
+                let position_out = positions
+                    .entry((swap.sender.clone(), standardize_address(&swap.token_out)))
+                    .or_insert_with_key(|(trader, token)| {
+                        TraderTokenPosition::new(trader.clone(), token.clone())
+                    });
+                position_out.acquire(swap.amount_out.parse::<f64>().unwrap_or(0.0), amount_usd);
+                position_out.mark_applied(swap.tx_version, swap.event_idx, swap.timestamp);
+
+                let day = daily
+                    .entry((swap.sender.clone(), day_bucket(swap.timestamp)))
+                    .or_default();
+                day.0 += realized;
+                day.1 += amount_usd;
+                day.2 += 1;
+            }
+
+            // Positions moved by this batch are the ones whose last swap falls inside it
+            let updated_positions = positions
+                .into_values()
+                .filter(|position| position.last_tx_version >= start_version)
+                .collect::<Vec<_>>();
+            for position in updated_positions {
+                insert_into(trader_token_positions::table)
+                    .values(&position)
+                    .on_conflict((
+                        trader_token_positions::trader_addr,
+                        trader_token_positions::token_address,
+                    ))
+                    .do_update()
+                    .set(&position)
+                    .execute(conn)
+                    .await?;
+            }
+
+            if daily.is_empty() {
+                return Ok(());
+            }
+            let existing_days = trader_pnl_daily::table
+                .filter(trader_pnl_daily::trader_addr.eq_any(traders))
+                .filter(
+                    trader_pnl_daily::day_start.eq_any(
+                        daily
+                            .keys()
+                            .map(|(_, day_start)| *day_start)
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+                .load::<TraderPnlDaily>(conn)
+                .await?
+                .into_iter()
+                .map(|day| ((day.trader_addr.clone(), day.day_start), day))
+                .collect::<AHashMap<_, _>>();
+            let days = daily
+                .into_iter()
+                .map(
+                    |((trader_addr, day_start), (realized_pnl, volume, swap_count))| {
+                        let existing = existing_days.get(&(trader_addr.clone(), day_start));
+                        let (prev_pnl, prev_volume, prev_count) = existing
+                            .map(|day| {
+                                (
+                                    day.realized_pnl_usd.parse::<f64>().unwrap_or(0.0),
+                                    day.volume_usd.parse::<f64>().unwrap_or(0.0),
+                                    day.swap_count,
+                                )
+                            })
+                            .unwrap_or_default();
+                        TraderPnlDaily {
+                            trader_addr,
+                            day_start,
+                            realized_pnl_usd: (prev_pnl + realized_pnl).to_string(),
+                            volume_usd: (prev_volume + volume).to_string(),
+                            swap_count: prev_count + swap_count,
+                        }
+                    },
+                )
+                .collect::<Vec<_>>();
+            insert_into(trader_pnl_daily::table)
+                .values(days)
+                .on_conflict((trader_pnl_daily::trader_addr, trader_pnl_daily::day_start))
+                .do_update()
+                .set((
+                    trader_pnl_daily::realized_pnl_usd
+                        .eq(excluded(trader_pnl_daily::realized_pnl_usd)),
+                    trader_pnl_daily::volume_usd.eq(excluded(trader_pnl_daily::volume_usd)),
+                    trader_pnl_daily::swap_count.eq(excluded(trader_pnl_daily::swap_count)),
+                ))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Applies the Hyperion swaps of the batch to the average cost positions of their senders,
+/// recording realized PnL on every disposal in `trader_pnl_daily`.
+pub async fn process_trader_positions(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    swaps: Vec<HyperionSwap>,
+) -> Result<(), ProcessorError> {
+    if swaps.is_empty() {
+        return Ok(());
+    }
+    with_query_retries(&pool, retry_policy, "trader_positions", |conn| {
+        Box::pin(execute_trader_positions_sql(conn, swaps.clone()))
+    })
+    .await
+}
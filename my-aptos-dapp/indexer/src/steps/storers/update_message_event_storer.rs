@@ -13,8 +13,8 @@ use crate::{
     db_models::{message::Message, user_stat::UserStat},
     schema::{messages, user_stats},
     utils::{
-        database_connection::get_db_connection,
-        database_utils::{get_config_table_chunk_size, ArcDbPool},
+        database_utils::{get_config_table_chunk_size, greatest, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
@@ -84,8 +84,10 @@ async fn execute_update_message_events_sql(
                 .set((
                     user_stats::user_addr.eq(user_stats::user_addr),
                     user_stats::creation_timestamp.eq(user_stats::creation_timestamp),
-                    user_stats::last_update_timestamp
-                        .eq(excluded(user_stats::last_update_timestamp)),
+                    user_stats::last_update_timestamp.eq(greatest(
+                        user_stats::last_update_timestamp,
+                        excluded(user_stats::last_update_timestamp),
+                    )),
                     user_stats::created_messages.eq(user_stats::created_messages),
                     user_stats::updated_messages
                         .eq(user_stats::updated_messages + excluded(user_stats::updated_messages)),
@@ -102,11 +104,10 @@ async fn execute_update_message_events_sql(
     .await
 }
 
-pub async fn process_update_message_events(
-    pool: ArcDbPool,
-    per_table_chunk_sizes: AHashMap<String, usize>,
+/// The latest update of each message, and the user stat changes of all the updates.
+fn update_message_changes(
     update_events: Vec<Message>,
-) -> Result<(), ProcessorError> {
+) -> (Vec<Message>, AHashMap<String, (i64, i64)>) {
     // Key is user address
     // Value is (number of updated messages, latest update message time)
     let mut user_stats_changes: AHashMap<String, (i64, i64)> = AHashMap::new();
@@ -143,6 +144,16 @@ pub async fn process_update_message_events(
             .or_insert(message);
     }
     let filtered_update_events: Vec<Message> = filtered_update_events_map.into_values().collect();
+    (filtered_update_events, user_stats_changes)
+}
+
+pub async fn process_update_message_events(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+    update_events: Vec<Message>,
+) -> Result<(), ProcessorError> {
+    let (filtered_update_events, user_stats_changes) = update_message_changes(update_events);
 
     let chunk_size = get_config_table_chunk_size::<Message>("messages", &per_table_chunk_sizes);
     let tasks = filtered_update_events
@@ -152,24 +163,38 @@ pub async fn process_update_message_events(
             let items = chunk.to_vec();
             let user_stats_changes = user_stats_changes.clone();
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing update message events",
-                );
-                execute_update_message_events_sql(conn, items, user_stats_changes).await
+                with_query_retries(&pool, retry_policy, "update_message_events", |conn| {
+                    Box::pin(execute_update_message_events_sql(
+                        conn,
+                        items.clone(),
+                        user_stats_changes.clone(),
+                    ))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::error!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(())
+}
+
+/// `process_update_message_events` on a single connection, one chunk after the other.
+pub async fn store_update_message_events(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    update_events: Vec<Message>,
+) -> QueryResult<()> {
+    let (filtered_update_events, user_stats_changes) = update_message_changes(update_events);
+    let chunk_size = get_config_table_chunk_size::<Message>("messages", per_table_chunk_sizes);
+    for chunk in filtered_update_events.chunks(chunk_size) {
+        execute_update_message_events_sql(conn, chunk.to_vec(), user_stats_changes.clone())
+            .await?;
     }
     Ok(())
 }
@@ -8,8 +8,8 @@ use crate::{
     db_models::package_upgrade::PackageUpgrade,
     schema::package_upgrade_history,
     utils::{
-        database_connection::get_db_connection,
         database_utils::{get_config_table_chunk_size, ArcDbPool},
+        query_retry::{task_error, with_query_retries, QueryRetryPolicy},
     },
 };
 
@@ -36,6 +36,7 @@ async fn execute_upgrade_package_changes_sql(
 
 pub async fn process_upgrade_package_changes(
     pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
     per_table_chunk_sizes: AHashMap<String, usize>,
     upgrade_changes: Vec<PackageUpgrade>,
 ) -> Result<(), ProcessorError> {
@@ -49,24 +50,35 @@ pub async fn process_upgrade_package_changes(
             let pool = pool.clone();
             let items = chunk.to_vec();
             tokio::spawn(async move {
-                let conn = &mut get_db_connection(&pool).await.expect(
-                    "Failed to get connection from pool while processing upgrade package changes",
-                );
-                execute_upgrade_package_changes_sql(conn, items).await
+                with_query_retries(&pool, retry_policy, "upgrade_package_changes", |conn| {
+                    Box::pin(execute_upgrade_package_changes_sql(conn, items.clone()))
+                })
+                .await
             })
         })
         .collect::<Vec<_>>();
 
     let results = futures_util::future::try_join_all(tasks)
         .await
-        .expect("Task panicked executing in chunks");
+        .map_err(task_error)?;
     for res in results {
-        res.map_err(|e| {
-            tracing::warn!("Error running query: {:?}", e);
-            ProcessorError::ProcessError {
-                message: e.to_string(),
-            }
-        })?;
+        res?;
+    }
+    Ok(())
+}
+
+/// `process_upgrade_package_changes` on a single connection, one chunk after the other.
+pub async fn store_upgrade_package_changes(
+    conn: &mut AsyncPgConnection,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    upgrade_changes: Vec<PackageUpgrade>,
+) -> QueryResult<()> {
+    let chunk_size = get_config_table_chunk_size::<PackageUpgrade>(
+        "package_upgrade_history",
+        per_table_chunk_sizes,
+    );
+    for chunk in upgrade_changes.chunks(chunk_size) {
+        execute_upgrade_package_changes_sql(conn, chunk.to_vec()).await?;
     }
     Ok(())
 }
@@ -0,0 +1,405 @@
+use ahash::AHashSet;
+use anyhow::Result;
+use aptos_indexer_processor_sdk::utils::{convert::standardize_address, errors::ProcessorError};
+use diesel::{insert_into, update, ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    config::indexer_processor_config::WashTradingConfig,
+    db_models::{
+        hyperion_pool_stat::hour_bucket,
+        hyperion_swap::HyperionSwap,
+        wash_trading_score::{
+            cluster_addresses, match_round_trips, score_legs, ScoredLeg, WashLeg, WashTradingScore,
+        },
+    },
+    schema::{hyperion_swaps, trader_stats, trades, wash_trading_scores},
+    utils::{
+        database_utils::ArcDbPool,
+        query_retry::{with_query_retries, QueryRetryPolicy},
+    },
+};
+
+/// First and last timestamp of the new legs, widened by the round trip window.
+fn round_trip_range(
+    timestamps: impl Iterator<Item = i64>,
+    config: &WashTradingConfig,
+) -> (i64, i64) {
+    let (first, last) = timestamps.fold((i64::MAX, i64::MIN), |(first, last), timestamp| {
+        (first.min(timestamp), last.max(timestamp))
+    });
+    (
+        first - config.round_trip_window_secs,
+        last + config.round_trip_window_secs,
+    )
+}
+
+/// Pairs the new swaps with the unmatched swaps of their pools within the round trip window
+/// and records the round trips. Returns the addresses and pools whose legs changed.
+async fn match_swap_round_trips(
+    conn: &mut AsyncPgConnection,
+    config: &WashTradingConfig,
+    swap_ids: Vec<String>,
+    since: i64,
+) -> QueryResult<Vec<(String, String)>> {
+    let new_swaps = hyperion_swaps::table
+        .filter(hyperion_swaps::swap_id.eq_any(&swap_ids))
+        .select((
+            hyperion_swaps::sender,
+            hyperion_swaps::pool_address,
+            hyperion_swaps::timestamp,
+        ))
+        .load::<(String, String, i64)>(conn)
+        .await?;
+    if new_swaps.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut pool_addresses = new_swaps
+        .iter()
+        .map(|(_, pool_address, _)| pool_address.clone())
+        .collect::<Vec<_>>();
+    pool_addresses.sort();
+    pool_addresses.dedup();
+    let (from, to) = round_trip_range(new_swaps.iter().map(|(_, _, timestamp)| *timestamp), config);
+    let candidates = hyperion_swaps::table
+        .filter(hyperion_swaps::pool_address.eq_any(&pool_addresses))
+        .filter(hyperion_swaps::round_trip_id.is_null())
+        .filter(hyperion_swaps::timestamp.between(from, to))
+        .order((hyperion_swaps::tx_version, hyperion_swaps::event_idx))
+        .load::<HyperionSwap>(conn)
+        .await?;
+    // Clusters are linked over the whole score window, not only the candidates
+    let links = hyperion_swaps::table
+        .filter(hyperion_swaps::pool_address.eq_any(&pool_addresses))
+        .filter(hyperion_swaps::timestamp.ge(since))
+        .filter(hyperion_swaps::sender.ne(hyperion_swaps::recipient))
+        .select((hyperion_swaps::sender, hyperion_swaps::recipient))
+        .distinct()
+        .load::<(String, String)>(conn)
+        .await?;
+    let clusters = cluster_addresses(
+        links
+            .iter()
+            .map(|(sender, recipient)| (sender.as_str(), recipient.as_str()))
+            .chain(
+                candidates
+                    .iter()
+                    .map(|swap| (swap.sender.as_str(), swap.recipient.as_str())),
+            ),
+    );
+    let parse = |value: &str| value.parse::<f64>().unwrap_or(0.0);
+    let legs = candidates
+        .iter()
+        .map(|swap| WashLeg {
+            id: swap.swap_id.clone(),
+            address: swap.sender.clone(),
+            pool_address: swap.pool_address.clone(),
+            cluster: clusters[&swap.sender].clone(),
+            token_in: standardize_address(&swap.token_in),
+            token_out: standardize_address(&swap.token_out),
+            amount_in: parse(&swap.amount_in),
+            amount_out: parse(&swap.amount_out),
+            volume: parse(&swap.amount_usd),
+            timestamp: swap.timestamp,
+        })
+        .collect::<Vec<_>>();
+
+    let swap_ids = swap_ids.into_iter().collect::<AHashSet<_>>();
+    let mut changed = new_swaps
+        .into_iter()
+        .map(|(sender, pool_address, _)| (sender, pool_address))
+        .collect::<Vec<_>>();
+    for (i, j) in match_round_trips(&legs, config) {
+        let (first, second) = (&legs[i], &legs[j]);
+        // Unmatched legs that did not pair before only pair through a new leg
+        if !swap_ids.contains(&first.id) && !swap_ids.contains(&second.id) {
+            continue;
+        }
+        update(
+            hyperion_swaps::table
+                .filter(hyperion_swaps::swap_id.eq_any([&first.id, &second.id]))
+                .filter(hyperion_swaps::round_trip_id.is_null()),
+        )
+        .set(hyperion_swaps::round_trip_id.eq(&first.id))
+        .execute(conn)
+        .await?;
+        for leg in [first, second] {
+            changed.push((leg.address.clone(), leg.pool_address.clone()));
+        }
+    }
+    changed.sort();
+    changed.dedup();
+    Ok(changed)
+}
+
+/// Rescores the given addresses in their pools over the score window and marks the round
+/// trips of flagged ones as wash. Returns the hourly buckets of the swaps flagged for the
+/// first time.
+async fn score_swap_addresses(
+    conn: &mut AsyncPgConnection,
+    config: &WashTradingConfig,
+    changed: Vec<(String, String)>,
+    since: i64,
+    chain_timestamp: i64,
+) -> QueryResult<(Vec<WashTradingScore>, Vec<(String, i64)>)> {
+    let addresses = changed
+        .iter()
+        .map(|(address, _)| address.clone())
+        .collect::<Vec<_>>();
+    let pool_addresses = changed
+        .iter()
+        .map(|(_, pool_address)| pool_address.clone())
+        .collect::<Vec<_>>();
+    let changed = changed.into_iter().collect::<AHashSet<_>>();
+    let legs = hyperion_swaps::table
+        .filter(hyperion_swaps::sender.eq_any(addresses))
+        .filter(hyperion_swaps::pool_address.eq_any(pool_addresses))
+        .filter(hyperion_swaps::timestamp.ge(since))
+        .select((
+            hyperion_swaps::sender,
+            hyperion_swaps::pool_address,
+            hyperion_swaps::amount_usd,
+            hyperion_swaps::round_trip_id,
+        ))
+        .load::<(String, String, String, Option<String>)>(conn)
+        .await?
+        .into_iter()
+        .filter(|(address, pool_address, _, _)| {
+            changed.contains(&(address.clone(), pool_address.clone()))
+        })
+        .map(
+            |(address, pool_address, amount_usd, round_trip_id)| ScoredLeg {
+                address,
+                pool_address,
+                volume: amount_usd.parse::<f64>().unwrap_or(0.0),
+                round_trip_id,
+            },
+        )
+        .collect::<Vec<_>>();
+    let scores = score_legs(&legs, config, chain_timestamp);
+
+    let mut wash_buckets = vec![];
+    for score in scores.iter().filter(|score| score.flagged) {
+        // Only swaps flagged for the first time change the volume windows
+        let flagged = update(
+            hyperion_swaps::table
+                .filter(hyperion_swaps::sender.eq(&score.address))
+                .filter(hyperion_swaps::pool_address.eq(&score.pool_address))
+                .filter(hyperion_swaps::timestamp.ge(since))
+                .filter(hyperion_swaps::round_trip_id.is_not_null())
+                .filter(hyperion_swaps::is_wash.eq(false)),
+        )
+        .set(hyperion_swaps::is_wash.eq(true))
+        .returning((hyperion_swaps::pool_address, hyperion_swaps::timestamp))
+        .get_results::<(String, i64)>(conn)
+        .await?;
+        wash_buckets.extend(
+            flagged
+                .into_iter()
+                .map(|(pool_address, timestamp)| (pool_address, hour_bucket(timestamp))),
+        );
+    }
+    wash_buckets.sort();
+    wash_buckets.dedup();
+    Ok((scores, wash_buckets))
+}
+
+/// Pairs the new trades with the unmatched trades of their traders within the round trip
+/// window and records the round trips. Returns the traders of the new trades.
+async fn match_trade_round_trips(
+    conn: &mut AsyncPgConnection,
+    config: &WashTradingConfig,
+    trade_ids: Vec<String>,
+) -> QueryResult<Vec<String>> {
+    let new_trades = trades::table
+        .filter(trades::trade_obj_addr.eq_any(&trade_ids))
+        .select((trades::trader_addr, trades::creation_timestamp))
+        .load::<(String, i64)>(conn)
+        .await?;
+    if new_trades.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut traders = new_trades
+        .iter()
+        .map(|(trader, _)| trader.clone())
+        .collect::<Vec<_>>();
+    traders.sort();
+    traders.dedup();
+    let (from, to) = round_trip_range(new_trades.iter().map(|(_, timestamp)| *timestamp), config);
+    // Trades are matched per trader, they have no counterparty to cluster with
+    let legs = trades::table
+        .filter(trades::trader_addr.eq_any(&traders))
+        .filter(trades::round_trip_id.is_null())
+        .filter(trades::creation_timestamp.between(from, to))
+        .order(trades::creation_timestamp)
+        .select((
+            trades::trade_obj_addr,
+            trades::trader_addr,
+            trades::token_from,
+            trades::token_to,
+            trades::amount_from,
+            trades::amount_to,
+            trades::price,
+            trades::creation_timestamp,
+        ))
+        .load::<(String, String, String, String, i64, i64, i64, i64)>(conn)
+        .await?
+        .into_iter()
+        .map(
+            |(id, trader, token_from, token_to, amount_from, amount_to, price, timestamp)| {
+                WashLeg {
+                    id,
+                    address: trader.clone(),
+                    pool_address: String::new(),
+                    cluster: trader,
+                    token_in: token_from,
+                    token_out: token_to,
+                    amount_in: amount_from as f64,
+                    amount_out: amount_to as f64,
+                    volume: price as f64,
+                    timestamp,
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let trade_ids = trade_ids.into_iter().collect::<AHashSet<_>>();
+    for (i, j) in match_round_trips(&legs, config) {
+        let (first, second) = (&legs[i], &legs[j]);
+        if !trade_ids.contains(&first.id) && !trade_ids.contains(&second.id) {
+            continue;
+        }
+        update(
+            trades::table
+                .filter(trades::trade_obj_addr.eq_any([&first.id, &second.id]))
+                .filter(trades::round_trip_id.is_null()),
+        )
+        .set(trades::round_trip_id.eq(&first.id))
+        .execute(conn)
+        .await?;
+    }
+    Ok(traders)
+}
+
+/// Rescores the given traders over the score window and marks the round trips of flagged
+/// ones as wash, taking them out of `trader_stats` when configured.
+async fn score_traders(
+    conn: &mut AsyncPgConnection,
+    config: &WashTradingConfig,
+    traders: Vec<String>,
+    since: i64,
+    chain_timestamp: i64,
+) -> QueryResult<Vec<WashTradingScore>> {
+    let legs = trades::table
+        .filter(trades::trader_addr.eq_any(traders))
+        .filter(trades::creation_timestamp.ge(since))
+        .select((trades::trader_addr, trades::price, trades::round_trip_id))
+        .load::<(String, i64, Option<String>)>(conn)
+        .await?
+        .into_iter()
+        .map(|(address, price, round_trip_id)| ScoredLeg {
+            address,
+            pool_address: String::new(),
+            volume: price as f64,
+            round_trip_id,
+        })
+        .collect::<Vec<_>>();
+    let scores = score_legs(&legs, config, chain_timestamp);
+
+    for score in scores.iter().filter(|score| score.flagged) {
+        let wash_volume = update(
+            trades::table
+                .filter(trades::trader_addr.eq(&score.address))
+                .filter(trades::creation_timestamp.ge(since))
+                .filter(trades::round_trip_id.is_not_null())
+                .filter(trades::is_wash.eq(false)),
+        )
+        .set(trades::is_wash.eq(true))
+        .returning(trades::price)
+        .get_results::<i64>(conn)
+        .await?
+        .into_iter()
+        .sum::<i64>();
+        if config.exclude_flagged_volume && wash_volume > 0 {
+            // total_volume sums the price of created trades, each wash trade is taken out
+            // once, when it is first flagged
+            update(trader_stats::table.find(&score.address))
+                .set(trader_stats::total_volume.eq(trader_stats::total_volume - wash_volume))
+                .execute(conn)
+                .await?;
+        }
+    }
+    Ok(scores)
+}
+
+async fn execute_wash_trading_sql(
+    conn: &mut AsyncPgConnection,
+    config: WashTradingConfig,
+    swap_ids: Vec<String>,
+    trade_ids: Vec<String>,
+    chain_timestamp: i64,
+) -> QueryResult<Vec<(String, i64)>> {
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            let since = chain_timestamp - config.score_window_secs;
+            let mut scores = vec![];
+            let mut wash_buckets = vec![];
+
+            if !swap_ids.is_empty() {
+                let changed = match_swap_round_trips(conn, &config, swap_ids, since).await?;
+                let (swap_scores, buckets) =
+                    score_swap_addresses(conn, &config, changed, since, chain_timestamp).await?;
+                scores.extend(swap_scores);
+                wash_buckets = buckets;
+            }
+            if !trade_ids.is_empty() {
+                let traders = match_trade_round_trips(conn, &config, trade_ids).await?;
+                scores.extend(score_traders(conn, &config, traders, since, chain_timestamp).await?);
+            }
+
+            for score in scores {
+                insert_into(wash_trading_scores::table)
+                    .values(&score)
+                    .on_conflict((
+                        wash_trading_scores::address,
+                        wash_trading_scores::pool_address,
+                    ))
+                    .do_update()
+                    .set(&score)
+                    .execute(conn)
+                    .await?;
+            }
+
+            Ok(wash_buckets)
+        })
+    })
+    .await
+}
+
+/// Pairs the new swaps and trades of a batch into round trips with the unmatched legs of the
+/// round trip window, then rescores the addresses whose legs changed over the score window
+/// and marks the round trips of flagged addresses as wash. Returns the hourly buckets of the
+/// swaps flagged for the first time.
+pub async fn process_wash_trading(
+    pool: ArcDbPool,
+    retry_policy: QueryRetryPolicy,
+    config: WashTradingConfig,
+    swap_ids: Vec<String>,
+    trade_ids: Vec<String>,
+    chain_timestamp: i64,
+) -> Result<Vec<(String, i64)>, ProcessorError> {
+    if swap_ids.is_empty() && trade_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    with_query_retries(&pool, retry_policy, "wash_trading", |conn| {
+        Box::pin(execute_wash_trading_sql(
+            conn,
+            config.clone(),
+            swap_ids.clone(),
+            trade_ids.clone(),
+            chain_timestamp,
+        ))
+    })
+    .await
+}
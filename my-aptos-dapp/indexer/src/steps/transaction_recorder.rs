@@ -0,0 +1,132 @@
+use ahash::AHashSet;
+use anyhow::{Context, Result};
+use aptos_indexer_processor_sdk::{
+    aptos_protos::transaction::v1::{transaction::TxnData, write_set_change::Change, Transaction},
+    traits::{async_step::AsyncRunType, AsyncStep, NamedStep, Processable},
+    types::transaction_context::TransactionContext,
+    utils::{convert::standardize_address, errors::ProcessorError},
+};
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+use crate::{
+    config::indexer_processor_config::RecordConfig,
+    utils::transaction_files::write_transactions_file,
+};
+
+/// TransactionRecorder is a step that writes the batches of the transaction stream to disk,
+/// so they can be replayed with a file `transaction_stream_config`, and passes them on.
+pub struct TransactionRecorder
+where
+    Self: Sized + Send + 'static,
+{
+    output_dir: PathBuf,
+    // Standardized addresses, every transaction is recorded when empty
+    addresses: AHashSet<String>,
+}
+
+impl TransactionRecorder {
+    pub fn new(config: &RecordConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.output_dir).with_context(|| {
+            format!(
+                "Failed to create recording directory {}",
+                config.output_dir.display()
+            )
+        })?;
+        Ok(Self {
+            output_dir: config.output_dir.clone(),
+            addresses: config
+                .addresses
+                .iter()
+                .map(|address| standardize_address(address))
+                .collect(),
+        })
+    }
+
+    fn is_address(&self, address: &str) -> bool {
+        self.addresses.contains(&standardize_address(address))
+    }
+
+    /// Whether the transaction was sent by, emitted an event of, or changed the state of
+    /// one of the addresses.
+    fn touches_addresses(&self, txn: &Transaction) -> bool {
+        let (sender, events) = match txn.txn_data.as_ref() {
+            Some(TxnData::User(user_txn)) => (
+                user_txn
+                    .request
+                    .as_ref()
+                    .map(|request| request.sender.as_str()),
+                user_txn.events.as_slice(),
+            ),
+            Some(TxnData::BlockMetadata(block_metadata)) => {
+                (None, block_metadata.events.as_slice())
+            }
+            Some(TxnData::Genesis(genesis)) => (None, genesis.events.as_slice()),
+            _ => (None, [].as_slice()),
+        };
+        if sender.is_some_and(|sender| self.is_address(sender)) {
+            return true;
+        }
+        let touches_event = events.iter().any(|event| {
+            let type_address = event.type_str.split("::").next().unwrap_or_default();
+            self.is_address(type_address)
+                || event
+                    .key
+                    .as_ref()
+                    .is_some_and(|key| self.is_address(&key.account_address))
+        });
+        if touches_event {
+            return true;
+        }
+        txn.info
+            .iter()
+            .flat_map(|info| &info.changes)
+            .any(|change| match change.change.as_ref() {
+                Some(Change::WriteResource(resource)) => self.is_address(&resource.address),
+                Some(Change::DeleteResource(resource)) => self.is_address(&resource.address),
+                Some(Change::WriteModule(module)) => self.is_address(&module.address),
+                Some(Change::DeleteModule(module)) => self.is_address(&module.address),
+                _ => false,
+            })
+    }
+}
+
+#[async_trait]
+impl Processable for TransactionRecorder {
+    type Input = Vec<Transaction>;
+    type Output = Vec<Transaction>;
+    type RunType = AsyncRunType;
+
+    async fn process(
+        &mut self,
+        item: TransactionContext<Vec<Transaction>>,
+    ) -> Result<Option<TransactionContext<Vec<Transaction>>>, ProcessorError> {
+        let transactions = item
+            .data
+            .iter()
+            .filter(|txn| self.addresses.is_empty() || self.touches_addresses(txn))
+            .cloned()
+            .collect::<Vec<_>>();
+        // Replay fills the versions missing between files, filtered batches stay replayable
+        if !transactions.is_empty() {
+            write_transactions_file(
+                &self.output_dir,
+                item.metadata.start_version,
+                item.metadata.end_version,
+                transactions,
+            )
+            .map_err(|e| ProcessorError::ProcessError {
+                message: format!("Failed to record batch: {:?}", e),
+            })?;
+        }
+        Ok(Some(item))
+    }
+}
+
+impl AsyncStep for TransactionRecorder {}
+
+impl NamedStep for TransactionRecorder {
+    fn name(&self) -> String {
+        "TransactionRecorder".to_string()
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use aptos_indexer_processor_sdk::{
+    traits::{async_step::AsyncRunType, AsyncStep, NamedStep, Processable},
+    types::transaction_context::TransactionContext,
+    utils::errors::ProcessorError,
+};
+use async_trait::async_trait;
+
+use super::{
+    extractor::{ContractEvent, TransactionContextData},
+    storers::{
+        hyperion_pool_stats_storer::process_hyperion_pool_stats,
+        token_stats_storer::process_token_stats, wash_trading_storer::process_wash_trading,
+    },
+};
+use crate::{
+    config::indexer_processor_config::WashTradingConfig,
+    utils::{database_utils::ArcDbPool, query_retry::QueryRetryPolicy},
+};
+
+/// WashTradingDetector is a step that scores back-and-forth trading once a batch has been
+/// stored, and optionally takes the flagged volume out of the stats the Storer just wrote.
+pub struct WashTradingDetector
+where
+    Self: Sized + Send + 'static,
+{
+    pool: ArcDbPool,
+    wash_trading_config: WashTradingConfig,
+    retry_policy: QueryRetryPolicy,
+}
+
+impl AsyncStep for WashTradingDetector {}
+
+impl NamedStep for WashTradingDetector {
+    fn name(&self) -> String {
+        "WashTradingDetector".to_string()
+    }
+}
+
+impl WashTradingDetector {
+    pub fn new(
+        pool: ArcDbPool,
+        wash_trading_config: WashTradingConfig,
+        retry_policy: QueryRetryPolicy,
+    ) -> Self {
+        Self {
+            pool,
+            wash_trading_config,
+            retry_policy,
+        }
+    }
+}
+
+#[async_trait]
+impl Processable for WashTradingDetector {
+    type Input = TransactionContextData;
+    type Output = TransactionContextData;
+    type RunType = AsyncRunType;
+
+    async fn process(
+        &mut self,
+        transaction_context_data: TransactionContext<TransactionContextData>,
+    ) -> Result<Option<TransactionContext<TransactionContextData>>, ProcessorError> {
+        // New legs of the batch, only created trades are legs of a round trip
+        let mut swap_ids = vec![];
+        let mut trade_ids = vec![];
+        for event in &transaction_context_data.data.events {
+            match event {
+                ContractEvent::HyperionSwap(swap) => swap_ids.push(swap.swap_id.clone()),
+                ContractEvent::CreateTradeEvent(trade, _) => {
+                    trade_ids.push(trade.trade_obj_addr.clone())
+                }
+                _ => {}
+            }
+        }
+        if swap_ids.is_empty() && trade_ids.is_empty() {
+            return Ok(Some(transaction_context_data));
+        }
+        let chain_timestamp = transaction_context_data
+            .metadata
+            .end_transaction_timestamp
+            .as_ref()
+            .map(|t| t.seconds)
+            .unwrap_or_default();
+
+        let wash_buckets = process_wash_trading(
+            self.pool.clone(),
+            self.retry_policy,
+            self.wash_trading_config.clone(),
+            swap_ids,
+            trade_ids,
+            chain_timestamp,
+        )
+        .await?;
+
+        // Newly flagged swaps can sit in buckets the Storer did not touch in this batch
+        if self.wash_trading_config.exclude_flagged_volume && !wash_buckets.is_empty() {
+            let mut wash_pools = wash_buckets
+                .iter()
+                .map(|(pool_address, _)| pool_address.clone())
+                .collect::<Vec<_>>();
+            wash_pools.dedup();
+            process_hyperion_pool_stats(
+                self.pool.clone(),
+                self.retry_policy,
+                wash_pools.clone(),
+                wash_buckets,
+                chain_timestamp,
+                true,
+            )
+            .await?;
+            process_token_stats(
+                self.pool.clone(),
+                self.retry_policy,
+                wash_pools,
+                chain_timestamp,
+            )
+            .await?;
+        }
+
+        Ok(Some(transaction_context_data))
+    }
+}
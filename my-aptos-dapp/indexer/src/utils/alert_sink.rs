@@ -0,0 +1,97 @@
+//! Destinations of new alerts and indexed events. Outbox rows are written in the transaction
+//! that stores the alerts, and a failure to enqueue events fails the batch so it is retried.
+//! Sinks pushing alerts after the commit, like the log, are best effort and only logged.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::{database_utils::ArcDbPool, webhook::WebhookSink};
+use crate::{
+    config::indexer_processor_config::{AlertSinkConfig, WebhookEventKind},
+    db_models::{
+        alert::Alert, hyperion_pool::HyperionPool, hyperion_swap::HyperionSwap,
+        webhook_outbox::NewWebhookOutboxEntry,
+    },
+};
+
+/// Indexed activity a sink can subscribe to besides alerts.
+#[derive(Clone, Debug)]
+pub enum SinkEvent {
+    PoolCreated(HyperionPool),
+    Swap(HyperionSwap),
+}
+
+impl SinkEvent {
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            SinkEvent::PoolCreated(_) => WebhookEventKind::PoolCreated,
+            SinkEvent::Swap(_) => WebhookEventKind::Swap,
+        }
+    }
+
+    /// Stable id of the event, so replaying a range never delivers it twice.
+    pub fn id(&self) -> String {
+        match self {
+            SinkEvent::PoolCreated(pool) => format!("pool_created-{}", pool.pool_address),
+            SinkEvent::Swap(swap) => format!("swap-{}", swap.swap_id),
+        }
+    }
+}
+
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Outbox rows of alerts about to be stored, inserted in the same transaction.
+    fn outbox_entries(&self, _alerts: &[Alert], _now: i64) -> Vec<NewWebhookOutboxEntry> {
+        vec![]
+    }
+
+    /// Delivers alerts once they are committed, each alert is only handed over once.
+    async fn send(&self, _alerts: &[Alert]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Delivers indexed events of the batch. Sinks that only care about alerts ignore them.
+    async fn send_events(&self, _events: &[SinkEvent]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Logs every alert through tracing.
+pub struct LogSink;
+
+#[async_trait]
+impl AlertSink for LogSink {
+    fn name(&self) -> String {
+        "log".to_string()
+    }
+
+    async fn send(&self, alerts: &[Alert]) -> Result<()> {
+        for alert in alerts {
+            tracing::info!(
+                alert_id = %alert.alert_id,
+                alert_type = %alert.alert_type,
+                severity = %alert.severity,
+                pool_address = %alert.pool_address,
+                tx_version = alert.tx_version,
+                "{}",
+                alert.message
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn build_alert_sinks(configs: &[AlertSinkConfig], pool: ArcDbPool) -> Vec<Arc<dyn AlertSink>> {
+    configs
+        .iter()
+        .map(|config| match config {
+            AlertSinkConfig::Log => Arc::new(LogSink) as Arc<dyn AlertSink>,
+            AlertSinkConfig::Webhook(webhook_config) => {
+                Arc::new(WebhookSink::new(pool.clone(), webhook_config.clone()))
+            }
+        })
+        .collect()
+}
@@ -1,14 +1,19 @@
+use anyhow::Context;
 use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
-use diesel::ConnectionResult;
+use diesel::{Connection, ConnectionResult};
 use diesel_async::{
+    async_connection_wrapper::AsyncConnectionWrapper,
     pooled_connection::{bb8::Pool, AsyncDieselConnectionManager, ManagerConfig},
     AsyncPgConnection,
 };
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use futures_util::{future::BoxFuture, FutureExt};
 use std::sync::Arc;
 
 use super::database_utils::{ArcDbPool, DbPoolConnection};
 
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("src/db_migrations/migrations");
+
 fn establish_connection(database_url: &str) -> BoxFuture<ConnectionResult<AsyncPgConnection>> {
     use native_tls::{Certificate, TlsConnector};
     use postgres_native_tls::MakeTlsConnector;
@@ -88,3 +93,17 @@ pub async fn get_db_connection(pool: &ArcDbPool) -> Result<DbPoolConnection, Pro
         }
     })
 }
+
+/// Runs the pending embedded migrations. The migration harness is synchronous, so it runs on
+/// a blocking thread with its own connection.
+pub async fn run_migrations(database_url: &str) -> anyhow::Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = AsyncConnectionWrapper::<AsyncPgConnection>::establish(&database_url)
+            .context("Failed to connect to run migrations")?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| anyhow::anyhow!("Failed to run migrations: {}", e))?;
+        Ok(())
+    })
+    .await?
+}
@@ -1,4 +1,5 @@
 use ahash::AHashMap;
+use diesel::sql_types::BigInt;
 use diesel_async::{
     pooled_connection::bb8::{Pool, PooledConnection},
     AsyncPgConnection,
@@ -9,6 +10,17 @@ pub type DbPool = Pool<AsyncPgConnection>;
 pub type ArcDbPool = Arc<DbPool>;
 pub type DbPoolConnection<'a> = PooledConnection<'a, AsyncPgConnection>;
 
+diesel::sql_function! {
+    /// Postgres `GREATEST`, keeps a stat's timestamp from moving back when ranges are written
+    /// out of order
+    fn greatest(a: BigInt, b: BigInt) -> BigInt;
+}
+
+diesel::sql_function! {
+    /// Postgres `LEAST`, the counterpart of `greatest` for creation timestamps
+    fn least(a: BigInt, b: BigInt) -> BigInt;
+}
+
 // the max is actually u16::MAX but we see that when the size is too big we get an overflow error so reducing it a bit
 const MAX_DIESEL_PARAM_SIZE: usize = (u16::MAX / 2) as usize;
 
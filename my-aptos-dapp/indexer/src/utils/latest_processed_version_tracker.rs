@@ -2,17 +2,19 @@ use ahash::AHashMap;
 use anyhow::Result;
 use aptos_indexer_processor_sdk::{
     traits::{NamedStep, PollableAsyncRunType, PollableAsyncStep, Processable},
-    types::transaction_context::TransactionContext,
+    types::transaction_context::{TransactionContext, TransactionMetadata},
     utils::{errors::ProcessorError, time::parse_timestamp},
 };
 use async_trait::async_trait;
-use diesel::{query_dsl::methods::FilterDsl, upsert::excluded, ExpressionMethods};
+use diesel::{query_dsl::methods::FilterDsl, upsert::excluded, ExpressionMethods, QueryResult};
+use diesel_async::AsyncPgConnection;
 use std::marker::PhantomData;
 
 use super::{
     database_connection::{get_db_connection, new_db_pool},
     database_execution::execute_with_better_error,
     database_utils::ArcDbPool,
+    metrics::{CHAIN_LAG_SECONDS, GAP_BUFFER_SIZE, LAST_PROCESSED_VERSION, STEP_BATCH_LATENCY},
 };
 use crate::{
     config::indexer_processor_config::DbConfig, db_models::processor_status::ProcessorStatus,
@@ -21,6 +23,46 @@ use crate::{
 
 const UPDATE_PROCESSOR_STATUS_SECS: u64 = 1;
 
+/// Status of `tracker_name` once the batch of `metadata` and all the versions before it are
+/// processed.
+pub fn batch_processor_status(
+    tracker_name: &str,
+    metadata: &TransactionMetadata,
+) -> ProcessorStatus {
+    ProcessorStatus {
+        processor: tracker_name.to_string(),
+        last_success_version: metadata.end_version as i64,
+        last_transaction_timestamp: metadata
+            .end_transaction_timestamp
+            .as_ref()
+            .map(|t| parse_timestamp(t, metadata.end_version as i64))
+            .map(|t| t.naive_utc()),
+    }
+}
+
+/// Moves the `processor_status` row forward to `status`, it is left as is when already past it.
+pub async fn upsert_processor_status(
+    conn: &mut AsyncPgConnection,
+    status: &ProcessorStatus,
+) -> QueryResult<()> {
+    let query = diesel::insert_into(processor_status::table)
+        .values(status)
+        .on_conflict(processor_status::processor)
+        .do_update()
+        .set((
+            processor_status::last_success_version
+                .eq(excluded(processor_status::last_success_version)),
+            processor_status::last_updated.eq(excluded(processor_status::last_updated)),
+            processor_status::last_transaction_timestamp
+                .eq(excluded(processor_status::last_transaction_timestamp)),
+        ))
+        .filter(
+            processor_status::last_success_version
+                .lt(excluded(processor_status::last_success_version)),
+        );
+    execute_with_better_error(conn, vec![query]).await
+}
+
 pub struct LatestVersionProcessedTracker<T>
 where
     Self: Sized + Send + 'static,
@@ -80,39 +122,19 @@ where
     async fn save_processor_status(&mut self) -> Result<(), ProcessorError> {
         // Update the processor status
         if let Some(last_success_batch) = self.last_success_batch.as_ref() {
-            let end_timestamp = last_success_batch
-                .metadata
-                .end_transaction_timestamp
-                .as_ref()
-                .map(|t| parse_timestamp(t, last_success_batch.metadata.end_version as i64))
-                .map(|t| t.naive_utc());
-            let status = ProcessorStatus {
-                processor: self.tracker_name.clone(),
-                last_success_version: last_success_batch.metadata.end_version as i64,
-                last_transaction_timestamp: end_timestamp,
-            };
-            let query = diesel::insert_into(processor_status::table)
-                .values(&status)
-                .on_conflict(processor_status::processor)
-                .do_update()
-                .set((
-                    processor_status::last_success_version
-                        .eq(excluded(processor_status::last_success_version)),
-                    processor_status::last_updated.eq(excluded(processor_status::last_updated)),
-                    processor_status::last_transaction_timestamp
-                        .eq(excluded(processor_status::last_transaction_timestamp)),
-                ))
-                .filter(
-                    processor_status::last_success_version
-                        .lt(excluded(processor_status::last_success_version)),
-                );
+            let status = batch_processor_status(&self.tracker_name, &last_success_batch.metadata);
             let conn = &mut get_db_connection(&self.pool).await?;
-            execute_with_better_error(conn, vec![query])
-                .await
-                .map_err(|e| ProcessorError::DBStoreError {
+            upsert_processor_status(conn, &status).await.map_err(|e| {
+                ProcessorError::DBStoreError {
                     message: format!("Failed to update processor status: {}", e),
-                    query: Some(format!("{:?}", query)),
-                })?;
+                    query: None,
+                }
+            })?;
+            LAST_PROCESSED_VERSION.set(status.last_success_version);
+            if let Some(end_timestamp) = status.last_transaction_timestamp {
+                let lag = chrono::Utc::now().naive_utc() - end_timestamp;
+                CHAIN_LAG_SECONDS.set(lag.num_milliseconds() as f64 / 1000.0);
+            }
         }
         Ok(())
     }
@@ -132,6 +154,9 @@ where
         &mut self,
         current_batch: TransactionContext<T>,
     ) -> Result<Option<TransactionContext<T>>, ProcessorError> {
+        let _timer = STEP_BATCH_LATENCY
+            .with_label_values(&["LatestVersionProcessedTracker"])
+            .start_timer();
         let tx_context = TransactionContext {
             data: (),
             metadata: current_batch.metadata.clone(),
@@ -162,6 +187,13 @@ where
     ) -> Result<Option<Vec<TransactionContext<Self::Output>>>, ProcessorError> {
         // If processing or polling ends, save the last successful batch to the database.
         self.save_processor_status().await?;
+        if !self.seen_versions.is_empty() {
+            tracing::info!(
+                "{} out of order batches after version {} are replayed on restart",
+                self.seen_versions.len(),
+                self.next_version
+            );
+        }
         Ok(None)
     }
 }
@@ -177,7 +209,7 @@ where
     }
 
     async fn poll(&mut self) -> Result<Option<Vec<TransactionContext<T>>>, ProcessorError> {
-        // TODO: Add metrics for gap count
+        GAP_BUFFER_SIZE.set(self.seen_versions.len() as i64);
         self.save_processor_status().await?;
         // Nothing should be returned
         Ok(None)
@@ -0,0 +1,145 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_gauge, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, Gauge, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+use std::time::Duration;
+
+use super::database_utils::ArcDbPool;
+
+const DB_POOL_SAMPLE_INTERVAL_SECS: u64 = 5;
+
+/// Contract events extracted from transactions, by `ContractEvent` variant
+pub static EVENTS_EXTRACTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_events_extracted_total",
+        "Contract events extracted from transactions",
+        &["event"]
+    )
+    .unwrap()
+});
+
+/// Rows handed to the database, by table
+pub static ROWS_WRITTEN: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_rows_written_total",
+        "Rows written to the database",
+        &["table"]
+    )
+    .unwrap()
+});
+
+/// Time a step spends on one batch, by step
+pub static STEP_BATCH_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "indexer_step_batch_latency_seconds",
+        "Time a processor step spends on one batch",
+        &["step"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap()
+});
+
+/// Time a chunked batch spends writing a table, by table
+pub static TABLE_WRITE_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "indexer_table_write_latency_seconds",
+        "Time the Storer spends writing one table of a batch",
+        &["table"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap()
+});
+
+/// Storer queries run again after a transient error, by query and error
+pub static QUERY_RETRIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_query_retries_total",
+        "Storer queries retried after a transient database error",
+        &["query", "error"]
+    )
+    .unwrap()
+});
+
+/// Storer queries that failed their batch, by query and error, `fatal` when not retryable
+pub static QUERY_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_query_failures_total",
+        "Storer queries that failed after their retries or on a fatal error",
+        &["query", "error"]
+    )
+    .unwrap()
+});
+
+/// Last version saved to `processor_status`
+pub static LAST_PROCESSED_VERSION: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "indexer_last_processed_version",
+        "Last transaction version saved to processor_status"
+    )
+    .unwrap()
+});
+
+/// Seconds between the wall clock and the chain time of the last processed version
+pub static CHAIN_LAG_SECONDS: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "indexer_chain_lag_seconds",
+        "Seconds between now and the timestamp of the last processed transaction"
+    )
+    .unwrap()
+});
+
+/// Batches processed out of order and waiting for the gap before them to close
+pub static GAP_BUFFER_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "indexer_version_gap_buffer_size",
+        "Out of order batches waiting for earlier versions"
+    )
+    .unwrap()
+});
+
+/// Connections of the processor's database pool, by state
+pub static DB_POOL_CONNECTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "indexer_db_pool_connections",
+        "Connections of the processor's database pool",
+        &["state"]
+    )
+    .unwrap()
+});
+
+pub fn record_rows_written(table: &str, rows: usize) {
+    if rows > 0 {
+        ROWS_WRITTEN.with_label_values(&[table]).inc_by(rows as u64);
+    }
+}
+
+/// Samples the pool's connections in the background, `max` is the configured pool size.
+pub fn spawn_db_pool_metrics(pool: ArcDbPool, max_size: u32) {
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["max"])
+        .set(max_size as i64);
+    tokio::spawn(async move {
+        loop {
+            let state = pool.state();
+            DB_POOL_CONNECTIONS
+                .with_label_values(&["open"])
+                .set(state.connections as i64);
+            DB_POOL_CONNECTIONS
+                .with_label_values(&["idle"])
+                .set(state.idle_connections as i64);
+            DB_POOL_CONNECTIONS
+                .with_label_values(&["in_use"])
+                .set(state.connections as i64 - state.idle_connections as i64);
+            tokio::time::sleep(Duration::from_secs(DB_POOL_SAMPLE_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// All registered metrics in the Prometheus text format
+pub fn encode_metrics() -> anyhow::Result<String> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
@@ -1,6 +1,14 @@
+pub mod alert_sink;
 pub mod chain_id;
 pub mod database_connection;
 pub mod database_execution;
 pub mod database_utils;
 pub mod latest_processed_version_tracker;
+pub mod metrics;
+pub mod price_router;
+pub mod query_retry;
+pub mod shutdown;
 pub mod starting_version;
+pub mod transaction_files;
+pub mod webhook;
+pub mod write_scheduler;
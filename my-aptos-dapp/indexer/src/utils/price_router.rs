@@ -0,0 +1,225 @@
+//! Derives USD prices for every token reachable from a configured stablecoin by treating
+//! the indexed Hyperion pools as a graph. Tokens are priced hop by hop: at each hop, every
+//! unpriced token adjacent to an already priced token gets the liquidity-weighted average
+//! of the prices implied by those pools, and remembers the deepest path it was reached by.
+
+use ahash::AHashMap;
+use aptos_indexer_processor_sdk::utils::convert::standardize_address;
+
+use crate::{
+    config::indexer_processor_config::PricingConfig, db_models::hyperion_pool::HyperionPool,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutedPrice {
+    pub token_address: String,
+    /// USD value of one smallest on-chain unit of the token
+    pub price_usd: f64,
+    /// USD depth of the pools the price was derived from
+    pub liquidity_usd: f64,
+    pub anchor_token: String,
+    /// Pool addresses from the anchor stablecoin to this token
+    pub path: Vec<String>,
+}
+
+struct PoolEdge {
+    pool_address: String,
+    token0: String,
+    token1: String,
+    // token1 per token0
+    price: f64,
+    reserve0: f64,
+    reserve1: f64,
+}
+
+struct Candidate {
+    price_usd: f64,
+    weight: f64,
+    anchor_token: String,
+    path: Vec<String>,
+}
+
+pub fn route_token_prices(pools: &[HyperionPool], config: &PricingConfig) -> Vec<RoutedPrice> {
+    let edges = pools
+        .iter()
+        .filter_map(|pool| {
+            let price = pool.price();
+            let (reserve0, reserve1) = pool.virtual_reserves();
+            if !price.is_finite() || price <= 0.0 || reserve0 <= 0.0 || reserve1 <= 0.0 {
+                return None;
+            }
+            Some(PoolEdge {
+                pool_address: pool.pool_address.clone(),
+                token0: standardize_address(&pool.token0_address),
+                token1: standardize_address(&pool.token1_address),
+                price,
+                reserve0,
+                reserve1,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut prices: AHashMap<String, RoutedPrice> = AHashMap::new();
+    for stablecoin in &config.stablecoins {
+        let address = standardize_address(&stablecoin.address);
+        let price_usd = 10f64.powi(-(stablecoin.decimals as i32));
+        let liquidity_usd = edges
+            .iter()
+            .map(|edge| {
+                if edge.token0 == address {
+                    edge.reserve0 * price_usd
+                } else if edge.token1 == address {
+                    edge.reserve1 * price_usd
+                } else {
+                    0.0
+                }
+            })
+            .sum();
+        prices.insert(
+            address.clone(),
+            RoutedPrice {
+                token_address: address.clone(),
+                price_usd,
+                liquidity_usd,
+                anchor_token: address,
+                path: vec![],
+            },
+        );
+    }
+
+    for _ in 0..config.max_hops {
+        let mut candidates: AHashMap<String, Vec<Candidate>> = AHashMap::new();
+        for edge in &edges {
+            // (priced side, unpriced side, priced side reserve, unpriced tokens per priced token)
+            let directions = [
+                (&edge.token0, &edge.token1, edge.reserve0, edge.price),
+                (&edge.token1, &edge.token0, edge.reserve1, 1.0 / edge.price),
+            ];
+            for (known, unknown, known_reserve, unknown_per_known) in directions {
+                let Some(known_price) = prices.get(known) else {
+                    continue;
+                };
+                if prices.contains_key(unknown) {
+                    continue;
+                }
+                // A path is only as deep as its shallowest pool
+                let depth_usd =
+                    (known_reserve * known_price.price_usd).min(known_price.liquidity_usd);
+                if depth_usd < config.min_liquidity_usd {
+                    continue;
+                }
+                let mut path = known_price.path.clone();
+                path.push(edge.pool_address.clone());
+                candidates
+                    .entry(unknown.clone())
+                    .or_default()
+                    .push(Candidate {
+                        price_usd: known_price.price_usd / unknown_per_known,
+                        weight: depth_usd,
+                        anchor_token: known_price.anchor_token.clone(),
+                        path,
+                    });
+            }
+        }
+
+        if candidates.is_empty() {
+            break;
+        }
+
+        for (token_address, token_candidates) in candidates {
+            let total_weight = token_candidates.iter().map(|c| c.weight).sum::<f64>();
+            let price_usd = token_candidates
+                .iter()
+                .map(|c| c.price_usd * c.weight)
+                .sum::<f64>()
+                / total_weight;
+            let deepest = token_candidates
+                .into_iter()
+                .max_by(|a, b| a.weight.total_cmp(&b.weight))
+                .expect("Candidates are never empty");
+            prices.insert(
+                token_address.clone(),
+                RoutedPrice {
+                    token_address,
+                    price_usd,
+                    liquidity_usd: total_weight,
+                    anchor_token: deepest.anchor_token,
+                    path: deepest.path,
+                },
+            );
+        }
+    }
+
+    prices.into_values().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::indexer_processor_config::StablecoinConfig;
+
+    const USDC: &str = "0x1";
+    const APT: &str = "0x2";
+    const GUI: &str = "0x3";
+
+    fn pool(address: &str, token0: &str, token1: &str, price: f64, liquidity: f64) -> HyperionPool {
+        let sqrt_price_x96 = price.sqrt() * 2f64.powi(96);
+        HyperionPool {
+            pool_address: address.to_string(),
+            token0_address: token0.to_string(),
+            token1_address: token1.to_string(),
+            token0_symbol: "".to_string(),
+            token1_symbol: "".to_string(),
+            fee_tier: 3000,
+            tick_spacing: 60,
+            liquidity: format!("{:.0}", liquidity),
+            sqrt_price_x96: format!("{:.0}", sqrt_price_x96),
+            tick: 0,
+            creation_timestamp: 0,
+            last_update_timestamp: 0,
+            last_update_version: 0,
+        }
+    }
+
+    fn config() -> PricingConfig {
+        PricingConfig {
+            stablecoins: vec![StablecoinConfig {
+                address: USDC.to_string(),
+                decimals: 6,
+            }],
+            max_hops: 3,
+            min_liquidity_usd: 1_000.0,
+            refresh_interval_secs: 0,
+        }
+    }
+
+    fn price_of(prices: &[RoutedPrice], token: &str) -> Option<RoutedPrice> {
+        let token = standardize_address(token);
+        prices.iter().find(|p| p.token_address == token).cloned()
+    }
+
+    #[test]
+    fn test_routes_over_multiple_hops() {
+        // 1 APT (8 decimals) = 10 USDC (6 decimals), 1 GUI (6 decimals) = 0.01 APT
+        let pools = vec![
+            pool("0xa", APT, USDC, 0.1, 1e12),
+            pool("0xb", GUI, APT, 1.0, 1e12),
+        ];
+        let prices = route_token_prices(&pools, &config());
+
+        let apt = price_of(&prices, APT).unwrap();
+        assert!((apt.price_usd * 1e8 - 10.0).abs() < 1e-6);
+        assert_eq!(apt.path, vec!["0xa".to_string()]);
+
+        let gui = price_of(&prices, GUI).unwrap();
+        assert!((gui.price_usd * 1e6 - 0.1).abs() < 1e-6);
+        assert_eq!(gui.path, vec!["0xa".to_string(), "0xb".to_string()]);
+    }
+
+    #[test]
+    fn test_skips_shallow_pools() {
+        let pools = vec![pool("0xa", APT, USDC, 0.1, 1_000.0)];
+        let prices = route_token_prices(&pools, &config());
+        assert!(price_of(&prices, APT).is_none());
+    }
+}
@@ -0,0 +1,225 @@
+use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
+use diesel::{
+    result::{DatabaseErrorKind, Error as DieselError},
+    QueryResult,
+};
+use diesel_async::{
+    pooled_connection::bb8::RunError, scoped_futures::ScopedBoxFuture, AsyncPgConnection,
+};
+use std::{collections::hash_map::RandomState, fmt, hash::BuildHasher, time::Duration};
+
+use super::{
+    database_utils::ArcDbPool,
+    metrics::{QUERY_FAILURES, QUERY_RETRIES},
+};
+use crate::config::indexer_processor_config::DbConfig;
+
+/// How storer queries are retried after a transient error, handed to the storers with the
+/// rest of their config.
+#[derive(Clone, Copy, Debug)]
+pub struct QueryRetryPolicy {
+    pub retries: u32,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl QueryRetryPolicy {
+    pub fn from_config(db_config: &DbConfig) -> Self {
+        Self {
+            retries: db_config.query_retries,
+            initial_delay_ms: db_config.query_retry_delay_ms,
+            max_delay_ms: db_config.query_max_retry_delay_ms,
+        }
+    }
+
+    /// Delay before retry `attempt`, counted from 1. It doubles on every attempt up to
+    /// `max_delay_ms`, and is jittered over its upper half so that the chunks of a batch
+    /// failing together don't retry in lockstep.
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_delay_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(20))
+            .min(self.max_delay_ms);
+        let jitter = RandomState::new().hash_one(attempt) % (delay / 2 + 1);
+        Duration::from_millis(delay - delay / 2 + jitter)
+    }
+}
+
+impl Default for QueryRetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DbConfig::default_query_retries(),
+            initial_delay_ms: DbConfig::default_query_retry_delay_ms(),
+            max_delay_ms: DbConfig::default_query_max_retry_delay_ms(),
+        }
+    }
+}
+
+enum AttemptError {
+    Pool(RunError),
+    Query(DieselError),
+}
+
+impl AttemptError {
+    /// Metric label of an error known to have left nothing behind, None otherwise. Postgres
+    /// rolls back the transaction on a serialization failure (40001) or a deadlock (40P01),
+    /// and a pool timeout happens before any query is sent. A dropped connection may have
+    /// lost the reply to a commit, and the counter upserts of `user_stats` and `trader_stats`
+    /// would be applied twice if it had gone through, so it is not retried.
+    fn transient_reason(&self) -> Option<&'static str> {
+        match self {
+            AttemptError::Pool(RunError::TimedOut) => Some("pool_timeout"),
+            AttemptError::Query(DieselError::DatabaseError(kind, info)) => match kind {
+                DatabaseErrorKind::SerializationFailure => Some("serialization_failure"),
+                // Deadlocks have no kind of their own, Postgres reports 40P01 as Unknown
+                DatabaseErrorKind::Unknown if info.message().contains("deadlock detected") => {
+                    Some("deadlock")
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Metric label of an error that fails the query. Connection errors get their own label,
+    /// so dropped connections can be told apart from queries that are actually wrong.
+    fn failure_reason(&self) -> &'static str {
+        match self {
+            AttemptError::Pool(RunError::User(_))
+            | AttemptError::Query(DieselError::DatabaseError(
+                DatabaseErrorKind::ClosedConnection | DatabaseErrorKind::UnableToSendCommand,
+                _,
+            )) => "connection",
+            _ => self.transient_reason().unwrap_or("fatal"),
+        }
+    }
+}
+
+impl fmt::Display for AttemptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttemptError::Pool(e) => write!(f, "Failed to get connection from pool: {}", e),
+            AttemptError::Query(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<AttemptError> for ProcessorError {
+    fn from(error: AttemptError) -> Self {
+        match error {
+            AttemptError::Pool(_) => ProcessorError::DBStoreError {
+                message: error.to_string(),
+                query: None,
+            },
+            AttemptError::Query(_) => ProcessorError::ProcessError {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+/// Runs `query` on a connection of `pool`. When the pool times out or the query fails on a
+/// serialization failure or a deadlock, it is run again on a fresh connection after a backoff,
+/// up to `policy.retries` times. `query` must be safe to run twice, which holds for a single
+/// transaction since the failed attempt was rolled back.
+pub async fn with_query_retries<'a, T, F>(
+    pool: &ArcDbPool,
+    policy: QueryRetryPolicy,
+    name: &'static str,
+    mut query: F,
+) -> Result<T, ProcessorError>
+where
+    F: for<'c> FnMut(&'c mut AsyncPgConnection) -> ScopedBoxFuture<'a, 'c, QueryResult<T>>
+        + Send
+        + 'a,
+    T: Send + 'a,
+{
+    let mut attempt = 0;
+    loop {
+        let error = match pool.get().await {
+            Ok(mut conn) => match query(&mut *conn).await {
+                Ok(result) => return Ok(result),
+                Err(e) => AttemptError::Query(e),
+            },
+            Err(e) => AttemptError::Pool(e),
+        };
+        attempt += 1;
+        match error.transient_reason() {
+            Some(reason) if attempt <= policy.retries => {
+                QUERY_RETRIES.with_label_values(&[name, reason]).inc();
+                let delay = policy.delay(attempt);
+                tracing::warn!(
+                    "Retrying {} in {:?}, attempt {} of {}: {}",
+                    name,
+                    delay,
+                    attempt,
+                    policy.retries,
+                    error
+                );
+                tokio::time::sleep(delay).await;
+            }
+            _ => {
+                QUERY_FAILURES
+                    .with_label_values(&[name, error.failure_reason()])
+                    .inc();
+                tracing::warn!("Error running {}: {}", name, error);
+                return Err(error.into());
+            }
+        }
+    }
+}
+
+/// Error of a storer task that panicked or was cancelled before its query completed.
+pub fn task_error(error: tokio::task::JoinError) -> ProcessorError {
+    ProcessorError::ProcessError {
+        message: format!("Task panicked executing in chunks: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delay_doubles_up_to_the_max_with_jitter() {
+        let policy = QueryRetryPolicy {
+            retries: 5,
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+        };
+        for (attempt, delay_ms) in [
+            (1, 100),
+            (2, 200),
+            (3, 400),
+            (4, 800),
+            (5, 1_000),
+            (40, 1_000),
+        ] {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_millis(delay_ms - delay_ms / 2));
+            assert!(delay <= Duration::from_millis(delay_ms));
+        }
+    }
+
+    #[test]
+    fn test_closed_connections_fail_with_their_own_reason() {
+        let error = |kind, message: &str| {
+            AttemptError::Query(DieselError::DatabaseError(
+                kind,
+                Box::new(message.to_string()),
+            ))
+        };
+        for kind in [
+            DatabaseErrorKind::ClosedConnection,
+            DatabaseErrorKind::UnableToSendCommand,
+        ] {
+            let closed = error(kind, "connection reset by peer");
+            assert_eq!(closed.transient_reason(), None);
+            assert_eq!(closed.failure_reason(), "connection");
+        }
+        let deadlock = error(DatabaseErrorKind::Unknown, "deadlock detected");
+        assert_eq!(deadlock.failure_reason(), "deadlock");
+        let violation = error(DatabaseErrorKind::UniqueViolation, "duplicate key");
+        assert_eq!(violation.failure_reason(), "fatal");
+    }
+}
@@ -0,0 +1,40 @@
+use once_cell::sync::Lazy;
+use tokio::sync::watch;
+
+// Set once a shutdown is requested, never cleared
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+/// Asks every pipeline of the process to stop reading transactions and drain.
+pub fn request_shutdown() {
+    SHUTDOWN.send_replace(true);
+}
+
+pub fn is_shutdown_requested() -> bool {
+    *SHUTDOWN.borrow()
+}
+
+/// Resolves once a shutdown is requested.
+pub async fn shutdown_requested() {
+    let mut receiver = SHUTDOWN.subscribe();
+    // The sender is static, so the channel never closes
+    let _ = receiver.wait_for(|requested| *requested).await;
+}
+
+/// Resolves on SIGTERM, as sent by Cloud Run and Kubernetes, or on Ctrl-C.
+pub async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm =
+            signal(SignalKind::terminate()).expect("Failed to install the SIGTERM handler");
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
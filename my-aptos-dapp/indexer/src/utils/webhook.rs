@@ -0,0 +1,517 @@
+//! Webhook delivery of alerts and selected events. `WebhookSink` only enqueues payloads in
+//! `webhook_outbox`, alerts in the transaction that stores them, and a `WebhookDispatcher` per
+//! endpoint POSTs the due rows with an HMAC-SHA256 signature, retrying failures with
+//! exponential backoff. Deliveries survive restarts and a slow or unreachable endpoint never
+//! blocks indexing.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use diesel::{insert_into, update, upsert::excluded, ExpressionMethods, QueryDsl, QueryResult};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use std::time::Duration;
+
+use super::{
+    alert_sink::{AlertSink, SinkEvent},
+    database_connection::get_db_connection,
+    database_utils::ArcDbPool,
+};
+use crate::{
+    config::indexer_processor_config::{AlertSinkConfig, WebhookSinkConfig},
+    db_models::{
+        alert::Alert,
+        webhook_endpoint_status::WebhookEndpointStatus,
+        webhook_outbox::{
+            NewWebhookOutboxEntry, WebhookOutboxEntry, WEBHOOK_STATUS_DELIVERED,
+            WEBHOOK_STATUS_FAILED, WEBHOOK_STATUS_PENDING,
+        },
+    },
+    schema::{webhook_endpoint_status, webhook_outbox},
+};
+
+/// `sha256=<hex HMAC-SHA256 of "{timestamp}.{body}">`, keyed with the endpoint secret
+pub const SIGNATURE_HEADER: &str = "X-Indexer-Signature";
+/// Unix seconds the request was signed at, receivers should reject stale timestamps
+pub const TIMESTAMP_HEADER: &str = "X-Indexer-Timestamp";
+/// Alert or event id, identical across retries so receivers can deduplicate
+pub const DELIVERY_ID_HEADER: &str = "X-Indexer-Delivery";
+
+// Outbox rows delivered per poll of an endpoint
+const DISPATCH_BATCH_SIZE: i64 = 100;
+// Added to the time a claimed batch can take to deliver, before its rows are due again
+const CLAIM_LEASE_MARGIN_SECS: i64 = 60;
+// Rows per insert, well under the Postgres bind parameter limit
+const ENQUEUE_CHUNK_SIZE: usize = 1_000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+pub fn sign_payload(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Delay before the next attempt once `attempts` deliveries have failed.
+pub fn backoff_ms(config: &WebhookSinkConfig, attempts: i32) -> u64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 32) as u32;
+    config
+        .initial_backoff_ms
+        .saturating_mul(1u64 << exponent)
+        .min(config.max_backoff_ms)
+}
+
+/// POSTs a JSON payload with the signature headers. Non 2xx responses are errors.
+pub async fn post_signed(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    delivery_id: &str,
+    payload: &str,
+) -> Result<()> {
+    let timestamp = now_secs();
+    client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign_payload(secret, timestamp, payload))
+        .header(DELIVERY_ID_HEADER, delivery_id)
+        .body(payload.to_string())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Inserts outbox rows, skipping the ones already enqueued for their endpoint.
+pub async fn enqueue_outbox_entries(
+    conn: &mut AsyncPgConnection,
+    entries: &[NewWebhookOutboxEntry],
+) -> QueryResult<()> {
+    for chunk in entries.chunks(ENQUEUE_CHUNK_SIZE) {
+        insert_into(webhook_outbox::table)
+            .values(chunk)
+            .on_conflict((webhook_outbox::endpoint, webhook_outbox::dedup_key))
+            .do_nothing()
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Enqueues alerts and subscribed events in the outbox of one endpoint.
+pub struct WebhookSink {
+    pool: ArcDbPool,
+    config: WebhookSinkConfig,
+}
+
+impl WebhookSink {
+    pub fn new(pool: ArcDbPool, config: WebhookSinkConfig) -> Self {
+        Self { pool, config }
+    }
+
+    async fn enqueue(&self, entries: Vec<NewWebhookOutboxEntry>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let conn = &mut get_db_connection(&self.pool).await?;
+        enqueue_outbox_entries(conn, &entries).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook:{}", self.config.name)
+    }
+
+    fn outbox_entries(&self, alerts: &[Alert], now: i64) -> Vec<NewWebhookOutboxEntry> {
+        alerts
+            .iter()
+            .filter(|alert| {
+                self.config.alert_types.is_empty()
+                    || self.config.alert_types.contains(&alert.alert_type)
+            })
+            .map(|alert| {
+                let payload = json!({ "id": alert.alert_id, "type": "alert", "data": alert });
+                NewWebhookOutboxEntry::new(
+                    self.config.name.clone(),
+                    alert.alert_id.clone(),
+                    payload.to_string(),
+                    now,
+                )
+            })
+            .collect()
+    }
+
+    async fn send_events(&self, events: &[SinkEvent]) -> Result<()> {
+        let now = now_secs();
+        let mut entries = vec![];
+        for event in events {
+            if !self.config.events.contains(&event.kind()) {
+                continue;
+            }
+            let data = match event {
+                SinkEvent::PoolCreated(pool) => serde_json::to_value(pool)?,
+                SinkEvent::Swap(swap) => serde_json::to_value(swap)?,
+            };
+            let id = event.id();
+            let payload = json!({ "id": id, "type": event.kind(), "data": data });
+            entries.push(NewWebhookOutboxEntry::new(
+                self.config.name.clone(),
+                id,
+                payload.to_string(),
+                now,
+            ));
+        }
+        self.enqueue(entries).await
+    }
+}
+
+/// Delivers the outbox of one endpoint and keeps its `webhook_endpoint_status` up to date.
+pub struct WebhookDispatcher {
+    pool: ArcDbPool,
+    config: WebhookSinkConfig,
+    client: reqwest::Client,
+}
+
+impl WebhookDispatcher {
+    pub fn new(pool: ArcDbPool, config: WebhookSinkConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+        Ok(Self {
+            pool,
+            config,
+            client,
+        })
+    }
+
+    pub async fn run(self) {
+        // Status updates assume the endpoint row exists
+        while let Err(e) = self.register_endpoint().await {
+            tracing::warn!("Webhook {} failed to register: {:?}", self.config.name, e);
+            tokio::time::sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+        loop {
+            match self.dispatch_due().await {
+                // A full page means more rows may already be due
+                Ok(attempted) if attempted as i64 == DISPATCH_BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Webhook {} dispatch failed: {:?}", self.config.name, e),
+            }
+            tokio::time::sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+    }
+
+    pub async fn register_endpoint(&self) -> Result<()> {
+        let conn = &mut get_db_connection(&self.pool).await?;
+        insert_into(webhook_endpoint_status::table)
+            .values(WebhookEndpointStatus {
+                endpoint: self.config.name.clone(),
+                url: self.config.url.clone(),
+                delivered_count: 0,
+                failed_count: 0,
+                consecutive_failures: 0,
+                last_success_at: None,
+                last_failure_at: None,
+                last_error: None,
+            })
+            .on_conflict(webhook_endpoint_status::endpoint)
+            .do_update()
+            .set(webhook_endpoint_status::url.eq(excluded(webhook_endpoint_status::url)))
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Attempts every due row once, oldest first, and returns how many were attempted.
+    pub async fn dispatch_due(&self) -> Result<usize> {
+        let entries = self.claim_due().await?;
+        // No connection is held while waiting on the endpoint
+        for entry in &entries {
+            let result = post_signed(
+                &self.client,
+                &self.config.url,
+                &self.config.secret,
+                &entry.dedup_key,
+                &entry.payload,
+            )
+            .await;
+            match result {
+                Ok(()) => self.record_success(entry).await?,
+                Err(e) => self.record_failure(entry, format!("{:#}", e)).await?,
+            }
+        }
+        Ok(entries.len())
+    }
+
+    /// Claims a batch of due rows, so that processors sharing the outbox never POST the same
+    /// row. Rows locked by another claim are skipped, and the claimed rows are leased by moving
+    /// their next attempt past the time the batch can take to deliver. The delivery outcome
+    /// replaces the lease, and rows of a dispatcher that died meanwhile are due again once it
+    /// runs out.
+    async fn claim_due(&self) -> Result<Vec<WebhookOutboxEntry>> {
+        let now = now_secs();
+        let leased_until = now
+            + (self.config.timeout_ms * DISPATCH_BATCH_SIZE as u64).div_ceil(1_000) as i64
+            + CLAIM_LEASE_MARGIN_SECS;
+        let endpoint = self.config.name.clone();
+        let conn = &mut get_db_connection(&self.pool).await?;
+        let entries = conn
+            .transaction(|conn| {
+                Box::pin(async move {
+                    let entries = webhook_outbox::table
+                        .filter(webhook_outbox::endpoint.eq(endpoint))
+                        .filter(webhook_outbox::status.eq(WEBHOOK_STATUS_PENDING))
+                        .filter(webhook_outbox::next_attempt_at.le(now))
+                        .order(webhook_outbox::id)
+                        .limit(DISPATCH_BATCH_SIZE)
+                        .for_update()
+                        .skip_locked()
+                        .load::<WebhookOutboxEntry>(conn)
+                        .await?;
+                    let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+                    update(webhook_outbox::table.filter(webhook_outbox::id.eq_any(ids)))
+                        .set(webhook_outbox::next_attempt_at.eq(leased_until))
+                        .execute(conn)
+                        .await?;
+                    QueryResult::Ok(entries)
+                })
+            })
+            .await?;
+        Ok(entries)
+    }
+
+    async fn record_success(&self, entry: &WebhookOutboxEntry) -> Result<()> {
+        let now = now_secs();
+        let id = entry.id;
+        let endpoint = self.config.name.clone();
+        let conn = &mut get_db_connection(&self.pool).await?;
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                update(webhook_outbox::table.find(id))
+                    .set((
+                        webhook_outbox::status.eq(WEBHOOK_STATUS_DELIVERED),
+                        webhook_outbox::attempts.eq(webhook_outbox::attempts + 1),
+                        webhook_outbox::delivered_at.eq(Some(now)),
+                        webhook_outbox::last_error.eq(None::<String>),
+                    ))
+                    .execute(conn)
+                    .await?;
+                update(webhook_endpoint_status::table.find(endpoint))
+                    .set((
+                        webhook_endpoint_status::delivered_count
+                            .eq(webhook_endpoint_status::delivered_count + 1),
+                        webhook_endpoint_status::consecutive_failures.eq(0),
+                        webhook_endpoint_status::last_success_at.eq(Some(now)),
+                    ))
+                    .execute(conn)
+                    .await?;
+                diesel::QueryResult::Ok(())
+            })
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn record_failure(&self, entry: &WebhookOutboxEntry, error: String) -> Result<()> {
+        let now = now_secs();
+        let attempts = entry.attempts + 1;
+        let gave_up = attempts >= self.config.max_attempts;
+        let (status, next_attempt_at) = if gave_up {
+            (WEBHOOK_STATUS_FAILED, entry.next_attempt_at)
+        } else {
+            let delay_secs = backoff_ms(&self.config, attempts).div_ceil(1_000) as i64;
+            (WEBHOOK_STATUS_PENDING, now + delay_secs)
+        };
+        tracing::warn!(
+            endpoint = %self.config.name,
+            dedup_key = %entry.dedup_key,
+            attempts,
+            gave_up,
+            "Webhook delivery failed: {}",
+            error
+        );
+
+        let id = entry.id;
+        let endpoint = self.config.name.clone();
+        let conn = &mut get_db_connection(&self.pool).await?;
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                update(webhook_outbox::table.find(id))
+                    .set((
+                        webhook_outbox::status.eq(status),
+                        webhook_outbox::attempts.eq(attempts),
+                        webhook_outbox::next_attempt_at.eq(next_attempt_at),
+                        webhook_outbox::last_error.eq(Some(error.clone())),
+                    ))
+                    .execute(conn)
+                    .await?;
+                update(webhook_endpoint_status::table.find(endpoint))
+                    .set((
+                        webhook_endpoint_status::failed_count
+                            .eq(webhook_endpoint_status::failed_count + i64::from(gave_up)),
+                        webhook_endpoint_status::consecutive_failures
+                            .eq(webhook_endpoint_status::consecutive_failures + 1),
+                        webhook_endpoint_status::last_failure_at.eq(Some(now)),
+                        webhook_endpoint_status::last_error.eq(Some(error)),
+                    ))
+                    .execute(conn)
+                    .await?;
+                diesel::QueryResult::Ok(())
+            })
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+/// Starts a background dispatcher for every webhook sink in the config.
+pub fn spawn_webhook_dispatchers(configs: &[AlertSinkConfig], pool: ArcDbPool) -> Result<()> {
+    for config in configs {
+        if let AlertSinkConfig::Webhook(webhook_config) = config {
+            let dispatcher = WebhookDispatcher::new(pool.clone(), webhook_config.clone())?;
+            tokio::spawn(dispatcher.run());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    fn test_config(url: String) -> WebhookSinkConfig {
+        WebhookSinkConfig {
+            name: "test".to_string(),
+            url,
+            secret: "s3cret".to_string(),
+            alert_types: vec![],
+            events: vec![],
+            max_attempts: WebhookSinkConfig::default_max_attempts(),
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 10_000,
+            timeout_ms: 5_000,
+            poll_interval_ms: WebhookSinkConfig::default_poll_interval_ms(),
+        }
+    }
+
+    /// Local HTTP stand-in that answers one request with `status` and returns the raw request.
+    async fn serve_once(status: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text[..header_end]
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|value| value.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            socket
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                        status
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> &'a str {
+        let prefix = format!("{}:", name.to_ascii_lowercase());
+        request
+            .lines()
+            .find(|line| line.to_ascii_lowercase().starts_with(&prefix))
+            .map(|line| line[prefix.len()..].trim())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_signed_sends_verifiable_signature() {
+        let (url, server) = serve_once("200 OK").await;
+        let config = test_config(url);
+        let payload = r#"{"id":"large_swap-1","type":"alert"}"#;
+        post_signed(
+            &reqwest::Client::new(),
+            &config.url,
+            &config.secret,
+            "large_swap-1",
+            payload,
+        )
+        .await
+        .unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.ends_with(payload));
+        assert_eq!(header(&request, DELIVERY_ID_HEADER), "large_swap-1");
+        let timestamp = header(&request, TIMESTAMP_HEADER).parse::<i64>().unwrap();
+        assert_eq!(
+            header(&request, SIGNATURE_HEADER),
+            sign_payload(&config.secret, timestamp, payload)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_signed_fails_on_error_status() {
+        let (url, server) = serve_once("500 Internal Server Error").await;
+        let result = post_signed(&reqwest::Client::new(), &url, "s3cret", "id", "{}").await;
+        assert!(result.is_err());
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_sign_payload_depends_on_secret_and_timestamp() {
+        let signature = sign_payload("s3cret", 1_700_000_000, "{}");
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_ne!(signature, sign_payload("other", 1_700_000_000, "{}"));
+        assert_ne!(signature, sign_payload("s3cret", 1_700_000_001, "{}"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = test_config(String::new());
+        assert_eq!(backoff_ms(&config, 1), 1_000);
+        assert_eq!(backoff_ms(&config, 2), 2_000);
+        assert_eq!(backoff_ms(&config, 4), 8_000);
+        assert_eq!(backoff_ms(&config, 5), 10_000);
+        assert_eq!(backoff_ms(&config, 100), 10_000);
+    }
+}
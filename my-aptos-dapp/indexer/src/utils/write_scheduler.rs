@@ -0,0 +1,135 @@
+use aptos_indexer_processor_sdk::utils::errors::ProcessorError;
+use futures_util::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use std::{collections::HashSet, future::Future, time::Instant};
+
+use super::metrics::TABLE_WRITE_LATENCY;
+
+struct ScheduledWrite<'a> {
+    name: &'static str,
+    table: &'static str,
+    after: Vec<&'static str>,
+    write: BoxFuture<'a, Result<(), ProcessorError>>,
+}
+
+/// Runs the writes of a batch concurrently, each one once the writes it comes after are done.
+/// Writes that touch disjoint tables share no dependency and run side by side on the pool.
+#[derive(Default)]
+pub struct WriteScheduler<'a> {
+    writes: Vec<ScheduledWrite<'a>>,
+}
+
+impl<'a> WriteScheduler<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the write `name` to `table`, started once every write of `after` is done. A
+    /// dependency that is never added, e.g. a write skipped for this batch, counts as done.
+    pub fn add(
+        &mut self,
+        name: &'static str,
+        table: &'static str,
+        after: &[&'static str],
+        write: impl Future<Output = Result<(), ProcessorError>> + Send + 'a,
+    ) {
+        self.writes.push(ScheduledWrite {
+            name,
+            table,
+            after: after.to_vec(),
+            write: write.boxed(),
+        });
+    }
+
+    /// Runs every write and returns the first error, the writes not started yet are dropped.
+    /// The latency of each write is recorded under its table.
+    pub async fn run(self) -> Result<(), ProcessorError> {
+        let names = self.writes.iter().map(|w| w.name).collect::<HashSet<_>>();
+        let mut pending = self
+            .writes
+            .into_iter()
+            .map(|mut w| {
+                w.after.retain(|dependency| names.contains(dependency));
+                w
+            })
+            .collect::<Vec<_>>();
+        let mut done = HashSet::new();
+        let mut running = FuturesUnordered::new();
+        loop {
+            let (ready, waiting): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|w| w.after.iter().all(|dependency| done.contains(dependency)));
+            pending = waiting;
+            for w in ready {
+                running.push(async move {
+                    let start = Instant::now();
+                    let result = w.write.await;
+                    TABLE_WRITE_LATENCY
+                        .with_label_values(&[w.table])
+                        .observe(start.elapsed().as_secs_f64());
+                    result.map(|()| w.name)
+                });
+            }
+            match running.next().await {
+                Some(result) => {
+                    done.insert(result?);
+                }
+                None if pending.is_empty() => return Ok(()),
+                None => {
+                    return Err(ProcessorError::ProcessError {
+                        message: format!(
+                            "Writes {:?} depend on each other",
+                            pending.iter().map(|w| w.name).collect::<Vec<_>>()
+                        ),
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    fn logged(
+        log: &Arc<Mutex<Vec<&'static str>>>,
+        name: &'static str,
+        delay_ms: u64,
+    ) -> impl Future<Output = Result<(), ProcessorError>> + Send + 'static {
+        let log = log.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            log.lock().unwrap().push(name);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_independent_writes_concurrently_and_ordered_ones_in_order() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut scheduler = WriteScheduler::new();
+        scheduler.add("create", "trades", &[], logged(&log, "create", 50));
+        scheduler.add("update", "trades", &["create"], logged(&log, "update", 0));
+        scheduler.add(
+            "cancel",
+            "trades",
+            &["update", "skipped"],
+            logged(&log, "cancel", 0),
+        );
+        scheduler.add("messages", "messages", &[], logged(&log, "messages", 10));
+        scheduler.run().await.unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["messages", "create", "update", "cancel"]
+        );
+
+        let mut scheduler = WriteScheduler::new();
+        scheduler.add("a", "a", &["b"], logged(&log, "a", 0));
+        scheduler.add("b", "b", &["a"], logged(&log, "b", 0));
+        assert!(scheduler.run().await.is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Builders for the `Transaction` protos the integration tests push through the pipeline.
+
+use aptos_indexer_processor_sdk::aptos_protos::{
+    transaction::v1::{
+        transaction::{TransactionType, TxnData},
+        write_set_change::{Change, Type as WriteSetChangeType},
+        Event, MoveModule, MoveModuleBytecode, Transaction, TransactionInfo, UserTransaction,
+        UserTransactionRequest, WriteModule, WriteResource, WriteSetChange,
+    },
+    util::timestamp::Timestamp,
+};
+use serde_json::{json, Value};
+
+/// Hyperion is deployed at the address the extractor matches `pool_v3` events against, the
+/// trade and message modules are indexed under the same address.
+pub const CONTRACT_ADDRESS: &str =
+    "0x8b4a2c4bb53857c718a04c020b98f8c2e1f99a68b0f57389a8bf5434cd22e05c";
+
+/// `sqrt_price_x96` of a price of 1.
+pub const SQRT_PRICE_ONE: &str = "79228162514264337593543950336";
+
+/// A 64 hex digit address ending in `suffix`, as emitted by the chain.
+pub fn address(suffix: &str) -> String {
+    format!("0x{:0>64}", suffix)
+}
+
+/// An event of `module::name` emitted by `address`.
+pub fn event_at(address: &str, module_event: &str, data: Value) -> Event {
+    Event {
+        type_str: format!("{}::{}", address, module_event),
+        data: data.to_string(),
+        ..Default::default()
+    }
+}
+
+/// An event of `module::name` emitted by the indexed contract.
+pub fn event(module_event: &str, data: Value) -> Event {
+    event_at(CONTRACT_ADDRESS, module_event, data)
+}
+
+/// A successful user transaction at `version`, committed at `timestamp` in chain seconds.
+pub fn user_transaction(version: u64, timestamp: i64, events: Vec<Event>) -> Transaction {
+    Transaction {
+        version,
+        timestamp: Some(Timestamp {
+            seconds: timestamp,
+            nanos: 0,
+        }),
+        r#type: TransactionType::User as i32,
+        info: Some(TransactionInfo {
+            success: true,
+            ..Default::default()
+        }),
+        txn_data: Some(TxnData::User(UserTransaction {
+            request: Some(UserTransactionRequest {
+                sender: address("5e4d"),
+                ..Default::default()
+            }),
+            events,
+        })),
+        ..Default::default()
+    }
+}
+
+/// A user transaction that aborted, its events must not be indexed.
+pub fn failed_transaction(version: u64, timestamp: i64, events: Vec<Event>) -> Transaction {
+    let mut transaction = user_transaction(version, timestamp, events);
+    transaction.info.as_mut().unwrap().success = false;
+    transaction
+}
+
+/// A publish of `package_name` with `modules` at `upgrade_number`: the module bytecode
+/// writes and the updated `PackageRegistry` of the contract.
+pub fn upgrade_transaction(
+    version: u64,
+    timestamp: i64,
+    package_name: &str,
+    modules: &[&str],
+    upgrade_number: u64,
+) -> Transaction {
+    let mut transaction = user_transaction(version, timestamp, vec![]);
+    let mut changes = modules
+        .iter()
+        .map(|module| WriteSetChange {
+            r#type: WriteSetChangeType::WriteModule as i32,
+            change: Some(Change::WriteModule(WriteModule {
+                address: CONTRACT_ADDRESS.to_string(),
+                data: Some(MoveModuleBytecode {
+                    bytecode: module.as_bytes().to_vec(),
+                    abi: Some(MoveModule {
+                        address: CONTRACT_ADDRESS.to_string(),
+                        name: module.to_string(),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            })),
+        })
+        .collect::<Vec<_>>();
+    let registry = json!({
+        "packages": [{
+            "manifest": "0x",
+            "modules": modules
+                .iter()
+                .map(|module| json!({ "name": module, "source": "0x" }))
+                .collect::<Vec<_>>(),
+            "name": package_name,
+            "source_digest": "DIGEST",
+            "upgrade_number": upgrade_number.to_string(),
+            "upgrade_policy": { "policy": 1 },
+        }]
+    });
+    changes.push(WriteSetChange {
+        r#type: WriteSetChangeType::WriteResource as i32,
+        change: Some(Change::WriteResource(WriteResource {
+            address: CONTRACT_ADDRESS.to_string(),
+            type_str: "0x1::code::PackageRegistry".to_string(),
+            data: registry.to_string(),
+            ..Default::default()
+        })),
+    });
+    transaction.info.as_mut().unwrap().changes = changes;
+    transaction
+}
+
+pub fn message_event(
+    kind: &str,
+    message_obj_addr: &str,
+    creator: &str,
+    content: &str,
+    creation_timestamp: i64,
+    last_update_timestamp: i64,
+) -> Event {
+    event(
+        &format!("custom_indexer_ex_message_board::{}MessageEvent", kind),
+        json!({
+            "message_obj_addr": message_obj_addr,
+            "message": {
+                "creator": creator,
+                "content": content,
+                "creation_timestamp": creation_timestamp.to_string(),
+                "last_update_timestamp": last_update_timestamp.to_string(),
+            },
+        }),
+    )
+}
+
+/// A trade_radar event, the full trade state as of `last_update_timestamp`.
+pub struct TradeFixture<'a> {
+    pub trade_obj_addr: &'a str,
+    pub trader: &'a str,
+    pub trade_type: u8,
+    pub amount_from: i64,
+    pub amount_to: i64,
+    pub price: i64,
+    pub status: u8,
+    pub creation_timestamp: i64,
+    pub last_update_timestamp: i64,
+    pub notes: &'a str,
+}
+
+impl TradeFixture<'_> {
+    pub fn event(&self, kind: &str) -> Event {
+        event(
+            &format!("trade_radar::{}TradeEvent", kind),
+            json!({
+                "trade_obj_addr": self.trade_obj_addr,
+                "trader": self.trader,
+                "trade_type": self.trade_type,
+                "token_from": "0x1::aptos_coin::AptosCoin",
+                "token_to": address("cafe"),
+                "amount_from": self.amount_from.to_string(),
+                "amount_to": self.amount_to.to_string(),
+                "price": self.price.to_string(),
+                "status": self.status,
+                "creation_timestamp": self.creation_timestamp.to_string(),
+                "last_update_timestamp": self.last_update_timestamp.to_string(),
+                "notes": self.notes,
+            }),
+        )
+    }
+}
+
+pub fn pool_created_event(pool: &str, token0: &str, token1: &str, timestamp: i64) -> Event {
+    event(
+        "pool_v3::PoolCreatedEvent",
+        json!({
+            "pool_address": pool,
+            "token0": token0,
+            "token1": token1,
+            "token0_symbol": "T0",
+            "token1_symbol": "T1",
+            "fee": "3000",
+            "tick_spacing": "60",
+            "sqrt_price_x96": SQRT_PRICE_ONE,
+            "tick": "0",
+            "timestamp": timestamp.to_string(),
+        }),
+    )
+}
+
+pub fn pool_state_update_event(pool: &str, liquidity: &str, timestamp: i64) -> Event {
+    event(
+        "pool_v3::PoolStateUpdateEvent",
+        json!({
+            "pool_address": pool,
+            "liquidity": liquidity,
+            "sqrt_price_x96": SQRT_PRICE_ONE,
+            "tick": "0",
+            "timestamp": timestamp.to_string(),
+        }),
+    )
+}
+
+pub struct SwapFixture<'a> {
+    pub pool: &'a str,
+    pub sender: &'a str,
+    pub token_in: &'a str,
+    pub token_out: &'a str,
+    pub amount_in: &'a str,
+    pub amount_out: &'a str,
+    pub sqrt_price_x96: &'a str,
+    pub liquidity: &'a str,
+    pub timestamp: i64,
+}
+
+impl SwapFixture<'_> {
+    pub fn event(&self) -> Event {
+        event(
+            "pool_v3::SwapEvent",
+            json!({
+                "pool": self.pool,
+                "sender": self.sender,
+                "recipient": self.sender,
+                "token_in": self.token_in,
+                "token_out": self.token_out,
+                "amount_in": self.amount_in,
+                "amount_out": self.amount_out,
+                "sqrt_price_x96": self.sqrt_price_x96,
+                "liquidity": self.liquidity,
+                "tick": "0",
+                "timestamp": self.timestamp.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,160 @@
+pub mod fixtures;
+
+use aptos_indexer_processor_sdk::{
+    aptos_protos::transaction::v1::Transaction,
+    traits::Processable,
+    types::transaction_context::{TransactionContext, TransactionMetadata},
+};
+use indexer::{
+    config::{
+        indexer_processor_config::{BatchCommitMode, MevConfig, PricingConfig, WashTradingConfig},
+        processor_config::ProcessorConfig,
+    },
+    steps::{extractor::Extractor, storer::Storer},
+    utils::{
+        database_connection::{get_db_connection, new_db_pool, run_migrations},
+        database_utils::{ArcDbPool, DbPoolConnection},
+        query_retry::QueryRetryPolicy,
+    },
+};
+use std::{
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use self::fixtures::CONTRACT_ADDRESS;
+
+/// `processor_status` row the Storer moves in `AtomicWithCursor` mode.
+pub const TRACKER_NAME: &str = "test_processor";
+
+static NEXT_DATABASE: AtomicUsize = AtomicUsize::new(0);
+
+/// A throwaway Postgres server in a temporary directory, stopped and deleted on drop. The
+/// server binaries are looked up in `POSTGRES_BIN_DIR`, or on the `PATH` when unset.
+pub struct TestDatabase {
+    dir: PathBuf,
+    pub pool: ArcDbPool,
+    commit_mode: BatchCommitMode,
+}
+
+impl TestDatabase {
+    /// Starts a server and runs the embedded migrations. Panics when no usable Postgres
+    /// installation is found, the tests that need one are `#[ignore]`d by default.
+    pub async fn start() -> Self {
+        if let Err(err) = Command::new(pg_binary("initdb")).arg("--version").output() {
+            panic!(
+                "initdb not found ({}), set POSTGRES_BIN_DIR to run the integration tests",
+                err
+            );
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "indexer-test-db-{}-{}",
+            std::process::id(),
+            NEXT_DATABASE.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.join("data");
+        run(Command::new(pg_binary("initdb"))
+            .arg("-D")
+            .arg(&data_dir)
+            .args(["-U", "postgres", "-A", "trust", "--no-sync"]));
+
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        run(Command::new(pg_binary("pg_ctl"))
+            .arg("-D")
+            .arg(&data_dir)
+            .arg("-o")
+            .arg(format!(
+                "-p {} -k {} -c listen_addresses=127.0.0.1 -c fsync=off",
+                port,
+                dir.display()
+            ))
+            .arg("-l")
+            .arg(dir.join("postgres.log"))
+            .args(["-w", "start"]));
+
+        let url = format!("postgresql://postgres@127.0.0.1:{}/postgres", port);
+        run_migrations(&url).await.unwrap();
+        let pool = new_db_pool(&url, 5).await;
+        Self {
+            dir,
+            pool,
+            commit_mode: BatchCommitMode::default(),
+        }
+    }
+
+    pub fn with_commit_mode(mut self, commit_mode: BatchCommitMode) -> Self {
+        self.commit_mode = commit_mode;
+        self
+    }
+
+    pub async fn conn(&self) -> DbPoolConnection<'_> {
+        get_db_connection(&self.pool).await.unwrap()
+    }
+
+    /// Pushes the transactions through the Extractor and the Storer as one batch.
+    pub async fn process(&self, transactions: Vec<Transaction>) {
+        let mut extractor = Extractor::new(
+            CONTRACT_ADDRESS.to_string(),
+            ProcessorConfig::ContractProcessor.domains().to_vec(),
+        );
+        let mut storer = Storer::new(
+            self.pool.clone(),
+            PricingConfig::default(),
+            MevConfig::default(),
+            WashTradingConfig::default(),
+            QueryRetryPolicy::default(),
+            false,
+            self.commit_mode,
+            TRACKER_NAME.to_string(),
+        );
+        let (first, last) = (&transactions[0], &transactions[transactions.len() - 1]);
+        let batch = TransactionContext {
+            metadata: TransactionMetadata {
+                start_version: first.version,
+                end_version: last.version,
+                start_transaction_timestamp: first.timestamp.clone(),
+                end_transaction_timestamp: last.timestamp.clone(),
+                total_size_in_bytes: 0,
+            },
+            data: transactions,
+        };
+        let extracted = extractor.process(batch).await.unwrap().unwrap();
+        storer.process(extracted).await.unwrap();
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let _ = Command::new(pg_binary("pg_ctl"))
+            .arg("-D")
+            .arg(self.dir.join("data"))
+            .args(["-m", "immediate", "stop"])
+            .output();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn pg_binary(name: &str) -> PathBuf {
+    match std::env::var_os("POSTGRES_BIN_DIR") {
+        Some(bin_dir) => Path::new(&bin_dir).join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+fn run(command: &mut Command) {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{:?} failed, note that Postgres refuses to run as root: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,440 @@
+//! End-to-end tests pushing fixture transactions through the Extractor and the Storer into a
+//! throwaway Postgres. They need a Postgres installation and are ignored by default.
+
+mod common;
+
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use indexer::{
+    config::indexer_processor_config::BatchCommitMode,
+    db_models::{
+        hyperion_pool::HyperionPool, hyperion_pool_stat::hour_bucket, hyperion_swap::HyperionSwap,
+        trader_stat::TraderStat, user_stat::UserStat,
+    },
+    schema::{
+        hyperion_pool_hourly_stats, hyperion_pool_hourly_traders, hyperion_pool_stats,
+        hyperion_pools, hyperion_swaps, messages, module_upgrade_history, package_upgrade_history,
+        processor_status, trader_stats, trades, user_stats,
+    },
+};
+
+use common::{fixtures::*, TestDatabase};
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_message_events() {
+    let db = TestDatabase::start().await;
+    let creator = address("a11ce");
+    let (first, second) = (address("1001"), address("1002"));
+    db.process(vec![user_transaction(
+        1,
+        100,
+        vec![
+            message_event("Create", &first, &creator, "hello", 100, 100),
+            message_event("Create", &second, &creator, "world", 100, 100),
+        ],
+    )])
+    .await;
+    // Both updates land on the same row, the later event of the transaction wins
+    db.process(vec![user_transaction(
+        2,
+        200,
+        vec![
+            message_event("Update", &first, &creator, "first edit", 100, 200),
+            message_event("Update", &first, &creator, "second edit", 100, 200),
+        ],
+    )])
+    .await;
+    {
+        let conn = &mut db.conn().await;
+        let rows = messages::table
+            .order(messages::message_obj_addr)
+            .select((
+                messages::content,
+                messages::creation_timestamp,
+                messages::last_update_timestamp,
+                messages::last_update_event_idx,
+            ))
+            .load::<(String, i64, i64, i64)>(conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("second edit".to_string(), 100, 200, 1),
+                ("world".to_string(), 100, 100, 0),
+            ]
+        );
+        let stat = user_stats::table
+            .find(&creator)
+            .first::<UserStat>(conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            (
+                stat.created_messages,
+                stat.updated_messages,
+                stat.s1_points,
+                stat.total_points,
+                stat.creation_timestamp,
+                stat.last_update_timestamp,
+            ),
+            (2, 2, 6, 6, 100, 200)
+        );
+    }
+
+    // An update older than the stored one, e.g. from a replayed range, is ignored
+    db.process(vec![user_transaction(
+        3,
+        300,
+        vec![message_event("Update", &first, &creator, "stale", 100, 150)],
+    )])
+    .await;
+    let conn = &mut db.conn().await;
+    let content = messages::table
+        .find(&first)
+        .select(messages::content)
+        .first::<String>(conn)
+        .await
+        .unwrap();
+    assert_eq!(content, "second edit");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_trade_lifecycle() {
+    let db = TestDatabase::start().await;
+    let trader = address("b0b");
+    let (buy_addr, sell_addr) = (address("7001"), address("7002"));
+    let buy = TradeFixture {
+        trade_obj_addr: &buy_addr,
+        trader: &trader,
+        trade_type: 1,
+        amount_from: 1_000,
+        amount_to: 2_000,
+        price: 500,
+        status: 0,
+        creation_timestamp: 100,
+        last_update_timestamp: 100,
+        notes: "",
+    };
+    let sell = TradeFixture {
+        trade_obj_addr: &sell_addr,
+        trade_type: 2,
+        price: 300,
+        ..buy
+    };
+    db.process(vec![user_transaction(
+        10,
+        100,
+        vec![buy.event("Create"), sell.event("Create")],
+    )])
+    .await;
+    db.process(vec![
+        user_transaction(
+            11,
+            200,
+            vec![TradeFixture {
+                price: 600,
+                notes: "updated",
+                last_update_timestamp: 200,
+                ..buy
+            }
+            .event("Update")],
+        ),
+        user_transaction(
+            12,
+            250,
+            vec![TradeFixture {
+                status: 2,
+                last_update_timestamp: 250,
+                ..sell
+            }
+            .event("Cancel")],
+        ),
+        user_transaction(
+            13,
+            300,
+            vec![TradeFixture {
+                price: 600,
+                notes: "updated",
+                status: 1,
+                last_update_timestamp: 300,
+                ..buy
+            }
+            .event("Complete")],
+        ),
+    ])
+    .await;
+
+    let conn = &mut db.conn().await;
+    let rows = trades::table
+        .order(trades::trade_obj_addr)
+        .select((
+            trades::trade_type,
+            trades::price,
+            trades::status,
+            trades::notes,
+            trades::last_update_timestamp,
+        ))
+        .load::<(i16, i64, i16, String, i64)>(conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            (1, 600, 1, "updated".to_string(), 300),
+            (2, 300, 2, "".to_string(), 250),
+        ]
+    );
+    let stat = trader_stats::table
+        .find(&trader)
+        .first::<TraderStat>(conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        (
+            stat.total_trades,
+            stat.total_buy_trades,
+            stat.total_sell_trades,
+            stat.completed_trades,
+            stat.cancelled_trades,
+            stat.total_volume,
+        ),
+        (2, 1, 1, 1, 1, 800)
+    );
+    // 10 per created trade, 2 per update and 20 per completion
+    assert_eq!(stat.points, 42);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_hyperion_pool_and_swap_events() {
+    let db = TestDatabase::start().await;
+    let pool = address("9001");
+    let (token0, token1) = (address("a0"), address("a1"));
+    let trader = address("5a9");
+    let created_at = 100 * 3_600;
+    db.process(vec![user_transaction(
+        20,
+        created_at,
+        vec![pool_created_event(&pool, &token0, &token1, created_at)],
+    )])
+    .await;
+    // State updates only carry the pool address, the tokens of the pool row stay as created
+    db.process(vec![user_transaction(
+        21,
+        created_at + 60,
+        vec![pool_state_update_event(&pool, "1000000", created_at + 60)],
+    )])
+    .await;
+    let swap = SwapFixture {
+        pool: &pool,
+        sender: &trader,
+        token_in: &token0,
+        token_out: &token1,
+        amount_in: "1000",
+        amount_out: "990",
+        sqrt_price_x96: SQRT_PRICE_ONE,
+        liquidity: "2000000",
+        timestamp: created_at + 120,
+    };
+    // Malformed pool events are skipped rather than failing the batch
+    db.process(vec![user_transaction(
+        22,
+        created_at + 120,
+        vec![
+            swap.event(),
+            event("pool_v3::SwapEvent", serde_json::json!({})),
+        ],
+    )])
+    .await;
+
+    let conn = &mut db.conn().await;
+    let stored_pool = hyperion_pools::table
+        .find(&pool)
+        .first::<HyperionPool>(conn)
+        .await
+        .unwrap();
+    assert_eq!(stored_pool.token0_address, token0);
+    assert_eq!(stored_pool.token1_address, token1);
+    assert_eq!(stored_pool.fee_tier, 3000);
+    assert_eq!(stored_pool.creation_timestamp, created_at);
+    // The swap moved the pool after the state update
+    assert_eq!(stored_pool.liquidity, "2000000");
+    assert_eq!(stored_pool.last_update_version, 22);
+
+    let swaps = hyperion_swaps::table
+        .load::<HyperionSwap>(conn)
+        .await
+        .unwrap();
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].swap_id, format!("{}-22-0", pool));
+    assert!(swaps[0].zero_for_one);
+    assert_eq!(swaps[0].sqrt_price_before, SQRT_PRICE_ONE);
+    assert_eq!(swaps[0].execution_price, "0.99");
+
+    let hourly_traders = hyperion_pool_hourly_traders::table
+        .filter(hyperion_pool_hourly_traders::pool_address.eq(&pool))
+        .count()
+        .get_result::<i64>(conn)
+        .await
+        .unwrap();
+    assert_eq!(hourly_traders, 1);
+    let swap_count = hyperion_pool_hourly_stats::table
+        .find((&pool, hour_bucket(created_at)))
+        .select(hyperion_pool_hourly_stats::swap_count)
+        .first::<i64>(conn)
+        .await
+        .unwrap();
+    assert_eq!(swap_count, 1);
+    let (swap_count_24h, unique_traders_24h) = hyperion_pool_stats::table
+        .find(&pool)
+        .select((
+            hyperion_pool_stats::swap_count_24h,
+            hyperion_pool_stats::unique_traders_24h,
+        ))
+        .first::<(i64, i64)>(conn)
+        .await
+        .unwrap();
+    assert_eq!((swap_count_24h, unique_traders_24h), (1, 1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_upgrade_history() {
+    let db = TestDatabase::start().await;
+    db.process(vec![upgrade_transaction(
+        30,
+        400,
+        "hyperion",
+        &["pool_v3", "router"],
+        1,
+    )])
+    .await;
+
+    let conn = &mut db.conn().await;
+    let modules = module_upgrade_history::table
+        .order(module_upgrade_history::module_name)
+        .select((
+            module_upgrade_history::module_name,
+            module_upgrade_history::upgrade_number,
+            module_upgrade_history::module_bytecode,
+            module_upgrade_history::tx_version,
+        ))
+        .load::<(String, i64, Vec<u8>, i64)>(conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        modules,
+        vec![
+            ("pool_v3".to_string(), 1, b"pool_v3".to_vec(), 30),
+            ("router".to_string(), 1, b"router".to_vec(), 30),
+        ]
+    );
+    let packages = package_upgrade_history::table
+        .select((
+            package_upgrade_history::package_addr,
+            package_upgrade_history::package_name,
+            package_upgrade_history::upgrade_number,
+            package_upgrade_history::upgrade_policy,
+        ))
+        .load::<(String, String, i64, i64)>(conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        packages,
+        vec![(CONTRACT_ADDRESS.to_string(), "hyperion".to_string(), 1, 1)]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_ignores_failed_and_foreign_transactions() {
+    let db = TestDatabase::start().await;
+    let creator = address("a11ce");
+    let message = message_event("Create", &address("1001"), &creator, "hello", 100, 100);
+    let mut foreign = message.clone();
+    foreign.type_str = event_at(
+        &address("dead"),
+        "custom_indexer_ex_message_board::CreateMessageEvent",
+        serde_json::json!({}),
+    )
+    .type_str;
+    db.process(vec![
+        failed_transaction(40, 100, vec![message]),
+        user_transaction(41, 100, vec![foreign]),
+    ])
+    .await;
+
+    let conn = &mut db.conn().await;
+    let message_count = messages::table
+        .count()
+        .get_result::<i64>(conn)
+        .await
+        .unwrap();
+    let user_count = user_stats::table
+        .count()
+        .get_result::<i64>(conn)
+        .await
+        .unwrap();
+    assert_eq!((message_count, user_count), (0, 0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_atomic_batch_with_cursor() {
+    let db = TestDatabase::start()
+        .await
+        .with_commit_mode(BatchCommitMode::AtomicWithCursor);
+    let creator = address("a11ce");
+    let trader = address("b0b");
+    let trade = TradeFixture {
+        trade_obj_addr: &address("7001"),
+        trader: &trader,
+        trade_type: 1,
+        amount_from: 1_000,
+        amount_to: 2_000,
+        price: 500,
+        status: 0,
+        creation_timestamp: 100,
+        last_update_timestamp: 100,
+        notes: "",
+    };
+    db.process(vec![
+        user_transaction(
+            50,
+            100,
+            vec![message_event(
+                "Create",
+                &address("1001"),
+                &creator,
+                "hello",
+                100,
+                100,
+            )],
+        ),
+        user_transaction(51, 100, vec![trade.event("Create")]),
+    ])
+    .await;
+    // A replayed batch doesn't move the cursor back
+    db.process(vec![user_transaction(45, 90, vec![])]).await;
+
+    let conn = &mut db.conn().await;
+    let counts = (
+        messages::table
+            .count()
+            .get_result::<i64>(conn)
+            .await
+            .unwrap(),
+        trades::table.count().get_result::<i64>(conn).await.unwrap(),
+    );
+    assert_eq!(counts, (1, 1));
+    let last_success_version = processor_status::table
+        .find(TRACKER_NAME)
+        .select(processor_status::last_success_version)
+        .first::<i64>(conn)
+        .await
+        .unwrap();
+    assert_eq!(last_success_version, 51);
+}
@@ -0,0 +1,277 @@
+//! Webhook delivery against a local HTTP stand-in, with the alerts, the outbox and the
+//! endpoint status in a throwaway Postgres. Ignored by default, it needs a Postgres
+//! installation.
+
+#[allow(dead_code)]
+mod common;
+
+use ahash::AHashMap;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use indexer::{
+    config::indexer_processor_config::WebhookSinkConfig,
+    db_models::{
+        alert::{Alert, ALERT_TYPE_LARGE_SWAP, SEVERITY_WARNING},
+        webhook_endpoint_status::WebhookEndpointStatus,
+        webhook_outbox::{
+            WebhookOutboxEntry, WEBHOOK_STATUS_DELIVERED, WEBHOOK_STATUS_FAILED,
+            WEBHOOK_STATUS_PENDING,
+        },
+    },
+    schema::{webhook_endpoint_status, webhook_outbox},
+    steps::storers::alert_storer::process_alerts,
+    utils::{
+        alert_sink::AlertSink,
+        query_retry::QueryRetryPolicy,
+        webhook::{WebhookDispatcher, WebhookSink},
+    },
+};
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use common::TestDatabase;
+
+const ENDPOINT: &str = "test";
+
+/// Local HTTP stand-in answering one request per status, in order.
+async fn serve(statuses: Vec<&'static str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        for status in statuses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_request(&mut socket).await;
+            socket
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                        status
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+    });
+    url
+}
+
+async fn read_request(socket: &mut TcpStream) {
+    let mut request = vec![];
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    line.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|value| value.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            if request.len() >= header_end + 4 + content_length {
+                return;
+            }
+        }
+        if n == 0 {
+            return;
+        }
+    }
+}
+
+fn webhook_config(url: String) -> WebhookSinkConfig {
+    WebhookSinkConfig {
+        name: ENDPOINT.to_string(),
+        url,
+        secret: "s3cret".to_string(),
+        alert_types: vec![],
+        events: vec![],
+        max_attempts: 2,
+        initial_backoff_ms: 60_000,
+        max_backoff_ms: WebhookSinkConfig::default_max_backoff_ms(),
+        timeout_ms: 5_000,
+        poll_interval_ms: WebhookSinkConfig::default_poll_interval_ms(),
+    }
+}
+
+fn alert(alert_id: &str) -> Alert {
+    Alert {
+        alert_id: alert_id.to_string(),
+        alert_type: ALERT_TYPE_LARGE_SWAP.to_string(),
+        severity: SEVERITY_WARNING.to_string(),
+        pool_address: "0x1".to_string(),
+        address: "0x2".to_string(),
+        tx_version: 1,
+        value: "100000".to_string(),
+        message: "Large swap".to_string(),
+        timestamp: 100,
+        rule_name: String::new(),
+    }
+}
+
+async fn outbox(db: &TestDatabase) -> Vec<WebhookOutboxEntry> {
+    webhook_outbox::table
+        .order(webhook_outbox::id)
+        .load::<WebhookOutboxEntry>(&mut db.conn().await)
+        .await
+        .unwrap()
+}
+
+async fn endpoint_status(db: &TestDatabase) -> WebhookEndpointStatus {
+    webhook_endpoint_status::table
+        .find(ENDPOINT)
+        .first::<WebhookEndpointStatus>(&mut db.conn().await)
+        .await
+        .unwrap()
+}
+
+/// Moves every row's next attempt to the past, as if the backoff had elapsed.
+async fn elapse_backoff(db: &TestDatabase) {
+    diesel::update(webhook_outbox::table)
+        .set(webhook_outbox::next_attempt_at.eq(0))
+        .execute(&mut db.conn().await)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_dispatcher_retries_with_backoff_and_tracks_endpoint() {
+    let db = TestDatabase::start().await;
+    let url = serve(vec![
+        "500 Internal Server Error",
+        "500 Internal Server Error",
+        "200 OK",
+    ])
+    .await;
+    let config = webhook_config(url);
+    let sinks: Vec<Arc<dyn AlertSink>> =
+        vec![Arc::new(WebhookSink::new(db.pool.clone(), config.clone()))];
+    let dispatcher = WebhookDispatcher::new(db.pool.clone(), config).unwrap();
+    dispatcher.register_endpoint().await.unwrap();
+
+    // The outbox row is stored with the alert, and re-processing it enqueues nothing
+    for expected_new in [1, 0] {
+        let new_alerts = process_alerts(
+            db.pool.clone(),
+            QueryRetryPolicy::default(),
+            AHashMap::new(),
+            vec![alert("large_swap-1")],
+            sinks.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(new_alerts.len(), expected_new);
+    }
+    assert_eq!(outbox(&db).await.len(), 1);
+
+    // A failure schedules the retry initial_backoff_ms later
+    let before = chrono::Utc::now().timestamp();
+    assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+    let after = chrono::Utc::now().timestamp();
+    let entry = &outbox(&db).await[0];
+    assert_eq!(
+        (entry.status.as_str(), entry.attempts),
+        (WEBHOOK_STATUS_PENDING, 1)
+    );
+    assert!((before + 60..=after + 60).contains(&entry.next_attempt_at));
+    assert!(entry.last_error.is_some());
+    let status = endpoint_status(&db).await;
+    assert_eq!(
+        (
+            status.delivered_count,
+            status.failed_count,
+            status.consecutive_failures
+        ),
+        (0, 0, 1)
+    );
+    assert!(status.last_failure_at.is_some());
+    assert_eq!(dispatcher.dispatch_due().await.unwrap(), 0);
+
+    // The second failure reaches max_attempts, the row is given up on for good
+    elapse_backoff(&db).await;
+    assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+    let entry = &outbox(&db).await[0];
+    assert_eq!(
+        (entry.status.as_str(), entry.attempts),
+        (WEBHOOK_STATUS_FAILED, 2)
+    );
+    let status = endpoint_status(&db).await;
+    assert_eq!(
+        (
+            status.delivered_count,
+            status.failed_count,
+            status.consecutive_failures
+        ),
+        (0, 1, 2)
+    );
+    elapse_backoff(&db).await;
+    assert_eq!(dispatcher.dispatch_due().await.unwrap(), 0);
+
+    // A delivery resets the consecutive failures
+    process_alerts(
+        db.pool.clone(),
+        QueryRetryPolicy::default(),
+        AHashMap::new(),
+        vec![alert("large_swap-2")],
+        sinks,
+    )
+    .await
+    .unwrap();
+    assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+    let entry = &outbox(&db).await[1];
+    assert_eq!(
+        (entry.status.as_str(), entry.attempts),
+        (WEBHOOK_STATUS_DELIVERED, 1)
+    );
+    assert!(entry.delivered_at.is_some());
+    assert_eq!(entry.last_error, None);
+    let status = endpoint_status(&db).await;
+    assert_eq!(
+        (
+            status.delivered_count,
+            status.failed_count,
+            status.consecutive_failures
+        ),
+        (1, 1, 0)
+    );
+    assert!(status.last_success_at.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Postgres, run with --ignored"]
+async fn test_concurrent_dispatchers_deliver_each_row_once() {
+    let db = TestDatabase::start().await;
+    // The stand-in answers a single request, a second POST would time out and fail the row
+    let config = webhook_config(serve(vec!["200 OK"]).await);
+    let sinks: Vec<Arc<dyn AlertSink>> =
+        vec![Arc::new(WebhookSink::new(db.pool.clone(), config.clone()))];
+    let first = WebhookDispatcher::new(db.pool.clone(), config.clone()).unwrap();
+    let second = WebhookDispatcher::new(db.pool.clone(), config).unwrap();
+    first.register_endpoint().await.unwrap();
+    process_alerts(
+        db.pool.clone(),
+        QueryRetryPolicy::default(),
+        AHashMap::new(),
+        vec![alert("large_swap-1")],
+        sinks,
+    )
+    .await
+    .unwrap();
+
+    let (first_attempted, second_attempted) =
+        tokio::join!(first.dispatch_due(), second.dispatch_due());
+    assert_eq!(first_attempted.unwrap() + second_attempted.unwrap(), 1);
+    let entry = &outbox(&db).await[0];
+    assert_eq!(
+        (entry.status.as_str(), entry.attempts),
+        (WEBHOOK_STATUS_DELIVERED, 1)
+    );
+    assert_eq!(endpoint_status(&db).await.delivered_count, 1);
+}